@@ -17,17 +17,25 @@ import androidx.compose.foundation.shape.RoundedCornerShape
 import androidx.compose.material3.Scaffold
 import androidx.compose.material3.Text
 import androidx.compose.runtime.Composable
+import androidx.compose.runtime.getValue
+import androidx.compose.runtime.mutableStateOf
+import androidx.compose.runtime.remember
+import androidx.compose.runtime.setValue
 import androidx.compose.ui.Modifier
 import androidx.compose.ui.draw.clip
 import androidx.compose.ui.tooling.preview.Preview
 import androidx.compose.ui.unit.dp
 import androidx.compose.ui.viewinterop.AndroidView
+import com.foxhunter.egui_view.ui.EguiMessage
 import com.foxhunter.egui_view.ui.NativeGLSurfaceView
 import com.foxhunter.egui_view.ui.theme.MyApplicationTheme
 
 class MainActivity : ComponentActivity() {
     override fun onCreate(savedInstanceState: Bundle?) {
         super.onCreate(savedInstanceState)
+        // Read when the library loads, the first time a NativeGLSurfaceView is used
+        System.setProperty("egui_view.log_tag", "com.foxhunter.egui_view")
+        System.setProperty("egui_view.log_filters", "debug")
         enableEdgeToEdge()
         setContent {
             MyApplicationTheme {
@@ -44,8 +52,10 @@ class MainActivity : ComponentActivity() {
 
 @Composable
 fun Greeting(name: String, modifier: Modifier = Modifier) {
+    // Sent by the demo app whenever its color picker changes
+    var clearColor by remember { mutableStateOf<String?>(null) }
     Column(modifier = modifier) {
-        Text(text = "Hello $name!")
+        Text(text = clearColor?.let { "egui picked $it" } ?: "Hello $name!")
         Box(modifier = Modifier
             .animateContentSize(
                 animationSpec = tween(
@@ -56,7 +66,13 @@ fun Greeting(name: String, modifier: Modifier = Modifier) {
         ) {
             AndroidView(
                 factory = { context ->
-                    NativeGLSurfaceView(context)
+                    NativeGLSurfaceView(context).apply {
+                        messageListener = { message ->
+                            if (message is EguiMessage.Json && message.topic == "clear_color") {
+                                clearColor = message.json
+                            }
+                        }
+                    }
                 },
                 modifier = Modifier.fillMaxSize()
             )
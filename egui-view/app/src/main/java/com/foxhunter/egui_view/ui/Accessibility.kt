@@ -0,0 +1,197 @@
+package com.foxhunter.egui_view.ui
+
+import android.graphics.Rect
+import android.os.Bundle
+import android.view.MotionEvent
+import android.view.accessibility.AccessibilityEvent
+import android.view.accessibility.AccessibilityNodeInfo
+import android.view.accessibility.AccessibilityNodeInfo.AccessibilityAction
+import android.view.accessibility.AccessibilityNodeProvider
+
+/** No node, as returned by `findAccessibilityNodeAt0` */
+internal const val INVALID_ID = Int.MIN_VALUE
+
+/**
+ * One egui widget, as built by `accessibility_node_to_java` in Rust. Bounds are in view pixels.
+ */
+class AccessibilityNode(
+    /** [INVALID_ID] for the root, which is the view itself */
+    val parent: Int,
+    val children: IntArray,
+    /** The Android widget this behaves like */
+    val className: String,
+    val label: String?,
+    /** Text of text fields, or a formatted numeric value */
+    val value: String?,
+    val left: Int,
+    val top: Int,
+    val right: Int,
+    val bottom: Int,
+    /** Must match `accessibility::flags` in Rust */
+    private val flags: Int,
+    /** `AccessibilityNodeInfo.ACTION_*` ids the widget handles */
+    val actions: IntArray,
+    /** Current, min and max of sliders, or null */
+    val range: FloatArray?,
+) {
+    val isEnabled get() = flags and 1 != 0
+    val isFocused get() = flags and 2 != 0
+    val isCheckable get() = flags and 4 != 0
+    val isChecked get() = flags and 8 != 0
+    val isEditable get() = flags and 16 != 0
+}
+
+/**
+ * Exposes the egui widgets of a [NativeGLSurfaceView] to TalkBack and UI Automator as virtual
+ * views. The view itself stands for egui's root node.
+ */
+internal class EguiAccessibilityProvider(private val view: NativeGLSurfaceView) :
+    AccessibilityNodeProvider() {
+    /** The node TalkBack has selected */
+    private var accessibilityFocus = INVALID_ID
+
+    /** The node under the finger while exploring by touch */
+    private var hovered = INVALID_ID
+
+    override fun createAccessibilityNodeInfo(virtualViewId: Int): AccessibilityNodeInfo? {
+        val node = view.accessibilityNode(virtualViewId)
+        if (virtualViewId == AccessibilityNodeProvider.HOST_VIEW_ID) {
+            val info = AccessibilityNodeInfo(view)
+            view.onInitializeAccessibilityNodeInfo(info)
+            node?.children?.forEach { info.addChild(view, it) }
+            return info
+        }
+        node ?: return null
+
+        val info = AccessibilityNodeInfo(view, virtualViewId)
+        info.packageName = view.context.packageName
+        info.className = node.className
+        if (node.parent == AccessibilityNodeProvider.HOST_VIEW_ID) {
+            info.setParent(view)
+        } else {
+            info.setParent(view, node.parent)
+        }
+        node.children.forEach { info.addChild(view, it) }
+
+        if (node.isEditable) {
+            info.text = node.value
+            info.hintText = node.label
+        } else {
+            info.text = node.label
+            info.stateDescription = node.value
+        }
+        node.range?.let { (current, min, max) ->
+            info.rangeInfo = AccessibilityNodeInfo.RangeInfo(
+                AccessibilityNodeInfo.RangeInfo.RANGE_TYPE_FLOAT, min, max, current
+            )
+        }
+
+        val bounds = Rect(node.left, node.top, node.right, node.bottom)
+        info.isVisibleToUser = bounds.intersect(0, 0, view.width, view.height)
+        val location = IntArray(2)
+        view.getLocationOnScreen(location)
+        bounds.offset(location[0], location[1])
+        info.setBoundsInScreen(bounds)
+
+        info.isEnabled = node.isEnabled
+        info.isFocused = node.isFocused
+        info.isCheckable = node.isCheckable
+        info.isChecked = node.isChecked
+        info.isEditable = node.isEditable
+        for (action in node.actions) {
+            info.addAction(AccessibilityAction(action, null))
+            when (action) {
+                AccessibilityNodeInfo.ACTION_CLICK -> info.isClickable = true
+                AccessibilityNodeInfo.ACTION_FOCUS -> info.isFocusable = true
+            }
+        }
+
+        info.isAccessibilityFocused = virtualViewId == accessibilityFocus
+        info.addAction(
+            if (info.isAccessibilityFocused) {
+                AccessibilityAction.ACTION_CLEAR_ACCESSIBILITY_FOCUS
+            } else {
+                AccessibilityAction.ACTION_ACCESSIBILITY_FOCUS
+            }
+        )
+        return info
+    }
+
+    override fun performAction(virtualViewId: Int, action: Int, arguments: Bundle?): Boolean {
+        if (virtualViewId == AccessibilityNodeProvider.HOST_VIEW_ID) {
+            return view.performAccessibilityAction(action, arguments)
+        }
+        return when (action) {
+            AccessibilityNodeInfo.ACTION_ACCESSIBILITY_FOCUS -> {
+                if (accessibilityFocus == virtualViewId) {
+                    return false
+                }
+                val previous = accessibilityFocus
+                accessibilityFocus = virtualViewId
+                if (previous != INVALID_ID) {
+                    sendEvent(previous, AccessibilityEvent.TYPE_VIEW_ACCESSIBILITY_FOCUS_CLEARED)
+                }
+                sendEvent(virtualViewId, AccessibilityEvent.TYPE_VIEW_ACCESSIBILITY_FOCUSED)
+                true
+            }
+            AccessibilityNodeInfo.ACTION_CLEAR_ACCESSIBILITY_FOCUS -> {
+                if (accessibilityFocus != virtualViewId) {
+                    return false
+                }
+                accessibilityFocus = INVALID_ID
+                sendEvent(virtualViewId, AccessibilityEvent.TYPE_VIEW_ACCESSIBILITY_FOCUS_CLEARED)
+                true
+            }
+            else -> {
+                val argument = arguments?.getFloat(
+                    AccessibilityNodeInfo.ACTION_ARGUMENT_PROGRESS_VALUE, Float.NaN
+                ) ?: Float.NaN
+                val handled = view.performEguiAccessibilityAction(virtualViewId, action, argument)
+                if (handled && action == AccessibilityNodeInfo.ACTION_CLICK) {
+                    sendEvent(virtualViewId, AccessibilityEvent.TYPE_VIEW_CLICKED)
+                }
+                handled
+            }
+        }
+    }
+
+    /** Tracks the node under the finger while exploring by touch. Returns whether one is */
+    fun onHover(event: MotionEvent): Boolean {
+        val id = when (event.actionMasked) {
+            MotionEvent.ACTION_HOVER_ENTER, MotionEvent.ACTION_HOVER_MOVE ->
+                view.accessibilityNodeAt(event.x, event.y)
+            MotionEvent.ACTION_HOVER_EXIT -> INVALID_ID
+            else -> return false
+        }
+        if (id != hovered) {
+            val previous = hovered
+            hovered = id
+            if (id != INVALID_ID) {
+                sendEvent(id, AccessibilityEvent.TYPE_VIEW_HOVER_ENTER)
+            }
+            if (previous != INVALID_ID) {
+                sendEvent(previous, AccessibilityEvent.TYPE_VIEW_HOVER_EXIT)
+            }
+        }
+        return id != INVALID_ID
+    }
+
+    /** The egui tree changed in the last frame */
+    fun onTreeChanged() {
+        val event = AccessibilityEvent(AccessibilityEvent.TYPE_WINDOW_CONTENT_CHANGED)
+        event.contentChangeTypes = AccessibilityEvent.CONTENT_CHANGE_TYPE_SUBTREE
+        event.setSource(view)
+        view.parent?.requestSendAccessibilityEvent(view, event)
+    }
+
+    private fun sendEvent(virtualViewId: Int, eventType: Int) {
+        val event = AccessibilityEvent(eventType)
+        event.packageName = view.context.packageName
+        event.setSource(view, virtualViewId)
+        view.accessibilityNode(virtualViewId)?.let {
+            event.className = it.className
+            it.label?.let { label -> event.text.add(label) }
+        }
+        view.parent?.requestSendAccessibilityEvent(view, event)
+    }
+}
@@ -0,0 +1,14 @@
+package com.foxhunter.egui_view.ui
+
+/**
+ * A message between Kotlin and the hosted egui app, matching `messages::Message` in Rust. The
+ * topic says what it's about; the app and host agree on those.
+ */
+sealed class EguiMessage {
+    abstract val topic: String
+
+    /** JSON text, which neither side of the bridge parses */
+    data class Json(override val topic: String, val json: String) : EguiMessage()
+
+    class Bytes(override val topic: String, val bytes: ByteArray) : EguiMessage()
+}
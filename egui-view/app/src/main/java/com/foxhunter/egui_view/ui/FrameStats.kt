@@ -0,0 +1,43 @@
+package com.foxhunter.egui_view.ui
+
+/** Timings (in microseconds) and sizes of one frame rendered by a [NativeGLSurfaceView] */
+data class FrameStats(
+    /** Turning input into egui's `RawInput` */
+    val inputUs: Long,
+    /** The app's UI code */
+    val runUs: Long,
+    val tessellateUs: Long,
+    /** Uploading and freeing textures */
+    val texturesUs: Long,
+    /** Clearing and submitting draw calls */
+    val paintUs: Long,
+    val totalUs: Long,
+    val primitives: Long,
+    val vertices: Long,
+    val indices: Long,
+    /** Estimated GPU memory used by egui's textures */
+    val textureBytes: Long,
+) {
+    companion object {
+        /** Must match `FrameStats::PACKED_LEN` in Rust */
+        private const val PACKED_LEN = 10
+
+        /** Unpacks the records returned by `getFrameStats0`, oldest first */
+        fun fromPacked(packed: LongArray): List<FrameStats> =
+            (0 until packed.size / PACKED_LEN).map { i ->
+                val p = i * PACKED_LEN
+                FrameStats(
+                    inputUs = packed[p],
+                    runUs = packed[p + 1],
+                    tessellateUs = packed[p + 2],
+                    texturesUs = packed[p + 3],
+                    paintUs = packed[p + 4],
+                    totalUs = packed[p + 5],
+                    primitives = packed[p + 6],
+                    vertices = packed[p + 7],
+                    indices = packed[p + 8],
+                    textureBytes = packed[p + 9],
+                )
+            }
+    }
+}
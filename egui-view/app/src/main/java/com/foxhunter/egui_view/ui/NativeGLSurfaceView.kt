@@ -1,10 +1,40 @@
 package com.foxhunter.egui_view.ui
 
+import android.app.Activity
+import android.content.ActivityNotFoundException
+import android.content.ClipData
+import android.content.ClipboardManager
 import android.content.Context
+import android.content.ContextWrapper
+import android.content.Intent
+import android.content.res.Configuration
+import android.graphics.Matrix
+import android.net.Uri
+import android.os.Bundle
+import android.os.Parcelable
 import android.opengl.GLSurfaceView
+import android.text.InputType
 import android.util.Log
+import android.view.KeyEvent
 import android.view.MotionEvent
+import android.view.PointerIcon
 import android.view.SurfaceHolder
+import android.view.Window
+import android.view.accessibility.AccessibilityManager
+import android.view.accessibility.AccessibilityNodeProvider
+import android.view.inputmethod.CursorAnchorInfo
+import android.view.inputmethod.BaseInputConnection
+import android.view.inputmethod.EditorInfo
+import android.view.inputmethod.InputConnection
+import android.view.inputmethod.InputMethodManager
+import androidx.core.graphics.Insets
+import androidx.core.os.BundleCompat
+import androidx.core.view.ViewCompat
+import androidx.core.view.WindowCompat
+import androidx.core.view.WindowInsetsCompat
+import androidx.core.view.WindowInsetsControllerCompat
+import java.io.File
+import java.util.concurrent.ConcurrentHashMap
 import javax.microedition.khronos.egl.EGL10
 import javax.microedition.khronos.egl.EGLConfig
 import javax.microedition.khronos.egl.EGLContext
@@ -14,12 +44,104 @@ import javax.microedition.khronos.opengles.GL10
 private const val EGL_CONTEXT_CLIENT_VERSION = 0x3098
 private const val glVersion = 3.0
 
-class NativeGLSurfaceView(context: Context?) : GLSurfaceView(context) {
+/**
+ * Hosts an egui app implemented in Rust, or one of its child viewports.
+ */
+class NativeGLSurfaceView private constructor(
+    context: Context?,
+    appId: String,
+    /** The view whose app opened the viewport this one shows, or null for a top-level view */
+    private val viewportParent: NativeGLSurfaceView?,
+    private val viewportKey: Long
+) : GLSurfaceView(context) {
+    /**
+     * @param appId the id the app was registered under with `native_gl_surface::app::register_app`
+     */
+    constructor(context: Context?, appId: String = DEMO_APP_ID) : this(context, appId, null, 0)
+
+    /** Shows the viewport `viewportKey` of `parent`'s app */
+    internal constructor(context: Context, parent: NativeGLSurfaceView, viewportKey: Long) :
+        this(context, "", parent, viewportKey)
+
     private val renderer: NativeGLRenderer
 
+    /**
+     * Handle to the Rust side, or 0 once destroyed. Rust validates handles, so using a stale one
+     * from another thread is a no-op rather than a crash.
+     */
+    @Volatile
     var nativeSurface: Long
 
+    /**
+     * Where the app and egui state are kept so they survive process death, or null to only keep
+     * them in the view's instance state. Instance state needs the view to have an id.
+     */
+    var stateFile: File? = context
+        ?.takeIf { viewportParent == null }
+        ?.let { File(it.filesDir, "egui-$appId.state") }
+
+    /**
+     * Whether egui viewports may open in dialogs. If not, apps show them as windows inside this
+     * view. Checked each time a viewport opens.
+     */
+    @Volatile
+    var viewportDialogsEnabled: Boolean = true
+
+    /**
+     * Called on the UI thread when the renderer can't start on this view's GL context, with one of
+     * the [RendererError] codes and a description. The view stays blank until [retryRenderer] or a
+     * new context, so hosts may want to show a fallback instead.
+     */
+    @Volatile
+    var rendererErrorListener: ((code: Int, message: String) -> Unit)? = null
+
+    /** Messages from the app that arrived while there was no [messageListener]. UI thread only. */
+    private val pendingMessages = ArrayDeque<EguiMessage>()
+
+    /**
+     * Called on the UI thread with each message the app sends with `messages::send`. Messages
+     * sent while there is no listener are kept for the next one. Set on the UI thread.
+     */
+    var messageListener: ((EguiMessage) -> Unit)? = null
+        set(value) {
+            field = value
+            if (value != null) {
+                while (pendingMessages.isNotEmpty()) {
+                    value(pendingMessages.removeFirst())
+                }
+            }
+        }
+
+    /** Dialogs showing child viewports, by viewport key */
+    private val viewportDialogs = ConcurrentHashMap<Long, ViewportDialog>()
+
+    /**
+     * Captured whenever the GL surface or the native surface goes away. The native surface is
+     * destroyed on detach, which may come before `onSaveInstanceState`.
+     */
+    private var lastSavedState: ByteArray? = null
+
+    /** `EditorInfo.inputType` requested by the focused egui text field */
+    private var textInputType: Int = InputType.TYPE_CLASS_TEXT
+
+    private val accessibilityProvider = EguiAccessibilityProvider(this)
+
+    private val accessibilityManager =
+        context?.getSystemService(Context.ACCESSIBILITY_SERVICE) as AccessibilityManager?
+
+    /** Asks egui for its widget tree only while a service like TalkBack is listening */
+    private val accessibilityListener = AccessibilityManager.AccessibilityStateChangeListener {
+        enabled -> forwardInput { handle -> setAccessibilityEnabled0(handle, enabled) }
+    }
+
+    /** Cleared by the app with `ViewportCommand::IMEAllowed(false)` to keep the keyboard away */
+    @Volatile
+    private var imeAllowed: Boolean = true
+
     init {
+        isFocusable = true
+        isFocusableInTouchMode = true
+
         setEGLContextFactory(object: EGLContextFactory {
             override fun createContext(egl: EGL10?, display: EGLDisplay?, eglConfig: EGLConfig?): EGLContext {
                 Log.i("egui_view", "createContext: egl: $egl, display: $display, eglConfig: $eglConfig")
@@ -41,61 +163,902 @@ class NativeGLSurfaceView(context: Context?) : GLSurfaceView(context) {
         renderer = NativeGLRenderer(this)
 
         setRenderer(renderer)
+        // Rust tells us when it needs another frame, see `requestRenderAfter`
+        renderMode = RENDERMODE_WHEN_DIRTY
+
+        if (!crashReportDirChosen && context != null) {
+            crashReportDir = File(context.filesDir, "egui-crashes")
+        }
+
+        nativeSurface = createNativeSurface()
+        stateFile?.let { restoreStateFromFile0(nativeSurface, it.path) }
+
+        ViewCompat.setOnApplyWindowInsetsListener(this) { _, insets ->
+            forwardWindowInsets(insets)
+            insets
+        }
+    }
+
+    private fun createNativeSurface(): Long {
+        val config = resources.configuration
+        val handle = if (viewportParent == null) {
+            createNativeSurface0(
+                this,
+                appId,
+                resources.displayMetrics.density,
+                config.fontScale,
+                config.isNightMode()
+            )
+        } else {
+            createViewportSurface0(
+                this,
+                viewportParent.nativeSurface,
+                viewportKey,
+                resources.displayMetrics.density,
+                config.fontScale,
+                config.isNightMode()
+            )
+        }
+        setAccessibilityEnabled0(handle, accessibilityManager?.isEnabled == true)
+        return handle
+    }
+
+    override fun onAttachedToWindow() {
+        super.onAttachedToWindow()
+        accessibilityManager?.addAccessibilityStateChangeListener(accessibilityListener)
+        // Attached again after being detached, e.g. moved to another parent
+        if (nativeSurface == 0L) {
+            val handle = createNativeSurface()
+            lastSavedState?.let { restoreState0(handle, it) }
+            nativeSurface = handle
+        }
+    }
+
+    override fun onDetachedFromWindow() {
+        accessibilityManager?.removeAccessibilityStateChangeListener(accessibilityListener)
+        // Stops the render thread, so no frame can be using the native surface any more
+        super.onDetachedFromWindow()
+
+        val handle = nativeSurface
+        nativeSurface = 0
+        if (handle != 0L) {
+            lastSavedState = saveState0(handle)
+            stateFile?.let { saveStateToFile0(handle, it.path) }
+        }
+        destroyNativeSurface0(handle)
+        // Their surfaces belong to the app that was just destroyed
+        viewportDialogs.values.forEach { it.dismiss() }
+        viewportDialogs.clear()
+    }
+
+    override fun getAccessibilityNodeProvider(): AccessibilityNodeProvider = accessibilityProvider
+
+    override fun dispatchHoverEvent(event: MotionEvent): Boolean {
+        if (accessibilityManager?.isTouchExplorationEnabled == true &&
+            accessibilityProvider.onHover(event)
+        ) {
+            return true
+        }
+        return super.dispatchHoverEvent(event)
+    }
 
-        nativeSurface = createNativeSurface0()
+    /** The egui widget `virtualId` as of the last frame, or null if it isn't shown */
+    internal fun accessibilityNode(virtualId: Int): AccessibilityNode? {
+        val handle = nativeSurface
+        return if (handle != 0L) getAccessibilityNode0(handle, virtualId) else null
+    }
+
+    /** The innermost egui widget at (`x`, `y`), or [INVALID_ID] */
+    internal fun accessibilityNodeAt(x: Float, y: Float): Int {
+        val handle = nativeSurface
+        return if (handle != 0L) findAccessibilityNodeAt0(handle, x, y) else INVALID_ID
+    }
+
+    /** Hands an accessibility action to egui. Returns whether the widget supports it */
+    internal fun performEguiAccessibilityAction(
+        virtualId: Int,
+        action: Int,
+        argument: Float
+    ): Boolean {
+        var handled = false
+        forwardInput { handle ->
+            handled = performAccessibilityAction0(handle, virtualId, action, argument)
+        }
+        return handled
+    }
+
+    /** Called by Rust from the render thread when egui's widget tree changed */
+    @Suppress("unused")
+    fun onAccessibilityTreeChanged() {
+        post { accessibilityProvider.onTreeChanged() }
+    }
+
+    override fun onSizeChanged(w: Int, h: Int, oldw: Int, oldh: Int) {
+        super.onSizeChanged(w, h, oldw, oldh)
+        // Insets are relative to the window, so how much of them covers us depends on our bounds
+        ViewCompat.requestApplyInsets(this)
+    }
+
+    /** Tells Rust how much of the view is covered by system bars, cutouts and the keyboard */
+    private fun forwardWindowInsets(insets: WindowInsetsCompat) {
+        val location = IntArray(2)
+        getLocationInWindow(location)
+        val spaceRight = rootView.width - location[0] - width
+        val spaceBottom = rootView.height - location[1] - height
+
+        // Layout must match `WindowInsets::from_packed`
+        val packed = IntArray(12)
+        fun pack(offset: Int, window: Insets) {
+            packed[offset] = maxOf(0, window.left - location[0])
+            packed[offset + 1] = maxOf(0, window.top - location[1])
+            packed[offset + 2] = maxOf(0, window.right - spaceRight)
+            packed[offset + 3] = maxOf(0, window.bottom - spaceBottom)
+        }
+        pack(0, insets.getInsets(WindowInsetsCompat.Type.systemBars()))
+        pack(4, insets.getInsets(WindowInsetsCompat.Type.displayCutout()))
+        pack(8, insets.getInsets(WindowInsetsCompat.Type.ime()))
+
+        forwardInput { handle ->
+            onWindowInsetsChanged0(handle, packed)
+        }
+    }
+
+    override fun onConfigurationChanged(newConfig: Configuration) {
+        super.onConfigurationChanged(newConfig)
+        forwardInput { handle ->
+            onConfigurationChanged0(
+                handle,
+                resources.displayMetrics.density,
+                newConfig.fontScale,
+                newConfig.isNightMode()
+            )
+        }
     }
 
     override fun surfaceDestroyed(holder: SurfaceHolder) {
         super.surfaceDestroyed(holder)
         Log.i("egui_view", "surfaceDestroyed: holder: $holder")
-        synchronized(this) {
-            destroyNativeSurface0(nativeSurface)
-            nativeSurface = 0
+        // The native surface outlives the GL surface: `onSurfaceCreated0` rebuilds its GL
+        // resources on the next one. Save now anyway, the process may not come back from here.
+        val handle = nativeSurface
+        if (handle != 0L) {
+            lastSavedState = saveState0(handle)
+            stateFile?.let { saveStateToFile0(handle, it.path) }
+        }
+    }
+
+    override fun onSaveInstanceState(): Parcelable {
+        val handle = nativeSurface
+        val state = if (handle != 0L) saveState0(handle) else lastSavedState
+        return Bundle().apply {
+            putParcelable(SUPER_STATE_KEY, super.onSaveInstanceState())
+            putByteArray(EGUI_STATE_KEY, state)
+        }
+    }
+
+    override fun onRestoreInstanceState(state: Parcelable?) {
+        if (state !is Bundle) {
+            super.onRestoreInstanceState(state)
+            return
+        }
+        super.onRestoreInstanceState(
+            BundleCompat.getParcelable(state, SUPER_STATE_KEY, Parcelable::class.java)
+        )
+        val eguiState = state.getByteArray(EGUI_STATE_KEY) ?: return
+        val handle = nativeSurface
+        if (handle != 0L) {
+            restoreState0(handle, eguiState)
         }
     }
 
     override fun onTouchEvent(event: MotionEvent?): Boolean {
-        Log.i("egui_view", "TOUCH EVENT: $event")
+        if (event == null) {
+            return false
+        }
+
+        // Flatten the event (including batched history) into arrays that are cheap to pass over JNI.
+        // `times` holds every historical timestamp followed by the event time, and `samples` holds
+        // [x, y, pressure] for each pointer at each of those times.
+        val pointerCount = event.pointerCount
+        val historySize = event.historySize
+        val pointerIds = IntArray(pointerCount) { event.getPointerId(it) }
+        val times = LongArray(historySize + 1)
+        val samples = FloatArray((historySize + 1) * pointerCount * 3)
+        for (h in 0 until historySize) {
+            times[h] = event.getHistoricalEventTime(h)
+            for (p in 0 until pointerCount) {
+                val base = (h * pointerCount + p) * 3
+                samples[base] = event.getHistoricalX(p, h)
+                samples[base + 1] = event.getHistoricalY(p, h)
+                samples[base + 2] = event.getHistoricalPressure(p, h)
+            }
+        }
+        times[historySize] = event.eventTime
+        for (p in 0 until pointerCount) {
+            val base = (historySize * pointerCount + p) * 3
+            samples[base] = event.getX(p)
+            samples[base + 1] = event.getY(p)
+            samples[base + 2] = event.getPressure(p)
+        }
+
+        forwardInput { handle ->
+            onTouchEvent0(
+                handle,
+                event.actionMasked,
+                event.actionIndex,
+                event.deviceId,
+                pointerIds,
+                times,
+                samples
+            )
+        }
         return true
     }
 
+    override fun onKeyDown(keyCode: Int, event: KeyEvent): Boolean {
+        if (!forwardKeyEvent(keyCode, event)) {
+            return super.onKeyDown(keyCode, event)
+        }
+        return true
+    }
+
+    override fun onKeyUp(keyCode: Int, event: KeyEvent): Boolean {
+        if (!forwardKeyEvent(keyCode, event)) {
+            return super.onKeyUp(keyCode, event)
+        }
+        return true
+    }
+
+    private fun forwardKeyEvent(keyCode: Int, event: KeyEvent): Boolean {
+        // Leave navigation and system keys to Android
+        if (keyCode == KeyEvent.KEYCODE_BACK || event.isSystem) {
+            return false
+        }
+        forwardInput { handle ->
+            onKeyEvent0(
+                handle,
+                event.action,
+                keyCode,
+                event.metaState,
+                event.getUnicodeChar(event.metaState),
+                event.repeatCount
+            )
+        }
+        return true
+    }
+
+    /** Hands input to Rust and wakes up the render thread to process it */
+    private inline fun forwardInput(block: (Long) -> Unit) {
+        val handle = nativeSurface
+        if (handle != 0L) {
+            block(handle)
+        }
+        requestRender()
+    }
+
+    /** Render at the display refresh rate instead of only when egui asks for it */
+    fun setContinuousRendering(continuous: Boolean) {
+        forwardInput { handle ->
+            setContinuousRendering0(handle, continuous)
+        }
+    }
+
+    /** Draw a graph of recent frame times on top of the app */
+    fun setStatsOverlay(visible: Boolean) {
+        forwardInput { handle ->
+            setStatsOverlay0(handle, visible)
+        }
+    }
+
+    /** Timings of the last few seconds of frames, oldest first */
+    fun frameStats(): List<FrameStats> {
+        val handle = nativeSurface
+        if (handle == 0L) {
+            return emptyList()
+        }
+        return FrameStats.fromPacked(getFrameStats0(handle) ?: return emptyList())
+    }
+
+    /** GL version, limits and extensions of the render context, or null before the first frame */
+    fun glDiagnostics(): String? {
+        val handle = nativeSurface
+        if (handle == 0L) {
+            return null
+        }
+        return getGlDiagnostics0(handle)
+    }
+
+    /**
+     * Paints what the view currently shows again at `width` x `height` pixels, scaled to fit,
+     * and hands the PNG to `callback` on the UI thread. Null if nothing has been drawn yet.
+     *
+     * The last frame is scaled as is: the layout doesn't reflow to another aspect ratio, and text
+     * gets blurry when scaled up.
+     */
+    fun capturePng(width: Int, height: Int, callback: (ByteArray?) -> Unit) {
+        // Needs the GL context, so it has to run on the render thread
+        queueEvent {
+            val handle = nativeSurface
+            val png = if (handle != 0L) renderToPng0(handle, width, height) else null
+            post { callback(png) }
+        }
+    }
+
+    /**
+     * Called by Rust on the render thread when the app shows a new viewport. Returns whether it
+     * gets a dialog; sizes are in dp, or negative to fill the screen.
+     */
+    fun openViewport(key: Long, title: String, widthDp: Float, heightDp: Float): Boolean {
+        if (!viewportDialogsEnabled || context.findActivity() == null) {
+            return false
+        }
+        post {
+            val dialog = ViewportDialog(this, key, title, widthDp, heightDp)
+            viewportDialogs.put(key, dialog)?.dismiss()
+            dialog.show()
+        }
+        return true
+    }
+
+    /** Called by Rust, from any thread, once the app stops showing a viewport */
+    fun closeViewport(key: Long) {
+        post { viewportDialogs.remove(key)?.dismiss() }
+    }
+
+    /** Called by Rust, from any thread, when a viewport has a new frame */
+    fun requestViewportRender(key: Long) {
+        viewportDialogs[key]?.view?.requestRender()
+    }
+
+    /** The user tried to dismiss the dialog of the viewport this view shows */
+    internal fun requestViewportClose() {
+        forwardInput { handle ->
+            onViewportCloseRequested0(handle)
+        }
+    }
+
+    /** The view showing viewport `key` of this view's app, if it is shown */
+    private fun viewportView(key: Long): NativeGLSurfaceView? =
+        if (key == ROOT_VIEWPORT_KEY) this else viewportDialogs[key]?.view
+
+    /** The window of viewport `key`: the activity's for the root, or its dialog's */
+    private fun viewportWindow(key: Long): Window? =
+        if (key == ROOT_VIEWPORT_KEY) {
+            context.findActivity()?.window
+        } else {
+            viewportDialogs[key]?.window
+        }
+
+    /** Called by Rust, from any thread, when the app sends `ViewportCommand::Close` */
+    @Suppress("unused")
+    fun finishActivity(): Boolean {
+        val activity = context.findActivity() ?: return false
+        post { activity.finish() }
+        return true
+    }
+
+    /** Called by Rust, from any thread: the title shown in recents, or a dialog's */
+    @Suppress("unused")
+    fun setViewportTitle(key: Long, title: String): Boolean {
+        if (key == ROOT_VIEWPORT_KEY) {
+            val activity = context.findActivity() ?: return false
+            post { activity.title = title }
+            return true
+        }
+        val dialog = viewportDialogs[key] ?: return false
+        post { dialog.setTitle(title.ifEmpty { null }) }
+        return true
+    }
+
+    /** Called by Rust, from any thread. Full screen is immersive mode, bars come back on a swipe */
+    @Suppress("unused")
+    fun setViewportFullscreen(key: Long, fullscreen: Boolean): Boolean {
+        val window = viewportWindow(key) ?: return false
+        post {
+            val controller = WindowCompat.getInsetsController(window, window.decorView)
+            if (fullscreen) {
+                controller.systemBarsBehavior =
+                    WindowInsetsControllerCompat.BEHAVIOR_SHOW_TRANSIENT_BARS_BY_SWIPE
+                controller.hide(WindowInsetsCompat.Type.systemBars())
+            } else {
+                controller.show(WindowInsetsCompat.Type.systemBars())
+            }
+        }
+        return true
+    }
+
+    /** Called by Rust, from any thread, when the app minimizes its root viewport */
+    @Suppress("unused")
+    fun moveTaskToBack(): Boolean {
+        val activity = context.findActivity() ?: return false
+        post { activity.moveTaskToBack(true) }
+        return true
+    }
+
+    /** Called by Rust, from any thread */
+    @Suppress("unused")
+    fun focusViewport(key: Long): Boolean {
+        val view = viewportView(key) ?: return false
+        post { view.requestFocus() }
+        return true
+    }
+
+    /** Called by Rust, from any thread. Hides the keyboard if it is up and no longer allowed */
+    @Suppress("unused")
+    fun setImeAllowed(key: Long, allowed: Boolean): Boolean {
+        val view = viewportView(key) ?: return false
+        view.imeAllowed = allowed
+        if (!allowed) {
+            view.hideSoftKeyboard()
+        }
+        return true
+    }
+
+    /**
+     * Called by Rust, from any thread, with where text is being edited in view pixels, so the
+     * IME can place its candidate popups next to it.
+     */
+    @Suppress("unused")
+    fun setImeRect(key: Long, left: Float, top: Float, bottom: Float): Boolean {
+        val view = viewportView(key) ?: return false
+        view.post {
+            val location = IntArray(2)
+            view.getLocationOnScreen(location)
+            val toScreen = Matrix().apply {
+                setTranslate(location[0].toFloat(), location[1].toFloat())
+            }
+            val info = CursorAnchorInfo.Builder()
+                .setMatrix(toScreen)
+                .setInsertionMarkerLocation(
+                    left,
+                    top,
+                    bottom,
+                    bottom,
+                    CursorAnchorInfo.FLAG_HAS_VISIBLE_REGION
+                )
+                .build()
+            val imm =
+                context.getSystemService(Context.INPUT_METHOD_SERVICE) as InputMethodManager
+            imm.updateCursorAnchorInfo(view, info)
+        }
+        return true
+    }
+
+    /** Tries to start the renderer again after [rendererErrorListener] was called */
+    fun retryRenderer() {
+        forwardInput { handle -> retryRenderer0(handle) }
+    }
+
+    /** Hands [message] to the app right before its next frame */
+    fun postMessage(message: EguiMessage) {
+        forwardInput { handle ->
+            when (message) {
+                is EguiMessage.Json -> postJsonMessage0(handle, message.topic, message.json)
+                is EguiMessage.Bytes -> postBytesMessage0(handle, message.topic, message.bytes)
+            }
+        }
+    }
+
+    /** Called by Rust, from any thread, for each message the app sends. One payload is null. */
+    @Suppress("unused")
+    fun onMessage(topic: String, json: String?, bytes: ByteArray?) {
+        val message = if (json != null) {
+            EguiMessage.Json(topic, json)
+        } else {
+            EguiMessage.Bytes(topic, bytes ?: ByteArray(0))
+        }
+        post {
+            messageListener?.invoke(message) ?: pendingMessages.addLast(message)
+        }
+    }
+
+    /** Called by Rust from the render thread when the renderer couldn't be created */
+    @Suppress("unused")
+    fun onRendererFailed(code: Int, message: String) {
+        Log.e("egui_view", "Renderer failed with code $code: $message")
+        post { rendererErrorListener?.invoke(code, message) }
+    }
+
+    private val pendingRender = Runnable { requestRender() }
+
+    /**
+     * Called by Rust, from any thread, when egui wants a new frame.
+     * `delayMs` is 0 for as soon as possible and -1 for no frame needed.
+     */
+    fun requestRenderAfter(delayMs: Long) {
+        when {
+            delayMs == 0L -> requestRender()
+            delayMs > 0L -> {
+                removeCallbacks(pendingRender)
+                postDelayed(pendingRender, delayMs)
+            }
+        }
+    }
+
+    internal fun commitText(text: String) {
+        forwardInput { handle ->
+            onCommitText0(handle, text)
+        }
+    }
+
+    internal fun setComposingText(text: String) {
+        forwardInput { handle ->
+            onComposingText0(handle, text)
+        }
+    }
+
+    internal fun finishComposingText() {
+        forwardInput { handle ->
+            onFinishComposingText0(handle)
+        }
+    }
+
+    override fun onCheckIsTextEditor(): Boolean = true
+
+    override fun onCreateInputConnection(outAttrs: EditorInfo): InputConnection {
+        outAttrs.inputType = textInputType
+        outAttrs.imeOptions = EditorInfo.IME_FLAG_NO_FULLSCREEN
+        return EguiInputConnection(this)
+    }
+
+    /** Called by Rust, possibly from the render thread */
+    @Suppress("unused")
+    fun showSoftKeyboard(inputType: Int) {
+        if (!imeAllowed) {
+            return
+        }
+        post {
+            val imm = context.getSystemService(Context.INPUT_METHOD_SERVICE) as InputMethodManager
+            val changed = textInputType != inputType
+            textInputType = inputType
+            requestFocus()
+            if (changed) {
+                imm.restartInput(this)
+            }
+            imm.showSoftInput(this, 0)
+        }
+    }
+
+    /** Called by Rust, possibly from the render thread */
+    @Suppress("unused")
+    fun hideSoftKeyboard() {
+        post {
+            val imm = context.getSystemService(Context.INPUT_METHOD_SERVICE) as InputMethodManager
+            imm.hideSoftInputFromWindow(windowToken, 0)
+        }
+    }
+
+    /** Called by Rust, possibly from the render thread */
+    @Suppress("unused")
+    fun setClipboardText(text: String) {
+        post {
+            val clipboard = context.getSystemService(Context.CLIPBOARD_SERVICE) as ClipboardManager
+            clipboard.setPrimaryClip(ClipData.newPlainText("egui", text))
+        }
+    }
+
+    /** Called by Rust from the render thread */
+    @Suppress("unused")
+    fun getClipboardText(): String? {
+        val clipboard = context.getSystemService(Context.CLIPBOARD_SERVICE) as ClipboardManager
+        val clip = clipboard.primaryClip ?: return null
+        if (clip.itemCount == 0) {
+            return null
+        }
+        return clip.getItemAt(0).coerceToText(context).toString()
+    }
+
+    /** Called by Rust, possibly from the render thread */
+    @Suppress("unused")
+    fun openUrl(url: String) {
+        post {
+            val intent = Intent(Intent.ACTION_VIEW, Uri.parse(url))
+            intent.addFlags(Intent.FLAG_ACTIVITY_NEW_TASK)
+            try {
+                context.startActivity(intent)
+            } catch (e: ActivityNotFoundException) {
+                Log.w("egui_view", "No activity to open $url", e)
+            }
+        }
+    }
+
+    /** Called by Rust, possibly from the render thread */
+    @Suppress("unused")
+    fun setPointerIconType(type: Int) {
+        post {
+            pointerIcon = PointerIcon.getSystemIcon(context, type)
+        }
+    }
+
+    /** Called by Rust, possibly from the render thread */
+    @Suppress("unused")
+    fun performHaptic(feedbackConstant: Int) {
+        post {
+            performHapticFeedback(feedbackConstant)
+        }
+    }
+
     companion object {
+        /** The demo app built into native_gl_surface */
+        const val DEMO_APP_ID = "demo"
+
+        /** Must match `viewport_key(ViewportId::ROOT)` in Rust */
+        private const val ROOT_VIEWPORT_KEY = -1L
+
+        private const val SUPER_STATE_KEY = "superState"
+        private const val EGUI_STATE_KEY = "eguiState"
+
+        /**
+         * Version of the native method table this class was written against. Must match
+         * `NATIVES_VERSION` in Rust; loading the library fails if it doesn't.
+         */
+        const val NATIVES_VERSION = 2
+
         init {
+            // The library registers its natives on whichever classes these name, so this file
+            // works with the prebuilt library from any package
+            System.setProperty("egui_view.surface_view_class", NativeGLSurfaceView::class.java.name)
+            System.setProperty("egui_view.renderer_class", NativeGLRenderer::class.java.name)
+            System.setProperty("egui_view.accessibility_node_class", AccessibilityNode::class.java.name)
             System.loadLibrary("native_gl_surface")
         }
 
+        /**
+         * Changes which Rust log records reach logcat and the in-app log console, with
+         * `RUST_LOG`-style filters like `"info,native_gl_surface::touch=trace"`. Returns false
+         * and keeps the current filters if [filters] doesn't parse.
+         */
         @JvmStatic
-        private external fun createNativeSurface0(): Long
+        fun setLogFilters(filters: String): Boolean = setLogFilters0(filters)
+
+        @JvmStatic
+        private external fun setLogFilters0(filters: String): Boolean
+
+        @Volatile
+        private var crashReportDirChosen = false
+
+        /**
+         * Where Rust panics are written as crash reports, or null to not write them. Unless set
+         * earlier, the first view picks `egui-crashes` in the app's files dir. Reports left by
+         * earlier runs are listed by [pendingCrashReports], and the demo app shows them in a dialog.
+         */
+        @JvmStatic
+        var crashReportDir: File? = null
+            set(value) {
+                field = value
+                crashReportDirChosen = true
+                setCrashReportDir0(value?.path)
+            }
+
+        /** The text of each crash report not deleted yet, oldest first */
+        @JvmStatic
+        fun pendingCrashReports(): Array<String> = getPendingCrashReports0() ?: emptyArray()
+
+        /** Deletes all crash reports. Returns false if some couldn't be deleted. */
+        @JvmStatic
+        fun clearCrashReports(): Boolean = clearCrashReports0()
+
+        @JvmStatic
+        private external fun setCrashReportDir0(dir: String?)
+
+        @JvmStatic
+        private external fun getPendingCrashReports0(): Array<String>?
+
+        @JvmStatic
+        private external fun clearCrashReports0(): Boolean
+
+        @JvmStatic
+        private external fun createNativeSurface0(
+            view: NativeGLSurfaceView,
+            appId: String,
+            density: Float,
+            fontScale: Float,
+            nightMode: Boolean
+        ): Long
+
+        @JvmStatic
+        private external fun createViewportSurface0(
+            view: NativeGLSurfaceView,
+            parent: Long,
+            viewportKey: Long,
+            density: Float,
+            fontScale: Float,
+            nightMode: Boolean
+        ): Long
 
         @JvmStatic
         private external fun destroyNativeSurface0(handle: Long)
+
+        @JvmStatic
+        private external fun onViewportCloseRequested0(handle: Long)
+
+        @JvmStatic
+        private external fun retryRenderer0(handle: Long)
+
+        @JvmStatic
+        private external fun postJsonMessage0(handle: Long, topic: String, json: String)
+
+        @JvmStatic
+        private external fun postBytesMessage0(handle: Long, topic: String, bytes: ByteArray)
+
+        @JvmStatic
+        private external fun saveState0(handle: Long): ByteArray?
+
+        @JvmStatic
+        private external fun restoreState0(handle: Long, state: ByteArray)
+
+        @JvmStatic
+        private external fun saveStateToFile0(handle: Long, path: String): Boolean
+
+        @JvmStatic
+        private external fun restoreStateFromFile0(handle: Long, path: String)
+
+        @JvmStatic
+        private external fun onTouchEvent0(
+            handle: Long,
+            action: Int,
+            actionIndex: Int,
+            deviceId: Int,
+            pointerIds: IntArray,
+            times: LongArray,
+            samples: FloatArray
+        )
+
+        @JvmStatic
+        private external fun onConfigurationChanged0(
+            handle: Long,
+            density: Float,
+            fontScale: Float,
+            nightMode: Boolean
+        )
+
+        @JvmStatic
+        private external fun onWindowInsetsChanged0(handle: Long, insets: IntArray)
+
+        @JvmStatic
+        private external fun setContinuousRendering0(handle: Long, continuous: Boolean)
+
+        @JvmStatic
+        private external fun setStatsOverlay0(handle: Long, visible: Boolean)
+
+        @JvmStatic
+        private external fun getFrameStats0(handle: Long): LongArray?
+
+        @JvmStatic
+        private external fun getGlDiagnostics0(handle: Long): String?
+
+        @JvmStatic
+        private external fun setAccessibilityEnabled0(handle: Long, enabled: Boolean)
+
+        @JvmStatic
+        private external fun getAccessibilityNode0(handle: Long, virtualId: Int): AccessibilityNode?
+
+        @JvmStatic
+        private external fun findAccessibilityNodeAt0(handle: Long, x: Float, y: Float): Int
+
+        @JvmStatic
+        private external fun performAccessibilityAction0(
+            handle: Long,
+            virtualId: Int,
+            action: Int,
+            argument: Float
+        ): Boolean
+
+        @JvmStatic
+        private external fun renderToPng0(handle: Long, width: Int, height: Int): ByteArray?
+
+        @JvmStatic
+        private external fun onKeyEvent0(
+            handle: Long,
+            action: Int,
+            keyCode: Int,
+            metaState: Int,
+            unicodeChar: Int,
+            repeatCount: Int
+        )
+
+        @JvmStatic
+        private external fun onCommitText0(handle: Long, text: String)
+
+        @JvmStatic
+        private external fun onComposingText0(handle: Long, text: String)
+
+        @JvmStatic
+        private external fun onFinishComposingText0(handle: Long)
+    }
+}
+
+private fun Configuration.isNightMode(): Boolean =
+    uiMode and Configuration.UI_MODE_NIGHT_MASK == Configuration.UI_MODE_NIGHT_YES
+
+/** Why the renderer couldn't start. Must match `RendererError::status_code` in Rust. */
+object RendererError {
+    /** No EGL context was current, or its GL functions couldn't be loaded */
+    const val NO_CONTEXT = 1
+
+    /** The GL context's shading language is too old for egui */
+    const val UNSUPPORTED_GLSL = 2
+
+    /** egui's shaders failed to compile or link; the message carries the log */
+    const val SHADER_COMPILE = 3
+
+    /** The app needs a GL extension the context doesn't have */
+    const val MISSING_EXTENSION = 4
+}
+
+/** Dialogs need an activity's window */
+private tailrec fun Context.findActivity(): Activity? = when (this) {
+    is Activity -> this
+    is ContextWrapper -> baseContext.findActivity()
+    else -> null
+}
+
+/** Forwards soft keyboard input to egui */
+private class EguiInputConnection(private val view: NativeGLSurfaceView) :
+    BaseInputConnection(view, false) {
+    override fun commitText(text: CharSequence?, newCursorPosition: Int): Boolean {
+        view.commitText(text?.toString() ?: "")
+        return true
+    }
+
+    override fun setComposingText(text: CharSequence?, newCursorPosition: Int): Boolean {
+        view.setComposingText(text?.toString() ?: "")
+        return true
+    }
+
+    override fun finishComposingText(): Boolean {
+        view.finishComposingText()
+        return true
+    }
+
+    override fun deleteSurroundingText(beforeLength: Int, afterLength: Int): Boolean {
+        // egui has no notion of surrounding text, so replay deletions as key presses
+        repeat(beforeLength) { sendKey(KeyEvent.KEYCODE_DEL) }
+        repeat(afterLength) { sendKey(KeyEvent.KEYCODE_FORWARD_DEL) }
+        return true
+    }
+
+    override fun performContextMenuAction(id: Int): Boolean {
+        when (id) {
+            android.R.id.copy -> sendKey(KeyEvent.KEYCODE_COPY)
+            android.R.id.cut -> sendKey(KeyEvent.KEYCODE_CUT)
+            android.R.id.paste -> sendKey(KeyEvent.KEYCODE_PASTE)
+            else -> return super.performContextMenuAction(id)
+        }
+        return true
+    }
+
+    private fun sendKey(keyCode: Int) {
+        sendKeyEvent(KeyEvent(KeyEvent.ACTION_DOWN, keyCode))
+        sendKeyEvent(KeyEvent(KeyEvent.ACTION_UP, keyCode))
     }
 }
 
 class NativeGLRenderer(private val glSurfaceView: NativeGLSurfaceView) : GLSurfaceView.Renderer {
     override fun onDrawFrame(gl: GL10) {
-        synchronized(this.glSurfaceView) {
+        val delayMs = try {
             onDrawFrame0(glSurfaceView.nativeSurface)
+        } catch (e: RuntimeException) {
+            // Thrown when Rust panics. The surface refuses any further frames, so stop asking.
+            Log.e("egui_view", "Native frame failed", e)
+            return
         }
+        glSurfaceView.requestRenderAfter(delayMs)
     }
 
     override fun onSurfaceCreated(gl: GL10?, conig: EGLConfig?) {
         Log.i("egui_view", "onSurfaceCreated")
-        synchronized(this.glSurfaceView) {
-            onSurfaceCreated0(glSurfaceView.nativeSurface)
-        }
+        onSurfaceCreated0(glSurfaceView.nativeSurface)
     }
 
     override fun onSurfaceChanged(gl: GL10, width: Int, height: Int) {
         Log.i("egui_view", "onSurfaceChanged: width: $width, height: $height")
-        synchronized(this.glSurfaceView) {
-            onSurfaceChanged0(glSurfaceView.nativeSurface, width, height)
-        }
+        onSurfaceChanged0(glSurfaceView.nativeSurface, width, height)
     }
 
     companion object {
         @JvmStatic
-        private external fun onDrawFrame0(nativeSurface: Long)
+        private external fun onDrawFrame0(nativeSurface: Long): Long
         @JvmStatic
         private external fun onSurfaceCreated0(nativeSurface: Long)
         @JvmStatic
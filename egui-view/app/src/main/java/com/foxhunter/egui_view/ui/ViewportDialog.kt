@@ -0,0 +1,37 @@
+package com.foxhunter.egui_view.ui
+
+import android.app.Dialog
+import android.view.KeyEvent
+import android.view.ViewGroup
+import kotlin.math.roundToInt
+
+/** Shows a child viewport of [parent]'s egui app */
+internal class ViewportDialog(
+    parent: NativeGLSurfaceView,
+    key: Long,
+    title: String,
+    widthDp: Float,
+    heightDp: Float
+) : Dialog(parent.context) {
+    val view = NativeGLSurfaceView(context, parent, key)
+
+    init {
+        setTitle(title.ifEmpty { null })
+        val density = context.resources.displayMetrics.density
+        fun px(dp: Float) =
+            if (dp > 0) (dp * density).roundToInt() else ViewGroup.LayoutParams.MATCH_PARENT
+        setContentView(view, ViewGroup.LayoutParams(px(widthDp), px(heightDp)))
+
+        // The app decides whether the viewport closes, and then tells us through `closeViewport`
+        setCanceledOnTouchOutside(false)
+        setOnKeyListener { _, keyCode, event ->
+            if (keyCode != KeyEvent.KEYCODE_BACK) {
+                return@setOnKeyListener false
+            }
+            if (event.action == KeyEvent.ACTION_UP) {
+                view.requestViewportClose()
+            }
+            true
+        }
+    }
+}
@@ -2,6 +2,8 @@ fn main() {
     if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "android" {
         println!("cargo:rustc-link-lib=EGL");
         println!("cargo:rustc-link-lib=GLESv3");
+        // ATrace, for frame profiling
+        println!("cargo:rustc-link-lib=android");
     }
 }
 
@@ -0,0 +1,334 @@
+//! Exposing egui's AccessKit tree to Android accessibility services.
+//!
+//! egui describes every widget as an [`accesskit::Node`] each frame. [`AccessibilityTree`] turns
+//! that into what an `AccessibilityNodeProvider` needs: small ids that stay the same while a
+//! widget is shown, bounds in surface pixels, an Android widget class and the actions a service
+//! may perform. [`AccessibilityTree::action_request`] turns those actions back into egui input.
+
+use std::collections::HashMap;
+
+use egui::accesskit::{
+    self, Action, ActionData, ActionRequest, Affine, NodeId, Role, Toggled, TreeUpdate,
+};
+
+/// `AccessibilityNodeProvider.HOST_VIEW_ID`, which stands for egui's root node.
+pub const HOST_VIEW_ID: i32 = -1;
+
+/// No node, as in `ExploreByTouchHelper.INVALID_ID`.
+pub const INVALID_ID: i32 = i32::MIN;
+
+/// Bits of [`AccessNode::packed_flags`]. Must match `AccessibilityNode` in Kotlin.
+pub mod flags {
+    pub const ENABLED: i32 = 1 << 0;
+    pub const FOCUSED: i32 = 1 << 1;
+    pub const CHECKABLE: i32 = 1 << 2;
+    pub const CHECKED: i32 = 1 << 3;
+    pub const EDITABLE: i32 = 1 << 4;
+}
+
+/// Android's accessibility actions that egui widgets can handle.
+pub mod android_action {
+    /// `AccessibilityNodeInfo.ACTION_FOCUS`
+    pub const FOCUS: i32 = 0x1;
+    /// `AccessibilityNodeInfo.ACTION_CLICK`
+    pub const CLICK: i32 = 0x10;
+    /// `AccessibilityNodeInfo.ACTION_SCROLL_FORWARD`, which steps sliders up
+    pub const SCROLL_FORWARD: i32 = 0x1000;
+    /// `AccessibilityNodeInfo.ACTION_SCROLL_BACKWARD`, which steps sliders down
+    pub const SCROLL_BACKWARD: i32 = 0x2000;
+    /// `android.R.id.accessibilityActionSetProgress`, with the value as argument
+    pub const SET_PROGRESS: i32 = 0x0102_003d;
+}
+
+/// The value of a slider or drag value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RangeInfo {
+    pub current: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// One widget, as an `AccessibilityNodeInfo` describes it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessNode {
+    /// [`HOST_VIEW_ID`] for the root
+    pub virtual_id: i32,
+    /// `None` for the root
+    pub parent: Option<i32>,
+    pub children: Vec<i32>,
+    pub role: Role,
+    pub label: Option<String>,
+    /// Text of text fields, or a formatted numeric value
+    pub value: Option<String>,
+    /// In surface pixels
+    pub bounds: Option<egui::Rect>,
+    pub enabled: bool,
+    pub focused: bool,
+    /// `Some` for checkboxes, radio buttons and other things that can be checked
+    pub checked: Option<bool>,
+    pub range: Option<RangeInfo>,
+    /// The [`android_action`]s the widget handles
+    pub actions: Vec<i32>,
+}
+
+impl AccessNode {
+    /// The Android widget this behaves like, so services describe it the way users expect.
+    pub fn class_name(&self) -> &'static str {
+        match self.role {
+            Role::Button | Role::Link => "android.widget.Button",
+            Role::CheckBox => "android.widget.CheckBox",
+            Role::RadioButton => "android.widget.RadioButton",
+            Role::Switch => "android.widget.Switch",
+            Role::Slider | Role::SpinButton => "android.widget.SeekBar",
+            Role::ProgressIndicator => "android.widget.ProgressBar",
+            Role::TextInput | Role::MultilineTextInput => "android.widget.EditText",
+            Role::ComboBox => "android.widget.Spinner",
+            Role::Label => "android.widget.TextView",
+            _ => "android.view.View",
+        }
+    }
+
+    pub fn is_clickable(&self) -> bool {
+        self.actions.contains(&android_action::CLICK)
+    }
+
+    pub fn is_focusable(&self) -> bool {
+        self.actions.contains(&android_action::FOCUS)
+    }
+
+    pub fn is_editable(&self) -> bool {
+        matches!(self.role, Role::TextInput | Role::MultilineTextInput)
+    }
+
+    /// The boolean properties, as [`flags`].
+    pub fn packed_flags(&self) -> i32 {
+        [
+            (self.enabled, flags::ENABLED),
+            (self.focused, flags::FOCUSED),
+            (self.checked.is_some(), flags::CHECKABLE),
+            (self.checked == Some(true), flags::CHECKED),
+            (self.is_editable(), flags::EDITABLE),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |packed, (_, flag)| packed | flag)
+    }
+}
+
+/// The latest tree egui produced, by virtual id.
+#[derive(Default)]
+pub struct AccessibilityTree {
+    /// As egui sent them, for partial updates
+    raw: HashMap<NodeId, accesskit::Node>,
+    root: Option<NodeId>,
+    nodes: HashMap<i32, AccessNode>,
+    /// Ids handed out so far, kept while the node is in the tree
+    virtual_ids: HashMap<NodeId, i32>,
+    next_virtual_id: i32,
+    focus: Option<i32>,
+}
+
+impl AccessibilityTree {
+    /// Applies one of egui's updates and returns whether anything a service could see changed.
+    pub fn update(&mut self, update: TreeUpdate) -> bool {
+        if let Some(tree) = update.tree {
+            self.raw.clear();
+            self.root = Some(tree.root);
+        }
+        self.raw.extend(update.nodes);
+
+        let old_nodes = std::mem::take(&mut self.nodes);
+        let old_focus = self.focus;
+        if let Some(root) = self.root {
+            self.translate(root, update.focus);
+        }
+        self.virtual_ids
+            .retain(|_, virtual_id| self.nodes.contains_key(virtual_id));
+        self.nodes != old_nodes || self.focus != old_focus
+    }
+
+    /// Forgets the tree, e.g. once no service needs it anymore.
+    pub fn clear(&mut self) {
+        *self = Self {
+            next_virtual_id: self.next_virtual_id,
+            ..Default::default()
+        };
+    }
+
+    pub fn node(&self, virtual_id: i32) -> Option<&AccessNode> {
+        self.nodes.get(&virtual_id)
+    }
+
+    /// Every node, in no particular order.
+    pub fn nodes(&self) -> impl Iterator<Item = &AccessNode> {
+        self.nodes.values()
+    }
+
+    /// The widget with keyboard focus, if any.
+    pub fn focus(&self) -> Option<i32> {
+        self.focus
+    }
+
+    /// The innermost node at `pos` in surface pixels, for explore-by-touch.
+    pub fn node_at(&self, pos: egui::Pos2) -> Option<i32> {
+        let mut found = None;
+        let mut candidates = vec![HOST_VIEW_ID];
+        while let Some(virtual_id) = candidates.pop() {
+            let Some(node) = self.nodes.get(&virtual_id) else {
+                continue;
+            };
+            let contains = node.bounds.is_none_or(|bounds| bounds.contains(pos));
+            if contains {
+                found = Some(virtual_id);
+                // Later children are drawn on top, so look at them first
+                candidates.clear();
+                candidates.extend(&node.children);
+            }
+        }
+        found
+    }
+
+    /// Turns an Android accessibility action on `virtual_id` into a request egui handles in its
+    /// next frame. `None` if the widget doesn't support the action.
+    pub fn action_request(
+        &self,
+        virtual_id: i32,
+        action: i32,
+        argument: Option<f64>,
+    ) -> Option<ActionRequest> {
+        let node = self.nodes.get(&virtual_id)?;
+        if !node.actions.contains(&action) {
+            return None;
+        }
+        let target = *self
+            .virtual_ids
+            .iter()
+            .find(|(_, id)| **id == virtual_id)?
+            .0;
+        let (action, data) = match action {
+            android_action::FOCUS => (Action::Focus, None),
+            android_action::CLICK => (Action::Default, None),
+            android_action::SCROLL_FORWARD => (Action::Increment, None),
+            android_action::SCROLL_BACKWARD => (Action::Decrement, None),
+            android_action::SET_PROGRESS => {
+                (Action::SetValue, Some(ActionData::NumericValue(argument?)))
+            }
+            _ => return None,
+        };
+        Some(ActionRequest {
+            action,
+            target,
+            data,
+        })
+    }
+
+    fn virtual_id(&mut self, id: NodeId) -> i32 {
+        if Some(id) == self.root {
+            return HOST_VIEW_ID;
+        }
+        *self.virtual_ids.entry(id).or_insert_with(|| {
+            let virtual_id = self.next_virtual_id;
+            self.next_virtual_id = self.next_virtual_id.wrapping_add(1).max(0);
+            virtual_id
+        })
+    }
+
+    /// Rebuilds [`Self::nodes`] from everything reachable from `root`.
+    fn translate(&mut self, root: NodeId, focus: NodeId) {
+        self.focus = None;
+        let mut pending = vec![(root, None, Affine::IDENTITY)];
+        while let Some((id, parent, parent_transform)) = pending.pop() {
+            let Some(raw) = self.raw.get(&id) else {
+                continue;
+            };
+            let transform = match raw.transform() {
+                Some(transform) => parent_transform * *transform,
+                None => parent_transform,
+            };
+            let children = raw.children().to_vec();
+            let node = translate_node(raw, transform, &self.raw);
+            let virtual_id = self.virtual_id(id);
+            // egui reports the root as focused when no widget is
+            let focused = id == focus && id != root;
+            if focused {
+                self.focus = Some(virtual_id);
+            }
+            let node = AccessNode {
+                virtual_id,
+                parent,
+                focused,
+                children: children
+                    .iter()
+                    .map(|&child| self.virtual_id(child))
+                    .collect(),
+                ..node
+            };
+            pending.extend(
+                children
+                    .into_iter()
+                    .map(|child| (child, Some(virtual_id), transform)),
+            );
+            self.nodes.insert(virtual_id, node);
+        }
+    }
+}
+
+/// Everything but the ids, which depend on the rest of the tree.
+fn translate_node(
+    raw: &accesskit::Node,
+    transform: Affine,
+    all: &HashMap<NodeId, accesskit::Node>,
+) -> AccessNode {
+    let mut actions = Vec::new();
+    if raw.supports_action(Action::Focus) {
+        actions.push(android_action::FOCUS);
+    }
+    if raw.default_action_verb().is_some() {
+        actions.push(android_action::CLICK);
+    }
+    if raw.supports_action(Action::Increment) {
+        actions.push(android_action::SCROLL_FORWARD);
+    }
+    if raw.supports_action(Action::Decrement) {
+        actions.push(android_action::SCROLL_BACKWARD);
+    }
+    if raw.supports_action(Action::SetValue) {
+        actions.push(android_action::SET_PROGRESS);
+    }
+
+    let bounds = raw.bounds().map(|bounds| {
+        let bounds = transform.transform_rect_bbox(bounds);
+        egui::Rect::from_min_max(
+            egui::pos2(bounds.x0 as f32, bounds.y0 as f32),
+            egui::pos2(bounds.x1 as f32, bounds.y1 as f32),
+        )
+    });
+    let range = raw.numeric_value().map(|current| RangeInfo {
+        current,
+        min: raw.min_numeric_value().unwrap_or(f64::NEG_INFINITY),
+        max: raw.max_numeric_value().unwrap_or(f64::INFINITY),
+    });
+    let value = raw
+        .value()
+        .map(str::to_owned)
+        .or_else(|| range.map(|range| range.current.to_string()));
+
+    AccessNode {
+        virtual_id: HOST_VIEW_ID,
+        parent: None,
+        children: Vec::new(),
+        role: raw.role(),
+        // Text fields are named by the label next to them
+        label: raw
+            .name()
+            .or_else(|| raw.labelled_by().iter().find_map(|id| all.get(id)?.name()))
+            .map(str::to_owned),
+        value,
+        bounds,
+        enabled: !raw.is_disabled(),
+        focused: false,
+        checked: raw.toggled().map(|toggled| toggled == Toggled::True),
+        range,
+        actions,
+    }
+}
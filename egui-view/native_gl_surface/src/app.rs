@@ -0,0 +1,89 @@
+//! The UIs a `NativeGLSurfaceView` can host, and the registry the Kotlin side picks them from.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use parking_lot::Mutex;
+
+use crate::{demo_app::AppState, messages::Message};
+
+/// The id of the built-in [`AppState`] demo, used when Kotlin doesn't ask for anything else.
+pub const DEMO_APP_ID: &str = "demo";
+
+/// An egui UI that can be embedded in a `NativeGLSurfaceView`.
+///
+/// This is the surface's equivalent of `eframe::App`. It must be [`Send`] because the view may be
+/// torn down on the UI thread while it otherwise lives on the GL render thread.
+pub trait SurfaceApp: Send {
+    /// Called once per frame to build the UI.
+    fn update(&mut self, ctx: &egui::Context);
+
+    /// The color the surface is cleared to before egui paints on top, in gamma-space RGBA like
+    /// [`egui::Color32::to_normalized_gamma_f32`]. It's written to the framebuffer as is.
+    ///
+    /// Only visible where egui doesn't draw anything, e.g. with a transparent `CentralPanel`.
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        visuals.panel_fill.to_normalized_gamma_f32()
+    }
+
+    /// GL extensions the app can't do without, e.g. for its own paint callbacks. The surface
+    /// reports an error instead of starting on a context that lacks any of them.
+    fn required_gl_extensions(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// Called before [`Self::update`] for each message the host posted since the last frame,
+    /// oldest first. Reply with [`crate::messages::send`].
+    fn on_message(&mut self, _ctx: &egui::Context, _message: Message) {}
+
+    /// Serializes whatever the app wants to survive the surface being recreated.
+    fn save(&mut self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores state produced by [`Self::save`].
+    fn load(&mut self, _data: &[u8]) {}
+}
+
+type AppFactory = Arc<dyn Fn() -> Box<dyn SurfaceApp> + Send + Sync>;
+
+static APPS: OnceLock<Mutex<HashMap<String, AppFactory>>> = OnceLock::new();
+
+fn apps() -> &'static Mutex<HashMap<String, AppFactory>> {
+    APPS.get_or_init(|| {
+        let mut apps: HashMap<String, AppFactory> = HashMap::new();
+        apps.insert(
+            DEMO_APP_ID.to_owned(),
+            Arc::new(|| Box::new(AppState::new())),
+        );
+        Mutex::new(apps)
+    })
+}
+
+/// Makes an app available to `NativeGLSurfaceView`s created with `appId = id`.
+///
+/// Call this before the first view using `id` is created. This library defines `JNI_OnLoad`, so
+/// an app linking it can't; call this from a native method of its own instead, which its Kotlin
+/// code calls once before creating any view. Registering an id twice replaces the earlier
+/// factory.
+pub fn register_app<A: SurfaceApp + 'static>(
+    id: impl Into<String>,
+    factory: impl Fn() -> A + Send + Sync + 'static,
+) {
+    apps()
+        .lock()
+        .insert(id.into(), Arc::new(move || Box::new(factory())));
+}
+
+pub fn is_registered(id: &str) -> bool {
+    apps().lock().contains_key(id)
+}
+
+/// Creates a fresh instance of the app registered under `id`.
+pub fn create_app(id: &str) -> Option<Box<dyn SurfaceApp>> {
+    // Not locked while the factory runs, it may register or create apps itself
+    let factory = apps().lock().get(id).cloned()?;
+    Some(factory())
+}
@@ -0,0 +1,96 @@
+//! Carrying out the [`ViewportCommand`]s apps send with [`egui::Context::send_viewport_cmd`].
+//!
+//! Commands an Android host can act on go to a [`ViewportCommandHost`]. The rest, and any the
+//! host turns down, are handed back to the app for one frame through [`unsupported_commands`],
+//! so it can e.g. hide a full screen button that doesn't work.
+//!
+//! [`ViewportCommand::Screenshot`] never reaches the host: the surface reads back the root
+//! viewport itself, and child viewports can't be captured.
+
+use egui::{Rect, UserAttentionType, ViewportCommand, ViewportId};
+
+/// The host side of viewport commands. Each method returns whether the host carried it out.
+pub trait ViewportCommandHost: Send {
+    /// The app wants to quit, e.g. by finishing the activity. Child viewports are closed the
+    /// same way as when the user dismisses them, so this is only called for the root.
+    fn close(&mut self, viewport: ViewportId) -> bool;
+
+    /// E.g. the task description shown in recents, or a dialog's title.
+    fn set_title(&mut self, viewport: ViewportId, title: &str) -> bool;
+
+    /// Immersive mode, with the system bars hidden.
+    fn set_fullscreen(&mut self, viewport: ViewportId, fullscreen: bool) -> bool;
+
+    /// Moves the task to the back. Android doesn't let apps bring themselves back, so hosts
+    /// usually refuse `false`.
+    fn set_minimized(&mut self, viewport: ViewportId, minimized: bool) -> bool;
+
+    fn focus(&mut self, viewport: ViewportId) -> bool;
+
+    fn request_user_attention(
+        &mut self,
+        viewport: ViewportId,
+        attention: UserAttentionType,
+    ) -> bool;
+
+    /// Whether text fields may bring up the soft keyboard.
+    fn set_ime_allowed(&mut self, viewport: ViewportId, allowed: bool) -> bool;
+
+    /// Where text is being edited, in physical pixels, so the IME can place its popups.
+    fn set_ime_rect(&mut self, viewport: ViewportId, rect_px: Rect) -> bool;
+}
+
+/// Passes `commands` for `viewport` to `host` and returns the ones that weren't carried out.
+pub fn dispatch(
+    viewport: ViewportId,
+    commands: Vec<ViewportCommand>,
+    pixels_per_point: f32,
+    host: &mut dyn ViewportCommandHost,
+) -> Vec<ViewportCommand> {
+    commands
+        .into_iter()
+        .filter(|command| !dispatch_one(viewport, command, pixels_per_point, host))
+        .collect()
+}
+
+fn dispatch_one(
+    viewport: ViewportId,
+    command: &ViewportCommand,
+    pixels_per_point: f32,
+    host: &mut dyn ViewportCommandHost,
+) -> bool {
+    match command {
+        ViewportCommand::Close => host.close(viewport),
+        // Nothing is closed behind the app's back, so there is never anything to cancel
+        ViewportCommand::CancelClose => true,
+        ViewportCommand::Title(title) => host.set_title(viewport, title),
+        ViewportCommand::Fullscreen(fullscreen) => host.set_fullscreen(viewport, *fullscreen),
+        ViewportCommand::Minimized(minimized) => host.set_minimized(viewport, *minimized),
+        ViewportCommand::Focus => host.focus(viewport),
+        ViewportCommand::RequestUserAttention(attention) => {
+            host.request_user_attention(viewport, *attention)
+        }
+        ViewportCommand::IMEAllowed(allowed) => host.set_ime_allowed(viewport, *allowed),
+        ViewportCommand::IMERect(rect) => host.set_ime_rect(viewport, *rect * pixels_per_point),
+        _ => false,
+    }
+}
+
+fn unsupported_id(viewport: ViewportId) -> egui::Id {
+    egui::Id::new("native_gl_surface::unsupported_commands").with(viewport)
+}
+
+/// Keeps what [`dispatch`] returned for `viewport` until its next frame.
+pub fn store_unsupported(
+    ctx: &egui::Context,
+    viewport: ViewportId,
+    commands: Vec<ViewportCommand>,
+) {
+    ctx.data_mut(|d| d.insert_temp(unsupported_id(viewport), commands));
+}
+
+/// The commands this viewport sent last frame that the host couldn't carry out.
+pub fn unsupported_commands(ctx: &egui::Context) -> Vec<ViewportCommand> {
+    ctx.data(|d| d.get_temp(unsupported_id(ctx.viewport_id())))
+        .unwrap_or_default()
+}
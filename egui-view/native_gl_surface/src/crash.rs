@@ -0,0 +1,368 @@
+//! Crash reports that survive the process.
+//!
+//! Once [`set_report_store`] has been given a directory, the hook from
+//! [`crate::panics::install_hook`] writes a report there for every panic: the message, a backtrace,
+//! what the last frame looked like and the most recent log lines. On the next start
+//! [`CrashReportStore::pending`] lists them, and [`CrashReportDialog`] shows them to the user.
+//!
+//! On Android the directory lives in the app's files dir; anywhere else any directory will do,
+//! which is how the tests use it.
+
+use std::{
+    backtrace::Backtrace,
+    cell::Cell,
+    fmt::Write as _,
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use egui::RichText;
+use log::{error, warn};
+use parking_lot::Mutex;
+
+use crate::{display::DisplayConfig, panics::PanicReport, persistence};
+
+/// How long the panic hook waits for a lock held elsewhere before leaving that part out. The
+/// panicking thread may be the one holding it.
+const LOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How many log lines go into a report
+const RECENT_LOG_LINES: usize = 50;
+
+const PREFIX: &str = "crash-";
+const EXTENSION: &str = ".txt";
+
+/// What the last frame drew into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameContext {
+    /// Size of the GL surface in physical pixels
+    pub size_px: [u32; 2],
+    pub display: DisplayConfig,
+    /// egui's [`egui::Context::cumulative_pass_nr`]
+    pub pass_nr: u64,
+}
+
+thread_local! {
+    /// Per thread, because every surface renders on a GL thread of its own: a panic there is
+    /// reported with that surface's frame, not whichever surface painted last.
+    static LAST_FRAME: Cell<Option<FrameContext>> = const { Cell::new(None) };
+}
+
+/// Remembers `frame` for the next crash report on this thread. Called at the start of every
+/// frame.
+pub fn record_frame(frame: FrameContext) {
+    LAST_FRAME.set(Some(frame));
+}
+
+/// The frame last passed to [`record_frame`] on this thread. `None` on threads that don't
+/// render, like the UI thread.
+pub fn last_frame() -> Option<FrameContext> {
+    LAST_FRAME.get()
+}
+
+/// Everything known about a panic at the time it happened.
+#[derive(Clone, Debug)]
+pub struct CrashReport {
+    pub panic: PanicReport,
+    pub thread: String,
+    pub time: SystemTime,
+    pub backtrace: String,
+    pub frame: Option<FrameContext>,
+    /// Oldest first, as formatted by [`egui_logging::LogRecord::to_line`]
+    pub recent_logs: Vec<String>,
+}
+
+impl CrashReport {
+    /// Collects a report for a panic on the current thread.
+    pub fn capture(panic: PanicReport) -> Self {
+        let thread = std::thread::current();
+        let recent_logs = egui_logging::logger()
+            .and_then(|logger| logger.try_records(LOCK_TIMEOUT))
+            .map(|records| {
+                let skip = records.len().saturating_sub(RECENT_LOG_LINES);
+                records[skip..]
+                    .iter()
+                    .map(|record| record.to_line())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            panic,
+            thread: thread.name().unwrap_or("<unnamed>").to_owned(),
+            time: SystemTime::now(),
+            backtrace: Backtrace::force_capture().to_string(),
+            frame: last_frame(),
+            recent_logs,
+        }
+    }
+
+    /// The report as written to disk.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{}", self.panic);
+        let _ = writeln!(text, "Thread: {}", self.thread);
+        if let Ok(since_epoch) = self.time.duration_since(SystemTime::UNIX_EPOCH) {
+            let _ = writeln!(
+                text,
+                "Time: {:.3} s since the Unix epoch",
+                since_epoch.as_secs_f64()
+            );
+        }
+        match &self.frame {
+            Some(frame) => {
+                let [width, height] = frame.size_px;
+                let display = &frame.display;
+                let _ = writeln!(
+                    text,
+                    "Surface: {width}x{height} px, density {}, font scale {}{}",
+                    display.density,
+                    display.font_scale,
+                    if display.night_mode {
+                        ", night mode"
+                    } else {
+                        ""
+                    },
+                );
+                let _ = writeln!(text, "Frame: {}", frame.pass_nr);
+            }
+            None => text.push_str("Surface: no frame drawn on this thread\n"),
+        }
+        let _ = write!(text, "\nBacktrace:\n{}\n", self.backtrace.trim_end());
+        text.push_str("\nRecent logs:\n");
+        for line in &self.recent_logs {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// A report written by this or an earlier run, and not deleted yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingReport {
+    /// File name inside the store's directory
+    pub name: String,
+    pub contents: String,
+}
+
+/// Keeps crash reports as text files in one directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrashReportStore {
+    dir: PathBuf,
+}
+
+impl CrashReportStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes `report`, creating the directory if needed, and returns the name it got.
+    pub fn write(&self, report: &CrashReport) -> io::Result<String> {
+        static SEQ: AtomicU32 = AtomicU32::new(0);
+        let millis = report
+            .time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{PREFIX}{millis:013}-{seq:04}{EXTENSION}");
+        persistence::write_file(&self.dir.join(&name), report.to_text().as_bytes())?;
+        Ok(name)
+    }
+
+    /// The reports in the directory, oldest first. A missing directory has none.
+    pub fn pending(&self) -> io::Result<Vec<PendingReport>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            if let Ok(name) = entry?.file_name().into_string() {
+                if is_report_name(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let contents = std::fs::read_to_string(self.dir.join(&name))?;
+                Ok(PendingReport { name, contents })
+            })
+            .collect()
+    }
+
+    /// Deletes the report called `name`. Refuses names that aren't reports, so this can't be used
+    /// to delete anything else.
+    pub fn remove(&self, name: &str) -> io::Result<()> {
+        if !is_report_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("not a crash report: {name:?}"),
+            ));
+        }
+        std::fs::remove_file(self.dir.join(name))
+    }
+
+    /// Deletes every report.
+    pub fn clear(&self) -> io::Result<()> {
+        for report in self.pending()? {
+            self.remove(&report.name)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_report_name(name: &str) -> bool {
+    name.starts_with(PREFIX) && name.ends_with(EXTENSION) && !name.contains(['/', '\\'])
+}
+
+static STORE: Mutex<Option<CrashReportStore>> = Mutex::new(None);
+static STARTUP_DIALOG: Mutex<Option<CrashReportDialog>> = Mutex::new(None);
+
+/// Sets where panics are written to, or stops writing them with `None`.
+///
+/// The first store set also creates the dialog shown by [`show_startup_dialog`], so it only
+/// lists reports left by earlier runs.
+pub fn set_report_store(store: Option<CrashReportStore>) {
+    if let Some(store) = &store {
+        STARTUP_DIALOG
+            .lock()
+            .get_or_insert_with(|| CrashReportDialog::new(Some(store.clone())));
+    }
+    *STORE.lock() = store;
+}
+
+/// The store set by [`set_report_store`].
+pub fn report_store() -> Option<CrashReportStore> {
+    STORE.try_lock_for(LOCK_TIMEOUT)?.clone()
+}
+
+/// Shows the reports found when the first store was set, if there were any. Call it every frame.
+pub fn show_startup_dialog(ctx: &egui::Context) {
+    if let Some(dialog) = &mut *STARTUP_DIALOG.lock() {
+        dialog.show(ctx);
+    }
+}
+
+/// Called by the panic hook. Writes a report if there's somewhere to write it to.
+pub(crate) fn on_panic(panic: PanicReport) {
+    let Some(store) = report_store() else {
+        return;
+    };
+    match store.write(&CrashReport::capture(panic)) {
+        Ok(name) => error!("Wrote crash report {name}"),
+        Err(err) => error!("Failed to write crash report to {:?}: {err}", store.dir()),
+    }
+}
+
+/// Shows the reports left by earlier runs, so they can be copied somewhere before they're deleted.
+///
+/// Create it once at startup, before anything could have panicked in this run, and call
+/// [`Self::show`] every frame. It opens by itself if there's anything to show.
+pub struct CrashReportDialog {
+    pub open: bool,
+    store: Option<CrashReportStore>,
+    reports: Vec<PendingReport>,
+    selected: usize,
+}
+
+impl CrashReportDialog {
+    pub fn new(store: Option<CrashReportStore>) -> Self {
+        let mut dialog = Self {
+            open: false,
+            store,
+            reports: Vec::new(),
+            selected: 0,
+        };
+        dialog.reload();
+        dialog.open = !dialog.reports.is_empty();
+        dialog
+    }
+
+    /// The reports found when the dialog was created, minus the ones deleted since.
+    pub fn reports(&self) -> &[PendingReport] {
+        &self.reports
+    }
+
+    fn reload(&mut self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        match store.pending() {
+            Ok(reports) => self.reports = reports,
+            Err(err) => warn!("Failed to list crash reports in {:?}: {err}", store.dir()),
+        }
+        self.selected = self.selected.min(self.reports.len().saturating_sub(1));
+    }
+
+    fn delete(&mut self, name: &str) {
+        if let Some(store) = &self.store {
+            if let Err(err) = store.remove(name) {
+                warn!("Failed to delete crash report {name}: {err}");
+            }
+        }
+        self.reload();
+    }
+
+    /// Shows the dialog as a window, while [`Self::open`] is set and there are reports left.
+    pub fn show(&mut self, ctx: &egui::Context) {
+        if self.reports.is_empty() {
+            self.open = false;
+        }
+        let mut open = self.open;
+        egui::Window::new("Crash reports")
+            .open(&mut open)
+            .default_size([480.0, 360.0])
+            .show(ctx, |ui| self.ui(ui));
+        self.open &= open;
+    }
+
+    /// Shows the dialog inside `ui`.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let Some(report) = self.reports.get(self.selected).cloned() else {
+            ui.label("No crash reports.");
+            return;
+        };
+        ui.label(match self.reports.len() {
+            1 => "The app crashed last time. Please send us this report.".to_owned(),
+            count => format!("The app crashed {count} times. Please send us these reports."),
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("crash_report")
+                .selected_text(&report.name)
+                .show_ui(ui, |ui| {
+                    for (i, report) in self.reports.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected, i, &report.name);
+                    }
+                });
+            if ui.button("Copy").clicked() {
+                ui.ctx().copy_text(report.contents.clone());
+            }
+            if ui.button("Delete").clicked() {
+                self.delete(&report.name);
+            }
+            if ui.button("Delete all").clicked() {
+                if let Some(store) = &self.store {
+                    if let Err(err) = store.clear() {
+                        warn!("Failed to delete crash reports: {err}");
+                    }
+                }
+                self.reload();
+            }
+        });
+        ui.separator();
+        egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+            ui.add(egui::Label::new(RichText::new(&report.contents).monospace()).extend());
+        });
+    }
+}
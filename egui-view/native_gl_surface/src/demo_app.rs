@@ -0,0 +1,304 @@
+//! The demo UI shown when no other app is requested.
+
+use egui::{
+    emath::{RectTransform, Rot2},
+    vec2, Color32, Frame, Pos2, Rect, Sense, Stroke, Vec2,
+};
+
+use egui_logging::LogConsole;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::SurfaceApp,
+    crash,
+    messages::{self, Message},
+};
+
+/// The topic the clear color is sent and received under, as a JSON array of three numbers.
+pub const CLEAR_COLOR_TOPIC: &str = "clear_color";
+
+pub struct AppState {
+    clear_color: [f32; 3],
+    rotation: f32,
+    translation: Vec2,
+    zoom: f32,
+    last_touch_time: f64,
+    name: String,
+    age: u32,
+    log_console: LogConsole,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        let SavedAppState {
+            name,
+            age,
+            clear_color,
+        } = SavedAppState::default();
+        Self {
+            clear_color,
+            rotation: 0.,
+            translation: Vec2::ZERO,
+            zoom: 1.,
+            last_touch_time: 0.0,
+            name,
+            age,
+            log_console: LogConsole::default(),
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &egui::Context) {
+        // Keep the content clear of system bars and the keyboard; the background still goes
+        // edge-to-edge.
+        let mut frame = Frame::central_panel(&ctx.style());
+        frame.inner_margin = frame.inner_margin + crate::insets::safe_area_margin(ctx);
+        egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
+            ui.heading("My egui Application");
+            ui.horizontal(|ui| {
+                let name_label = ui.label("Your name: ");
+                ui.text_edit_singleline(&mut self.name)
+                    .labelled_by(name_label.id);
+            });
+            ui.add(egui::Slider::new(&mut self.age, 0..=120).text("age"));
+            if ui.button("Increment").clicked() {
+                self.age += 1;
+            }
+            ui.label(format!("Hello '{}', age {}", self.name, self.age));
+
+            if ui.color_edit_button_rgb(&mut self.clear_color).changed() {
+                let [r, g, b] = self.clear_color;
+                messages::send(
+                    ctx,
+                    Message::json(CLEAR_COLOR_TOPIC, format!("[{r},{g},{b}]")),
+                );
+            }
+
+            ui.collapsing("GPU", crate::gl_info::diagnostics_ui);
+            ui.toggle_value(&mut self.log_console.open, "Logs");
+
+            // ui.image(egui::include_image!(
+            //     "../../../crates/egui/assets/ferris.png"
+            // ));
+            Frame::canvas(ui.style()).show(ui, |ui| {
+                let num_touches = ui.input(|i| i.multi_touch().map_or(0, |mt| mt.num_touches));
+                ui.label(format!("Current touches: {num_touches}"));
+
+                let color = if ui.visuals().dark_mode {
+                    Color32::WHITE
+                } else {
+                    Color32::BLACK
+                };
+
+                // Note that we use `Sense::drag()` although we do not use any pointer events. With
+                // the current implementation, the fact that a touch event of two or more fingers is
+                // recognized, does not mean that the pointer events are suppressed, which are always
+                // generated for the first finger. Therefore, if we do not explicitly consume pointer
+                // events, the window will move around, not only when dragged with a single finger, but
+                // also when a two-finger touch is active. I guess this problem can only be cleanly
+                // solved when the synthetic pointer events are created by egui, and not by the
+                // backend.
+
+                // set up the drawing canvas with normalized coordinates:
+                let (response, painter) =
+                    ui.allocate_painter(ui.available_size_before_wrap(), Sense::drag());
+
+                // normalize painter coordinates to ±1 units in each direction with [0,0] in the center:
+                let painter_proportions = response.rect.square_proportions();
+                let to_screen = RectTransform::from_to(
+                    Rect::from_min_size(Pos2::ZERO - painter_proportions, 2. * painter_proportions),
+                    response.rect,
+                );
+
+                // check for touch input (or the lack thereof) and update zoom and scale factors, plus
+                // color and width:
+                let mut stroke_width = 1.;
+                if let Some(multi_touch) = ui.ctx().multi_touch() {
+                    // This adjusts the current zoom factor and rotation angle according to the dynamic
+                    // change (for the current frame) of the touch gesture:
+                    self.zoom *= multi_touch.zoom_delta;
+                    self.rotation += multi_touch.rotation_delta;
+                    // the translation we get from `multi_touch` needs to be scaled down to the
+                    // normalized coordinates we use as the basis for painting:
+                    self.translation += to_screen.inverse().scale() * multi_touch.translation_delta;
+                    // touch pressure will make the arrow thicker (not all touch devices support this):
+                    stroke_width += 10. * multi_touch.force;
+
+                    self.last_touch_time = ui.input(|i| i.time);
+                } else {
+                    self.slowly_reset(ui);
+                }
+                let zoom_and_rotate = self.zoom * Rot2::from_angle(self.rotation);
+                let arrow_start_offset = self.translation + zoom_and_rotate * vec2(-0.5, 0.5);
+
+                // Paints an arrow pointing from bottom-left (-0.5, 0.5) to top-right (0.5, -0.5), but
+                // scaled, rotated, and translated according to the current touch gesture:
+                let arrow_start = Pos2::ZERO + arrow_start_offset;
+                let arrow_direction = zoom_and_rotate * vec2(1., -1.);
+                painter.arrow(
+                    to_screen * arrow_start,
+                    to_screen.scale() * arrow_direction,
+                    Stroke::new(stroke_width, color),
+                );
+            });
+        });
+
+        if let Some(logger) = egui_logging::logger() {
+            self.log_console.show(ctx, logger);
+        }
+        crash::show_startup_dialog(ctx);
+    }
+
+    fn slowly_reset(&mut self, ui: &egui::Ui) {
+        // This has nothing to do with the touch gesture. It just smoothly brings the
+        // painted arrow back into its original position, for a nice visual effect:
+
+        let time_since_last_touch = (ui.input(|i| i.time) - self.last_touch_time) as f32;
+
+        let delay = 0.5;
+        if time_since_last_touch < delay {
+            ui.ctx().request_repaint();
+        } else {
+            // seconds after which half the amount of zoom/rotation will be reverted:
+            let half_life =
+                egui::remap_clamp(time_since_last_touch, delay..=1.0, 1.0..=0.0).powf(4.0);
+
+            if half_life <= 1e-3 {
+                self.zoom = 1.0;
+                self.rotation = 0.0;
+                self.translation = Vec2::ZERO;
+            } else {
+                let dt = ui.input(|i| i.unstable_dt);
+                let half_life_factor = (-(2_f32.ln()) / half_life * dt).exp();
+                self.zoom = 1. + ((self.zoom - 1.) * half_life_factor);
+                self.rotation *= half_life_factor;
+                self.translation *= half_life_factor;
+                ui.ctx().request_repaint();
+            }
+        }
+    }
+}
+
+/// The part of [`AppState`] that is persisted. The touch gesture state is deliberately left out.
+///
+/// Fields missing from older saves fall back to their defaults, so add new ones freely.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedAppState {
+    pub name: String,
+    pub age: u32,
+    pub clear_color: [f32; 3],
+}
+
+impl Default for SavedAppState {
+    fn default() -> Self {
+        Self {
+            name: String::from("Bob"),
+            age: 69,
+            clear_color: [1.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SurfaceApp for AppState {
+    fn update(&mut self, ctx: &egui::Context) {
+        self.draw(ctx);
+    }
+
+    fn on_message(&mut self, _ctx: &egui::Context, message: Message) {
+        if message.topic != CLEAR_COLOR_TOPIC {
+            log::warn!("Demo app ignoring message {:?}", message.topic);
+            return;
+        }
+        match message.as_json().and_then(parse_json_numbers) {
+            Some(color) if color.len() == 3 => {
+                self.clear_color.copy_from_slice(&color);
+            }
+            _ => log::warn!("Demo app expects [r, g, b] for {CLEAR_COLOR_TOPIC}, got {message:?}"),
+        }
+    }
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        let cc = self.clear_color;
+        [cc[0], cc[1], cc[2], 1.0]
+    }
+
+    fn save(&mut self) -> Option<Vec<u8>> {
+        let saved = SavedAppState {
+            name: self.name.clone(),
+            age: self.age,
+            clear_color: self.clear_color,
+        };
+        match ron::to_string(&saved) {
+            Ok(text) => Some(text.into_bytes()),
+            Err(err) => {
+                log::warn!("Failed to save demo app state: {err}");
+                None
+            }
+        }
+    }
+
+    fn load(&mut self, data: &[u8]) {
+        match ron::de::from_bytes::<SavedAppState>(data) {
+            Ok(saved) => {
+                self.name = saved.name;
+                self.age = saved.age;
+                self.clear_color = saved.clear_color;
+            }
+            Err(err) => log::warn!("Failed to load demo app state: {err}"),
+        }
+    }
+}
+
+/// Parses a JSON array of numbers, e.g. `[0.5, 1e-3, -0]`. `None` for anything else.
+fn parse_json_numbers(json: &str) -> Option<Vec<f32>> {
+    let items = json.trim().strip_prefix('[')?.strip_suffix(']')?.trim();
+    if items.is_empty() {
+        return Some(Vec::new());
+    }
+    items
+        .split(',')
+        .map(|item| {
+            let item = item.trim();
+            is_json_number(item).then(|| item.parse().ok()).flatten()
+        })
+        .collect()
+}
+
+/// Whether `text` matches JSON's number grammar, which is stricter than [`str::parse`]: no `+`,
+/// `inf`, `NaN`, leading zeros, or missing digits around the `.`.
+fn is_json_number(text: &str) -> bool {
+    fn digits(text: &str) -> (&str, &str) {
+        let end = text
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len());
+        text.split_at(end)
+    }
+
+    let text = text.strip_prefix('-').unwrap_or(text);
+    let (int, mut rest) = digits(text);
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return false;
+    }
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let (fraction, after) = digits(fraction);
+        if fraction.is_empty() {
+            return false;
+        }
+        rest = after;
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let (exponent, after) = digits(exponent);
+        if exponent.is_empty() {
+            return false;
+        }
+        rest = after;
+    }
+    rest.is_empty()
+}
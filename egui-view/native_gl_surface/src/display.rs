@@ -0,0 +1,55 @@
+//! Display density, font scale and dark mode, as reported by Android's `Configuration`.
+
+/// The parts of `DisplayMetrics` and `Configuration` that affect how egui is laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayConfig {
+    /// `DisplayMetrics.density`: physical pixels per dp, which is what egui calls a point.
+    pub density: f32,
+    /// `Configuration.fontScale`, the user's accessibility text size. Applied as egui's zoom
+    /// factor, so it scales the whole UI and not just text.
+    pub font_scale: f32,
+    /// `Configuration.uiMode` has `UI_MODE_NIGHT_YES`
+    pub night_mode: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            font_scale: 1.0,
+            night_mode: false,
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Builds a config from raw JNI values, replacing nonsense with defaults.
+    pub fn new(density: f32, font_scale: f32, night_mode: bool) -> Self {
+        let sane = |value: f32| value.is_finite() && value > 0.0;
+        Self {
+            density: if sane(density) { density } else { 1.0 },
+            font_scale: if sane(font_scale) { font_scale } else { 1.0 },
+            night_mode,
+        }
+    }
+
+    /// Physical pixels per egui point, after zoom.
+    pub fn pixels_per_point(&self) -> f32 {
+        self.density * self.font_scale
+    }
+
+    /// Goes into [`egui::RawInput::system_theme`], so apps following the system theme switch
+    /// between dark and light visuals.
+    pub fn theme(&self) -> egui::Theme {
+        if self.night_mode {
+            egui::Theme::Dark
+        } else {
+            egui::Theme::Light
+        }
+    }
+
+    /// Applies the font scale to `ctx`. Takes effect at the start of the next [`egui::Context::run`].
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_zoom_factor(self.font_scale);
+    }
+}
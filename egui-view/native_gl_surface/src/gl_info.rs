@@ -0,0 +1,140 @@
+//! What the current GL context can do.
+
+use std::sync::Arc;
+
+use glow::HasContext as _;
+
+/// Limits and identification of a GL context, queried once when the painter is created.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlInfo {
+    /// `GL_MAX_TEXTURE_SIZE`
+    pub max_texture_side: usize,
+    pub version: String,
+    pub shading_language_version: String,
+    pub vendor: String,
+    pub renderer: String,
+    /// Sorted, for stable output
+    pub extensions: Vec<String>,
+}
+
+impl GlInfo {
+    /// Must be called on the thread the context is current on.
+    pub fn query(gl: &glow::Context) -> Self {
+        // SAFETY: plain state queries; the caller has a current context
+        let (max_texture_size, version, shading_language_version, vendor, renderer) = unsafe {
+            (
+                gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE),
+                gl.get_parameter_string(glow::VERSION),
+                gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION),
+                gl.get_parameter_string(glow::VENDOR),
+                gl.get_parameter_string(glow::RENDERER),
+            )
+        };
+        let mut extensions: Vec<String> = gl.supported_extensions().iter().cloned().collect();
+        extensions.sort();
+
+        Self {
+            // GLES 3.0 guarantees at least 2048
+            max_texture_side: usize::try_from(max_texture_size)
+                .ok()
+                .filter(|&side| side > 0)
+                .unwrap_or(2048),
+            version,
+            shading_language_version,
+            vendor,
+            renderer,
+            extensions,
+        }
+    }
+
+    /// Whether this is an OpenGL ES context rather than desktop GL.
+    pub fn is_es(&self) -> bool {
+        self.version.starts_with("OpenGL ES")
+    }
+
+    /// The `(major, minor)` shading language version, e.g. `(3, 0)` for "OpenGL ES GLSL ES 3.00".
+    pub fn glsl_version(&self) -> Option<(u32, u32)> {
+        let start = self
+            .shading_language_version
+            .find(|c: char| c.is_ascii_digit())?;
+        let (major, rest) = self.shading_language_version[start..].split_once('.')?;
+        let minor: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        Some((major.parse().ok()?, minor.parse().ok()?))
+    }
+
+    /// Whether egui_glow has shaders for this context: GLSL ES 1.00 or desktop GLSL 1.20 and up.
+    pub fn supports_egui_shaders(&self) -> bool {
+        let minimum = if self.is_es() { (1, 0) } else { (1, 20) };
+        self.glsl_version()
+            .is_some_and(|version| version >= minimum)
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions
+            .binary_search_by(|ext| ext.as_str().cmp(name))
+            .is_ok()
+    }
+
+    /// A human readable report, as handed to Kotlin.
+    pub fn summary(&self) -> String {
+        format!(
+            "GL version: {}\nGLSL version: {}\nVendor: {}\nRenderer: {}\nMax texture size: {}\nExtensions ({}):\n{}",
+            self.version,
+            self.shading_language_version,
+            self.vendor,
+            self.renderer,
+            self.max_texture_side,
+            self.extensions.len(),
+            self.extensions.join("\n"),
+        )
+    }
+
+    fn id() -> egui::Id {
+        egui::Id::new("native_gl_surface::GlInfo")
+    }
+
+    /// Makes this available to the hosted app through [`Self::from_ctx`].
+    pub fn store(self: &Arc<Self>, ctx: &egui::Context) {
+        ctx.data_mut(|d| d.insert_temp(Self::id(), Arc::clone(self)));
+    }
+
+    /// The info for the context the app is being rendered with, once the first frame has run.
+    pub fn from_ctx(ctx: &egui::Context) -> Option<Arc<Self>> {
+        ctx.data(|d| d.get_temp(Self::id()))
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        egui::Grid::new("gl_info").num_columns(2).show(ui, |ui| {
+            ui.label("GL version");
+            ui.label(&self.version);
+            ui.end_row();
+            ui.label("GLSL version");
+            ui.label(&self.shading_language_version);
+            ui.end_row();
+            ui.label("Vendor");
+            ui.label(&self.vendor);
+            ui.end_row();
+            ui.label("Renderer");
+            ui.label(&self.renderer);
+            ui.end_row();
+            ui.label("Max texture size");
+            ui.label(self.max_texture_side.to_string());
+            ui.end_row();
+        });
+        ui.collapsing(format!("Extensions ({})", self.extensions.len()), |ui| {
+            for ext in &self.extensions {
+                ui.monospace(ext);
+            }
+        });
+    }
+}
+
+/// Shows the [`GlInfo`] of the surface this UI is running in, if known yet.
+pub fn diagnostics_ui(ui: &mut egui::Ui) {
+    match GlInfo::from_ctx(ui.ctx()) {
+        Some(info) => info.ui(ui),
+        None => {
+            ui.label("No GL context yet");
+        }
+    }
+}
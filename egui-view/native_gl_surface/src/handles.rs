@@ -0,0 +1,150 @@
+//! Handles given out to Kotlin in place of raw pointers.
+//!
+//! A handle packs a slot index and the slot's generation into a `jlong`. Destroying a handle bumps
+//! the generation, so a handle that is stale, zero, or was never issued is detected instead of
+//! being dereferenced.
+
+use std::sync::Arc;
+
+/// Why a handle couldn't be resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HandleError {
+    /// The handle is `0`, which is never issued.
+    Null,
+    /// The handle was valid once but has since been removed.
+    Stale,
+    /// The handle was never issued by this registry.
+    Invalid,
+    /// The value behind the handle was left in an unknown state, e.g. by a panic.
+    Poisoned,
+}
+
+impl std::fmt::Display for HandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null handle"),
+            Self::Stale => write!(f, "stale handle (already destroyed)"),
+            Self::Invalid => write!(f, "invalid handle"),
+            Self::Poisoned => write!(f, "poisoned handle"),
+        }
+    }
+}
+
+impl std::error::Error for HandleError {}
+
+struct Slot<T> {
+    /// Odd while occupied, even while free. Starts at 1, so no handle is ever 0.
+    generation: u32,
+    value: Option<Arc<T>>,
+    /// Set by [`HandleRegistry::poison`] until the value is removed
+    poisoned: bool,
+}
+
+/// A generational slab of `Arc<T>`s, addressed by `i64` handles.
+pub struct HandleRegistry<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+impl<T> Default for HandleRegistry<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> HandleRegistry<T> {
+    pub const fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> i64 {
+        let value = Some(Arc::new(value));
+        let index = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.generation = slot.generation.wrapping_add(1).max(1);
+                slot.value = value;
+                slot.poisoned = false;
+                index
+            }
+            None => {
+                let index = u32::try_from(self.slots.len()).expect("Too many handles");
+                self.slots.push(Slot {
+                    generation: 1,
+                    value,
+                    poisoned: false,
+                });
+                index
+            }
+        };
+        pack(index, self.slots[index as usize].generation)
+    }
+
+    /// The value behind `handle`. The returned `Arc` keeps it alive even if the handle is removed
+    /// concurrently.
+    pub fn get(&self, handle: i64) -> Result<Arc<T>, HandleError> {
+        let slot = self.slot(handle)?;
+        if slot.poisoned {
+            return Err(HandleError::Poisoned);
+        }
+        slot.value.clone().ok_or(HandleError::Stale)
+    }
+
+    /// Makes [`Self::get`] fail for `handle` from now on. It can still be removed.
+    pub fn poison(&mut self, handle: i64) -> Result<(), HandleError> {
+        let (index, _) = unpack(handle);
+        self.slot(handle)?;
+        self.slots[index as usize].poisoned = true;
+        Ok(())
+    }
+
+    pub fn is_poisoned(&self, handle: i64) -> bool {
+        self.slot(handle).is_ok_and(|slot| slot.poisoned)
+    }
+
+    /// Invalidates `handle` and hands back its value.
+    pub fn remove(&mut self, handle: i64) -> Result<Arc<T>, HandleError> {
+        let (index, _) = unpack(handle);
+        self.slot(handle)?;
+        let slot = &mut self.slots[index as usize];
+        let value = slot.value.take().ok_or(HandleError::Stale)?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        Ok(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slot(&self, handle: i64) -> Result<&Slot<T>, HandleError> {
+        if handle == 0 {
+            return Err(HandleError::Null);
+        }
+        let (index, generation) = unpack(handle);
+        let slot = self.slots.get(index as usize).ok_or(HandleError::Invalid)?;
+        if generation == slot.generation && slot.value.is_some() {
+            Ok(slot)
+        } else if generation < slot.generation && generation % 2 == 1 {
+            Err(HandleError::Stale)
+        } else {
+            Err(HandleError::Invalid)
+        }
+    }
+}
+
+fn pack(index: u32, generation: u32) -> i64 {
+    ((u64::from(generation) << 32) | u64::from(index)) as i64
+}
+
+fn unpack(handle: i64) -> (u32, u32) {
+    let handle = handle as u64;
+    (handle as u32, (handle >> 32) as u32)
+}
@@ -0,0 +1,141 @@
+//! Window insets: the parts of the surface covered by system bars, display cutouts and the soft
+//! keyboard when the activity is drawn edge-to-edge.
+//!
+//! egui still gets the whole surface as its screen rect, so backgrounds reach the screen edges.
+//! Hosted apps lay out their content inside [`safe_area`] instead.
+
+use egui::{Margin, Rect};
+
+/// Distances from each edge of the surface, in physical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    /// The larger inset on every edge.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    pub fn to_margin(self, pixels_per_point: f32) -> Margin {
+        Margin {
+            left: self.left / pixels_per_point,
+            right: self.right / pixels_per_point,
+            top: self.top / pixels_per_point,
+            bottom: self.bottom / pixels_per_point,
+        }
+    }
+
+    fn from_ltrb(values: &[i32]) -> Self {
+        let px = |value: i32| value.max(0) as f32;
+        Self {
+            left: px(values[0]),
+            top: px(values[1]),
+            right: px(values[2]),
+            bottom: px(values[3]),
+        }
+    }
+}
+
+/// The `WindowInsetsCompat` types we care about, relative to the surface.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowInsets {
+    /// Status and navigation bars
+    pub system_bars: Insets,
+    pub display_cutout: Insets,
+    /// The soft keyboard; all zero while it is hidden
+    pub ime: Insets,
+}
+
+impl WindowInsets {
+    /// Unpacks the array built by `NativeGLSurfaceView`: left, top, right, bottom for the system
+    /// bars, then the display cutout, then the IME.
+    pub fn from_packed(values: &[i32]) -> Option<Self> {
+        if values.len() != 12 {
+            return None;
+        }
+        Some(Self {
+            system_bars: Insets::from_ltrb(&values[0..4]),
+            display_cutout: Insets::from_ltrb(&values[4..8]),
+            ime: Insets::from_ltrb(&values[8..12]),
+        })
+    }
+
+    /// Everything that is covered by something.
+    pub fn unsafe_insets(&self) -> Insets {
+        self.system_bars.max(self.display_cutout).max(self.ime)
+    }
+
+    /// The part of `screen_rect` (in points) that isn't covered.
+    pub fn safe_area(&self, screen_rect: Rect, pixels_per_point: f32) -> Rect {
+        let safe = screen_rect - self.unsafe_insets().to_margin(pixels_per_point);
+        if safe.is_positive() {
+            safe
+        } else {
+            // Insets larger than the surface, e.g. a keyboard over a tiny view
+            Rect::from_min_size(safe.min, egui::Vec2::ZERO)
+        }
+    }
+}
+
+fn safe_area_id(viewport_id: egui::ViewportId) -> egui::Id {
+    egui::Id::new("native_gl_surface::safe_area").with(viewport_id)
+}
+
+/// Makes `safe_area` available to the hosted app through [`safe_area`], in passes of
+/// `viewport_id`.
+pub fn store_safe_area(ctx: &egui::Context, viewport_id: egui::ViewportId, safe_area: Rect) {
+    ctx.data_mut(|d| d.insert_temp(safe_area_id(viewport_id), safe_area));
+}
+
+/// The part of the screen not covered by system bars, cutouts or the soft keyboard, in points.
+///
+/// Falls back to the whole screen when the host hasn't reported any insets.
+pub fn safe_area(ctx: &egui::Context) -> Rect {
+    ctx.data(|d| d.get_temp(safe_area_id(ctx.viewport_id())))
+        .unwrap_or_else(|| ctx.screen_rect())
+}
+
+/// [`safe_area`] as distances from the screen edges, e.g. to add to a panel's margin.
+pub fn safe_area_margin(ctx: &egui::Context) -> Margin {
+    let screen = ctx.screen_rect();
+    let safe = safe_area(ctx);
+    Margin {
+        left: (safe.left() - screen.left()).max(0.0),
+        right: (screen.right() - safe.right()).max(0.0),
+        top: (safe.top() - screen.top()).max(0.0),
+        bottom: (screen.bottom() - safe.bottom()).max(0.0),
+    }
+}
+
+/// Scrolls the focused widget into view when the soft keyboard opens on top of it.
+#[derive(Default)]
+pub struct ImeScroller {
+    /// The IME's bottom inset last frame, in pixels
+    ime_bottom: f32,
+}
+
+impl ImeScroller {
+    /// Call at the start of a pass, before the app builds its UI.
+    pub fn begin_pass(&mut self, ctx: &egui::Context, insets: &WindowInsets) {
+        let grew = insets.ime.bottom > self.ime_bottom;
+        self.ime_bottom = insets.ime.bottom;
+        if !grew {
+            return;
+        }
+        let focused = ctx.memory(|mem| mem.focused());
+        if let Some(response) = focused.and_then(|id| ctx.read_response(id)) {
+            // `None` only scrolls if the widget isn't already visible
+            response.scroll_to_me(None);
+        }
+    }
+}
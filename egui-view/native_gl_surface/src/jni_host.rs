@@ -0,0 +1,375 @@
+//! Calls from Rust back into the Kotlin `NativeGLSurfaceView`.
+
+use std::sync::Arc;
+
+use egui::{output::OutputEvent, CursorIcon, OpenUrl, Rect, UserAttentionType, ViewportId};
+use jni::{
+    objects::{GlobalRef, JObject, JString, JValue},
+    JNIEnv, JavaVM,
+};
+use log::warn;
+
+use crate::{
+    commands::ViewportCommandHost,
+    keyboard::{SoftKeyboard, TextInputType},
+    messages::{Message, MessageHost, Payload},
+    platform::PlatformHost,
+    renderer::RendererError,
+    repaint::{RepaintSchedule, RepaintSignal},
+    viewport_key,
+    viewports::ViewportHost,
+};
+
+/// Local references a single call to the view needs at most
+const LOCAL_FRAME_CAPACITY: i32 = 8;
+
+/// Holds on to the Kotlin view that owns a surface, and calls methods on it.
+///
+/// Calls may come from any thread; the view is responsible for hopping onto the UI thread.
+#[derive(Clone)]
+pub struct JniHost {
+    vm: Arc<JavaVM>,
+    view: GlobalRef,
+}
+
+impl JniHost {
+    pub fn new(env: &JNIEnv, view: &JObject) -> jni::errors::Result<Self> {
+        Ok(Self {
+            vm: Arc::new(env.get_java_vm()?),
+            view: env.new_global_ref(view)?,
+        })
+    }
+
+    /// Calls a method on the view, logging (and clearing) any Java exception it throws.
+    ///
+    /// `f` runs in its own local reference frame. Most calls come from inside a native method,
+    /// whose local references only go away once it returns, so a frame delivering many messages
+    /// would otherwise pile them up.
+    fn call<T>(
+        &self,
+        name: &str,
+        sig: &str,
+        f: impl FnOnce(&mut JNIEnv) -> jni::errors::Result<T>,
+    ) -> Option<T> {
+        let result = self.vm.attach_current_thread().and_then(|mut env| {
+            env.with_local_frame(LOCAL_FRAME_CAPACITY, |env| {
+                let result = f(env);
+                if env.exception_check().unwrap_or(false) {
+                    let _ = env.exception_describe();
+                    let _ = env.exception_clear();
+                }
+                result
+            })
+        });
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!("Calling {name}{sig} on the host view failed: {err}");
+                None
+            }
+        }
+    }
+
+    fn call_void(&self, name: &str, sig: &str, args: &[JValue]) {
+        self.call(name, sig, |env| {
+            env.call_method(&self.view, name, sig, args).map(|_| ())
+        });
+    }
+
+    /// Calls a method returning whether it did what was asked, `false` if the call failed.
+    fn call_bool(&self, name: &str, sig: &str, args: &[JValue]) -> bool {
+        self.call(name, sig, |env| {
+            env.call_method(&self.view, name, sig, args)?.z()
+        })
+        .unwrap_or(false)
+    }
+
+    /// Tells the view's `AccessibilityNodeProvider` that the tree changed.
+    pub fn accessibility_changed(&self) {
+        self.call_void("onAccessibilityTreeChanged", "()V", &[]);
+    }
+
+    /// Tells the view the renderer couldn't be created, as a
+    /// [`RendererError::status_code`] and a message.
+    pub fn renderer_failed(&self, err: &RendererError) {
+        let name = "onRendererFailed";
+        let sig = "(ILjava/lang/String;)V";
+        self.call(name, sig, |env| {
+            let message = env.new_string(err.to_string())?;
+            env.call_method(
+                &self.view,
+                name,
+                sig,
+                &[JValue::Int(err.status_code()), JValue::Object(&message)],
+            )
+            .map(|_| ())
+        });
+    }
+
+    fn call_with_string(&self, name: &str, text: &str) {
+        let sig = "(Ljava/lang/String;)V";
+        self.call(name, sig, |env| {
+            let text = env.new_string(text)?;
+            env.call_method(&self.view, name, sig, &[JValue::Object(&text)])
+                .map(|_| ())
+        });
+    }
+}
+
+impl MessageHost for JniHost {
+    fn deliver(&mut self, message: Message) {
+        let name = "onMessage";
+        let sig = "(Ljava/lang/String;Ljava/lang/String;[B)V";
+        self.call(name, sig, |env| {
+            let topic = env.new_string(&message.topic)?;
+            let (json, bytes) = match &message.payload {
+                Payload::Json(json) => (JObject::from(env.new_string(json)?), JObject::null()),
+                Payload::Bytes(bytes) => (
+                    JObject::null(),
+                    JObject::from(env.byte_array_from_slice(bytes)?),
+                ),
+            };
+            env.call_method(
+                &self.view,
+                name,
+                sig,
+                &[
+                    JValue::Object(&topic),
+                    JValue::Object(&json),
+                    JValue::Object(&bytes),
+                ],
+            )
+            .map(|_| ())
+        });
+    }
+}
+
+impl SoftKeyboard for JniHost {
+    fn show(&mut self, input_type: TextInputType) {
+        self.call_void(
+            "showSoftKeyboard",
+            "(I)V",
+            &[JValue::Int(input_type.to_android())],
+        );
+    }
+
+    fn hide(&mut self) {
+        self.call_void("hideSoftKeyboard", "()V", &[]);
+    }
+}
+
+impl PlatformHost for JniHost {
+    fn copy_text(&mut self, text: &str) {
+        self.call_with_string("setClipboardText", text);
+    }
+
+    fn paste_text(&mut self) -> Option<String> {
+        let name = "getClipboardText";
+        let sig = "()Ljava/lang/String;";
+        self.call(name, sig, |env| {
+            let text = JString::from(env.call_method(&self.view, name, sig, &[])?.l()?);
+            if text.is_null() {
+                return Ok(None);
+            }
+            let text = env.get_string(&text)?.into();
+            Ok(Some(text))
+        })
+        .flatten()
+    }
+
+    fn open_url(&mut self, open_url: &OpenUrl) {
+        self.call_with_string("openUrl", &open_url.url);
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.call_void(
+            "setPointerIconType",
+            "(I)V",
+            &[JValue::Int(pointer_icon_type(icon))],
+        );
+    }
+
+    fn widget_event(&mut self, event: &OutputEvent) {
+        // `HapticFeedbackConstants`
+        const VIRTUAL_KEY: i32 = 1;
+        const CLOCK_TICK: i32 = 4;
+
+        let feedback = match event {
+            OutputEvent::Clicked(_) | OutputEvent::DoubleClicked(_) => VIRTUAL_KEY,
+            OutputEvent::ValueChanged(info) if info.typ == egui::WidgetType::Slider => CLOCK_TICK,
+            _ => return,
+        };
+        self.call_void("performHaptic", "(I)V", &[JValue::Int(feedback)]);
+    }
+}
+
+impl RepaintSignal for JniHost {
+    fn request_repaint(&self, schedule: RepaintSchedule) {
+        self.call_void(
+            "requestRenderAfter",
+            "(J)V",
+            &[JValue::Long(schedule.to_millis())],
+        );
+    }
+}
+
+impl ViewportHost for JniHost {
+    fn open_viewport(&mut self, id: egui::ViewportId, builder: &egui::ViewportBuilder) -> bool {
+        let name = "openViewport";
+        let sig = "(JLjava/lang/String;FF)Z";
+        // In dp, or -1 to let the view pick
+        let [width, height] = builder
+            .inner_size
+            .map_or([-1.0; 2], |size| [size.x, size.y]);
+        self.call(name, sig, |env| {
+            let title = env.new_string(builder.title.as_deref().unwrap_or_default())?;
+            env.call_method(
+                &self.view,
+                name,
+                sig,
+                &[
+                    JValue::Long(viewport_key(id)),
+                    JValue::Object(&title),
+                    JValue::Float(width),
+                    JValue::Float(height),
+                ],
+            )?
+            .z()
+        })
+        .unwrap_or(false)
+    }
+
+    fn close_viewport(&mut self, id: egui::ViewportId) {
+        self.call_void("closeViewport", "(J)V", &[JValue::Long(viewport_key(id))]);
+    }
+
+    fn viewport_frame_ready(&mut self, id: egui::ViewportId) {
+        self.call_void(
+            "requestViewportRender",
+            "(J)V",
+            &[JValue::Long(viewport_key(id))],
+        );
+    }
+}
+
+impl ViewportCommandHost for JniHost {
+    fn close(&mut self, _viewport: ViewportId) -> bool {
+        self.call_bool("finishActivity", "()Z", &[])
+    }
+
+    fn set_title(&mut self, viewport: ViewportId, title: &str) -> bool {
+        let name = "setViewportTitle";
+        let sig = "(JLjava/lang/String;)Z";
+        self.call(name, sig, |env| {
+            let title = env.new_string(title)?;
+            env.call_method(
+                &self.view,
+                name,
+                sig,
+                &[JValue::Long(viewport_key(viewport)), JValue::Object(&title)],
+            )?
+            .z()
+        })
+        .unwrap_or(false)
+    }
+
+    fn set_fullscreen(&mut self, viewport: ViewportId, fullscreen: bool) -> bool {
+        self.call_bool(
+            "setViewportFullscreen",
+            "(JZ)Z",
+            &[
+                JValue::Long(viewport_key(viewport)),
+                JValue::Bool(fullscreen.into()),
+            ],
+        )
+    }
+
+    fn set_minimized(&mut self, viewport: ViewportId, minimized: bool) -> bool {
+        // Nothing can bring the task back to the front but the user
+        minimized && viewport == ViewportId::ROOT && self.call_bool("moveTaskToBack", "()Z", &[])
+    }
+
+    fn focus(&mut self, viewport: ViewportId) -> bool {
+        self.call_bool(
+            "focusViewport",
+            "(J)Z",
+            &[JValue::Long(viewport_key(viewport))],
+        )
+    }
+
+    fn request_user_attention(
+        &mut self,
+        _viewport: ViewportId,
+        attention: UserAttentionType,
+    ) -> bool {
+        // `HapticFeedbackConstants`; there is no window to flash, so the device buzzes instead
+        const LONG_PRESS: i32 = 0;
+        const VIRTUAL_KEY: i32 = 1;
+
+        let feedback = match attention {
+            UserAttentionType::Critical => LONG_PRESS,
+            UserAttentionType::Informational => VIRTUAL_KEY,
+            UserAttentionType::Reset => return true,
+        };
+        self.call_void("performHaptic", "(I)V", &[JValue::Int(feedback)]);
+        true
+    }
+
+    fn set_ime_allowed(&mut self, viewport: ViewportId, allowed: bool) -> bool {
+        self.call_bool(
+            "setImeAllowed",
+            "(JZ)Z",
+            &[
+                JValue::Long(viewport_key(viewport)),
+                JValue::Bool(allowed.into()),
+            ],
+        )
+    }
+
+    fn set_ime_rect(&mut self, viewport: ViewportId, rect_px: Rect) -> bool {
+        self.call_bool(
+            "setImeRect",
+            "(JFFF)Z",
+            &[
+                JValue::Long(viewport_key(viewport)),
+                JValue::Float(rect_px.left()),
+                JValue::Float(rect_px.top()),
+                JValue::Float(rect_px.bottom()),
+            ],
+        )
+    }
+}
+
+/// Maps an egui cursor to one of Android's `PointerIcon.TYPE_*` values.
+fn pointer_icon_type(icon: CursorIcon) -> i32 {
+    match icon {
+        CursorIcon::None => 0,
+        CursorIcon::Default => 1000,
+        CursorIcon::ContextMenu => 1001,
+        CursorIcon::PointingHand => 1002,
+        CursorIcon::Help => 1003,
+        CursorIcon::Wait | CursorIcon::Progress => 1004,
+        CursorIcon::Cell => 1006,
+        CursorIcon::Crosshair => 1007,
+        CursorIcon::Text => 1008,
+        CursorIcon::VerticalText => 1009,
+        CursorIcon::Alias => 1010,
+        CursorIcon::Copy => 1011,
+        CursorIcon::NoDrop | CursorIcon::NotAllowed => 1012,
+        CursorIcon::AllScroll | CursorIcon::Move => 1013,
+        CursorIcon::ResizeHorizontal
+        | CursorIcon::ResizeColumn
+        | CursorIcon::ResizeEast
+        | CursorIcon::ResizeWest => 1014,
+        CursorIcon::ResizeVertical
+        | CursorIcon::ResizeRow
+        | CursorIcon::ResizeNorth
+        | CursorIcon::ResizeSouth => 1015,
+        CursorIcon::ResizeNeSw | CursorIcon::ResizeNorthEast | CursorIcon::ResizeSouthWest => 1016,
+        CursorIcon::ResizeNwSe | CursorIcon::ResizeNorthWest | CursorIcon::ResizeSouthEast => 1017,
+        CursorIcon::ZoomIn => 1018,
+        CursorIcon::ZoomOut => 1019,
+        CursorIcon::Grab => 1020,
+        CursorIcon::Grabbing => 1021,
+    }
+}
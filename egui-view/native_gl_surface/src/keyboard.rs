@@ -0,0 +1,347 @@
+//! Hardware keys, the soft keyboard and IME composition.
+//!
+//! Like [`crate::touch`], everything here is plain Rust; the JNI glue lives in `lib.rs` and
+//! [`crate::jni_host`].
+
+use egui::{
+    accesskit::{Role, TreeUpdate},
+    ImeEvent, Key, Modifiers,
+};
+
+/// `KeyEvent.ACTION_DOWN`
+const ACTION_DOWN: i32 = 0;
+/// `KeyEvent.ACTION_UP`
+const ACTION_UP: i32 = 1;
+
+/// Maps an Android `KeyEvent.KEYCODE_*` to the matching egui key.
+pub fn key_from_keycode(key_code: i32) -> Option<Key> {
+    const DIGITS: [Key; 10] = [
+        Key::Num0,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+    const LETTERS: [Key; 26] = [
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+    ];
+    const FUNCTION_KEYS: [Key; 12] = [
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+    ];
+
+    let key = match key_code {
+        7..=16 => DIGITS[(key_code - 7) as usize],
+        144..=153 => DIGITS[(key_code - 144) as usize],
+        29..=54 => LETTERS[(key_code - 29) as usize],
+        131..=142 => FUNCTION_KEYS[(key_code - 131) as usize],
+        19 => Key::ArrowUp,
+        20 => Key::ArrowDown,
+        21 => Key::ArrowLeft,
+        22 => Key::ArrowRight,
+        55 => Key::Comma,
+        56 => Key::Period,
+        61 => Key::Tab,
+        62 => Key::Space,
+        66 | 160 => Key::Enter,
+        67 => Key::Backspace,
+        68 => Key::Backtick,
+        69 => Key::Minus,
+        70 => Key::Equals,
+        71 => Key::OpenBracket,
+        72 => Key::CloseBracket,
+        73 => Key::Backslash,
+        74 => Key::Semicolon,
+        75 => Key::Quote,
+        76 => Key::Slash,
+        81 => Key::Plus,
+        92 => Key::PageUp,
+        93 => Key::PageDown,
+        111 => Key::Escape,
+        112 => Key::Delete,
+        122 => Key::Home,
+        123 => Key::End,
+        124 => Key::Insert,
+        277 => Key::Cut,
+        278 => Key::Copy,
+        279 => Key::Paste,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Maps `KeyEvent.getMetaState()` to egui modifiers.
+pub fn modifiers_from_meta_state(meta_state: i32) -> Modifiers {
+    const META_SHIFT_ON: i32 = 0x1;
+    const META_ALT_ON: i32 = 0x2;
+    const META_CTRL_ON: i32 = 0x1000;
+
+    let ctrl = meta_state & META_CTRL_ON != 0;
+    Modifiers {
+        alt: meta_state & META_ALT_ON != 0,
+        ctrl,
+        shift: meta_state & META_SHIFT_ON != 0,
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+/// Translates one Android `KeyEvent`.
+///
+/// `unicode_char` is `KeyEvent.getUnicodeChar()`; when it is printable and no shortcut modifier
+/// is held, the key press also produces an [`egui::Event::Text`]. `repeat_count` is
+/// `KeyEvent.getRepeatCount()`, non-zero for presses generated by holding the key down.
+pub fn key_events(
+    action: i32,
+    key_code: i32,
+    meta_state: i32,
+    unicode_char: i32,
+    repeat_count: i32,
+    out: &mut Vec<egui::Event>,
+) {
+    let pressed = match action {
+        ACTION_DOWN => true,
+        ACTION_UP => false,
+        _ => return,
+    };
+    let modifiers = modifiers_from_meta_state(meta_state);
+
+    let key = key_from_keycode(key_code);
+    if let Some(key) = key {
+        out.push(egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed,
+            repeat: pressed && repeat_count > 0,
+            modifiers,
+        });
+    }
+
+    if pressed && !modifiers.ctrl && !modifiers.alt && key != Some(Key::Enter) {
+        if let Some(c) = char::from_u32(unicode_char as u32).filter(|c| !c.is_control()) {
+            out.push(egui::Event::Text(c.to_string()));
+        }
+    }
+}
+
+fn enter(out: &mut Vec<egui::Event>) {
+    for pressed in [true, false] {
+        out.push(egui::Event::Key {
+            key: Key::Enter,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        });
+    }
+}
+
+/// Tracks the composing region of the Android `InputConnection`.
+#[derive(Default)]
+pub struct ImeTranslator {
+    /// The current preedit text, if a composition is in progress.
+    composing: Option<String>,
+}
+
+impl ImeTranslator {
+    /// `InputConnection.setComposingText`
+    pub fn set_composing_text(&mut self, text: &str, out: &mut Vec<egui::Event>) {
+        if self.composing.is_none() {
+            out.push(egui::Event::Ime(ImeEvent::Enabled));
+        }
+        self.composing = Some(text.to_owned());
+        out.push(egui::Event::Ime(ImeEvent::Preedit(text.to_owned())));
+    }
+
+    /// `InputConnection.commitText`
+    pub fn commit_text(&mut self, text: &str, out: &mut Vec<egui::Event>) {
+        let was_composing = self.composing.take().is_some();
+        if text == "\n" {
+            if was_composing {
+                out.push(egui::Event::Ime(ImeEvent::Disabled));
+            }
+            enter(out);
+        } else if was_composing {
+            out.push(egui::Event::Ime(ImeEvent::Commit(text.to_owned())));
+        } else if !text.is_empty() {
+            out.push(egui::Event::Text(text.to_owned()));
+        }
+    }
+
+    /// `InputConnection.finishComposingText`: keep whatever is being composed.
+    pub fn finish_composing_text(&mut self, out: &mut Vec<egui::Event>) {
+        if let Some(text) = self.composing.take() {
+            out.push(egui::Event::Ime(ImeEvent::Commit(text)));
+        }
+    }
+}
+
+/// What kind of soft keyboard to show, mirroring `EditorInfo.inputType`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TextInputType {
+    #[default]
+    Text,
+    Password,
+    Number,
+    Multiline,
+}
+
+impl TextInputType {
+    /// The `InputType` flags to put into `EditorInfo.inputType`.
+    pub fn to_android(self) -> i32 {
+        const TYPE_CLASS_TEXT: i32 = 0x1;
+        const TYPE_CLASS_NUMBER: i32 = 0x2;
+        const TYPE_TEXT_VARIATION_PASSWORD: i32 = 0x80;
+        const TYPE_TEXT_FLAG_MULTI_LINE: i32 = 0x20000;
+
+        match self {
+            Self::Text => TYPE_CLASS_TEXT,
+            Self::Password => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_PASSWORD,
+            Self::Number => TYPE_CLASS_NUMBER,
+            Self::Multiline => TYPE_CLASS_TEXT | TYPE_TEXT_FLAG_MULTI_LINE,
+        }
+    }
+}
+
+fn input_type_id(widget: egui::Id) -> egui::Id {
+    egui::Id::new("native_gl_surface::TextInputType").with(widget)
+}
+
+fn detected_input_type_id(widget: egui::Id) -> egui::Id {
+    egui::Id::new("native_gl_surface::DetectedTextInputType").with(widget)
+}
+
+/// Tells the host which soft keyboard to show while this text field has focus.
+///
+/// Without a hint, password and multiline `TextEdit`s are recognized by
+/// [`detect_input_type`]; every other field gets a plain text keyboard.
+///
+/// ```
+/// # use native_gl_surface::keyboard::{self, TextInputType};
+/// # fn pin_field(ui: &mut egui::Ui, pin: &mut String) {
+/// let response = ui.add(egui::TextEdit::singleline(pin).password(true));
+/// keyboard::set_text_input_type(&response, TextInputType::Password);
+/// # }
+/// ```
+pub fn set_text_input_type(response: &egui::Response, input_type: TextInputType) {
+    response
+        .ctx
+        .data_mut(|d| d.insert_temp(input_type_id(response.id), input_type));
+}
+
+/// Records which keyboard the focused text field needs according to its AccessKit node, for fields
+/// without a [`set_text_input_type`] hint: password fields get [`TextInputType::Password`], so the
+/// IME doesn't suggest or remember what is typed into them.
+///
+/// Call after [`egui::Context::run`] with the frame's [`egui::PlatformOutput::accesskit_update`].
+/// egui only builds that while AccessKit is enabled, so the surface enables it while a text field
+/// has focus.
+pub fn detect_input_type(ctx: &egui::Context, update: &TreeUpdate) {
+    let Some(focused) = ctx.memory(|m| m.focused()) else {
+        return;
+    };
+    let Some((_, node)) = update.nodes.iter().find(|(id, _)| id.0 == focused.value()) else {
+        return;
+    };
+    let input_type = match node.role() {
+        Role::PasswordInput => TextInputType::Password,
+        Role::MultilineTextInput => TextInputType::Multiline,
+        _ => TextInputType::Text,
+    };
+    ctx.data_mut(|d| d.insert_temp(detected_input_type_id(focused), input_type));
+}
+
+/// The host side of the soft keyboard.
+pub trait SoftKeyboard {
+    fn show(&mut self, input_type: TextInputType);
+    fn hide(&mut self);
+}
+
+/// Decides, once per frame, whether the soft keyboard should be up.
+///
+/// Only changes are forwarded, so the host isn't asked to show the keyboard sixty times a second.
+#[derive(Default)]
+pub struct SoftKeyboardState {
+    shown: Option<TextInputType>,
+    /// The field the keyboard was held back for, until [`detect_input_type`] has seen it
+    waited_for: Option<egui::Id>,
+}
+
+impl SoftKeyboardState {
+    /// Call after [`egui::Context::run`] with the frame's [`egui::PlatformOutput`].
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        platform_output: &egui::PlatformOutput,
+        keyboard: &mut dyn SoftKeyboard,
+    ) {
+        // egui only emits IME output while a text field has keyboard focus.
+        let wanted = match (platform_output.ime, ctx.memory(|m| m.focused())) {
+            (Some(_), Some(id)) => {
+                let input_type = ctx.data(|d| {
+                    d.get_temp(input_type_id(id))
+                        .or_else(|| d.get_temp(detected_input_type_id(id)))
+                });
+                if input_type.is_none() && self.waited_for != Some(id) {
+                    // AccessKit is only enabled from the next pass on; give it one frame rather
+                    // than popping up a text keyboard for a password field
+                    self.waited_for = Some(id);
+                    ctx.request_repaint();
+                    return;
+                }
+                Some(input_type.unwrap_or_default())
+            }
+            (Some(_), None) => Some(TextInputType::default()),
+            (None, _) => None,
+        };
+
+        if wanted == self.shown {
+            return;
+        }
+        match wanted {
+            Some(input_type) => keyboard.show(input_type),
+            None => keyboard.hide(),
+        }
+        self.shown = wanted;
+    }
+}
@@ -1,380 +1,1401 @@
-#[deny(unsafe_op_in_unsafe_fn)]
+#![deny(unsafe_op_in_unsafe_fn)]
+pub mod accessibility;
+pub mod app;
+pub mod commands;
+pub mod crash;
+pub mod demo_app;
+pub mod display;
+pub mod gl_info;
+pub mod handles;
+pub mod insets;
+mod jni_host;
+pub mod keyboard;
+pub mod messages;
+pub mod natives;
+pub mod panics;
+pub mod persistence;
+pub mod platform;
+pub mod queue;
+pub mod renderer;
+pub mod repaint;
+pub mod screenshot;
+pub mod stats;
+pub mod surface;
+#[cfg(any(test, feature = "test-support"))]
+pub mod testing;
+pub mod textures;
+pub mod touch;
+pub mod viewports;
+
 use std::ffi::CString;
-use std::{
-    ffi::c_void,
-    sync::{Arc, OnceLock},
-    time::Instant,
-};
+use std::{ffi::c_void, path::Path, sync::Arc};
 
-use android_logger::Config;
-use egui::{
-    ahash::HashMapExt,
-    emath::{RectTransform, Rot2},
-    vec2, Color32, Frame, Pos2, Rect, Sense, Stroke, Vec2,
-};
+use accessibility::{AccessNode, AccessibilityTree};
+
+use crash::CrashReportStore;
+use display::DisplayConfig;
+use egui_logging::LogConfig;
+use gl_info::GlInfo;
+use handles::{HandleError, HandleRegistry};
+use insets::WindowInsets;
 use jni::{
-    objects::JClass,
-    sys::{jint, jlong, JNIEnv},
+    objects::{JByteArray, JClass, JFloatArray, JIntArray, JLongArray, JObject, JString, JValue},
+    sys::{
+        jboolean, jbyteArray, jfloat, jint, jlong, jlongArray, jobject, jobjectArray, jstring,
+        JNI_FALSE, JNI_TRUE,
+    },
+    JNIEnv,
 };
-use log::{info, trace, LevelFilter};
+use jni_host::JniHost;
+use keyboard::{ImeTranslator, SoftKeyboardState};
+use log::{info, trace, warn};
+use messages::Message;
+use natives::NativeClasses;
+use parking_lot::Mutex;
+use persistence::SavedState;
+use platform::PlatformOutputHandler;
+use queue::EventQueue;
+use renderer::{GlowRenderer, RendererError, SurfaceRenderer};
+use repaint::{RepaintSchedule, RepaintSignal, RunMode};
+use surface::{FrameInput, FrameOutput, RustSurface};
+use touch::{MotionEvent, TouchTranslator};
 
-struct AppState {
-    clear_color: [f32; 3],
-    rotation: f32,
-    translation: Vec2,
-    zoom: f32,
-    last_touch_time: f64,
-    name: String,
-    age: u32,
-}
+extern "C" fn on_draw_frame(mut env: JNIEnv, _: JClass, native_surface: jlong) -> jlong {
+    guarded(
+        &mut env,
+        native_surface,
+        RepaintSchedule::Idle.to_millis(),
+        |env| {
+            trace!("onDrawFrame0 called");
+            let Some(native) = NativeSurface::lookup(env, native_surface) else {
+                return RepaintSchedule::Idle.to_millis();
+            };
+            let mut render = native.render.lock();
+            let wrapper = &mut *render;
+            if wrapper.destroyed {
+                return RepaintSchedule::Idle.to_millis();
+            }
+            if let Some(link) = native.viewport {
+                draw_viewport(env, &native, wrapper, link);
+                // The parent asks for the next frame once it has one
+                return RepaintSchedule::Idle.to_millis();
+            }
+            if wrapper.inner.is_none() {
+                info!("Creating lazy RustSurface");
+                let mut surface = RustSurface::for_app_id(&native.app_id);
+                if let Some(state) = wrapper.pending_restore.take() {
+                    info!("Restoring saved state");
+                    state.restore(&surface.egui_ctx, surface.app.as_mut());
+                }
+                repaint::install_repaint_callback(&surface.egui_ctx, wrapper.host.clone());
+                surface.set_viewport_host(Box::new(wrapper.host.clone()));
+                surface.set_command_host(Box::new(wrapper.host.clone()));
+                surface.set_message_host(Box::new(wrapper.host.clone()));
+                wrapper.inner = Some(surface);
+            }
+            let surface = wrapper.inner.as_mut().unwrap();
+            if !surface.has_renderer() {
+                if wrapper.renderer_error.is_some() {
+                    return RepaintSchedule::Idle.to_millis();
+                }
+                match create_renderer(&mut wrapper.gl, surface.app.required_gl_extensions()) {
+                    Ok(renderer) => surface.set_renderer(Box::new(renderer)),
+                    Err(err) => {
+                        wrapper.renderer_failed(err);
+                        return RepaintSchedule::Idle.to_millis();
+                    }
+                }
+            }
 
-impl AppState {
-    fn new() -> Self {
-        Self {
-            clear_color: [1.0, 0.0, 1.0],
-            rotation: 0.,
-            translation: Vec2::ZERO,
-            zoom: 1.,
-            last_touch_time: 0.0,
-            name: String::from("Bob"),
-            age: 69,
-        }
-    }
+            let mut events = native.events.drain();
+            platform::clipboard_events(&mut events, &mut wrapper.host);
 
-    fn draw(&mut self, ctx: &egui::Context) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("My egui Application");
-            ui.horizontal(|ui| {
-                let name_label = ui.label("Your name: ");
-                ui.text_edit_singleline(&mut self.name)
-                    .labelled_by(name_label.id);
-            });
-            ui.add(egui::Slider::new(&mut self.age, 0..=120).text("age"));
-            if ui.button("Increment").clicked() {
-                self.age += 1;
-            }
-            ui.label(format!("Hello '{}', age {}", self.name, self.age));
-
-            ui.color_edit_button_rgb(&mut self.clear_color);
-
-            // ui.image(egui::include_image!(
-            //     "../../../crates/egui/assets/ferris.png"
-            // ));
-            Frame::canvas(ui.style()).show(ui, |ui| {
-                let num_touches = ui.input(|i| i.multi_touch().map_or(0, |mt| mt.num_touches));
-                ui.label(format!("Current touches: {num_touches}"));
-
-                let color = if ui.visuals().dark_mode {
-                    Color32::WHITE
-                } else {
-                    Color32::BLACK
-                };
-
-                // Note that we use `Sense::drag()` although we do not use any pointer events. With
-                // the current implementation, the fact that a touch event of two or more fingers is
-                // recognized, does not mean that the pointer events are suppressed, which are always
-                // generated for the first finger. Therefore, if we do not explicitly consume pointer
-                // events, the window will move around, not only when dragged with a single finger, but
-                // also when a two-finger touch is active. I guess this problem can only be cleanly
-                // solved when the synthetic pointer events are created by egui, and not by the
-                // backend.
-
-                // set up the drawing canvas with normalized coordinates:
-                let (response, painter) =
-                    ui.allocate_painter(ui.available_size_before_wrap(), Sense::drag());
-
-                // normalize painter coordinates to ±1 units in each direction with [0,0] in the center:
-                let painter_proportions = response.rect.square_proportions();
-                let to_screen = RectTransform::from_to(
-                    Rect::from_min_size(Pos2::ZERO - painter_proportions, 2. * painter_proportions),
-                    response.rect,
-                );
-
-                // check for touch input (or the lack thereof) and update zoom and scale factors, plus
-                // color and width:
-                let mut stroke_width = 1.;
-                if let Some(multi_touch) = ui.ctx().multi_touch() {
-                    // This adjusts the current zoom factor and rotation angle according to the dynamic
-                    // change (for the current frame) of the touch gesture:
-                    self.zoom *= multi_touch.zoom_delta;
-                    self.rotation += multi_touch.rotation_delta;
-                    // the translation we get from `multi_touch` needs to be scaled down to the
-                    // normalized coordinates we use as the basis for painting:
-                    self.translation += to_screen.inverse().scale() * multi_touch.translation_delta;
-                    // touch pressure will make the arrow thicker (not all touch devices support this):
-                    stroke_width += 10. * multi_touch.force;
-
-                    self.last_touch_time = ui.input(|i| i.time);
-                } else {
-                    self.slowly_reset(ui);
+            let size_px = wrapper.raw_surface_size.unwrap_or_default();
+            let input = FrameInput {
+                size_px: [size_px.x as u32, size_px.y as u32],
+                display: *native.display.lock(),
+                insets: *native.insets.lock(),
+                events,
+                messages: native.messages.drain(),
+                show_stats: *native.show_stats.lock(),
+                accessibility: *native.accessibility_enabled.lock(),
+            };
+            let FrameOutput {
+                mut platform_output,
+                viewport_output,
+            } = surface.frame(input);
+            *native.gl_info.lock() = surface.gl_info();
+
+            match platform_output.accesskit_update.take() {
+                Some(update) => {
+                    if native.accessibility.lock().update(update) {
+                        wrapper.host.accessibility_changed();
+                    }
                 }
-                let zoom_and_rotate = self.zoom * Rot2::from_angle(self.rotation);
-                let arrow_start_offset = self.translation + zoom_and_rotate * vec2(-0.5, 0.5);
-
-                // Paints an arrow pointing from bottom-left (-0.5, 0.5) to top-right (0.5, -0.5), but
-                // scaled, rotated, and translated according to the current touch gesture:
-                let arrow_start = Pos2::ZERO + arrow_start_offset;
-                let arrow_direction = zoom_and_rotate * vec2(1., -1.);
-                painter.arrow(
-                    to_screen * arrow_start,
-                    to_screen.scale() * arrow_direction,
-                    Stroke::new(stroke_width, color),
-                );
-            });
-        });
-    }
+                // Only sent while a service is listening
+                None => native.accessibility.lock().clear(),
+            }
 
-    fn slowly_reset(&mut self, ui: &egui::Ui) {
-        // This has nothing to do with the touch gesture. It just smoothly brings the
-        // painted arrow back into its original position, for a nice visual effect:
+            // Child viewports run as part of the root's frame, so it has to come as soon as any of
+            // them wants one
+            let mut repaint_delay = std::time::Duration::MAX;
+            for output in viewport_output.values() {
+                repaint_delay = repaint_delay.min(output.repaint_delay);
+            }
+            let mut schedule = RepaintSchedule::from_delay(repaint_delay);
+            if *native.run_mode.lock() == RunMode::Continuous {
+                schedule = RepaintSchedule::Immediately;
+            }
 
-        let time_since_last_touch = (ui.input(|i| i.time) - self.last_touch_time) as f32;
+            wrapper
+                .keyboard
+                .update(&surface.egui_ctx, &platform_output, &mut wrapper.host);
+            wrapper.platform.handle(&platform_output, &mut wrapper.host);
 
-        let delay = 0.5;
-        if time_since_last_touch < delay {
-            ui.ctx().request_repaint();
-        } else {
-            // seconds after which half the amount of zoom/rotation will be reverted:
-            let half_life =
-                egui::remap_clamp(time_since_last_touch, delay..=1.0, 1.0..=0.0).powf(4.0);
-
-            if half_life <= 1e-3 {
-                self.zoom = 1.0;
-                self.rotation = 0.0;
-                self.translation = Vec2::ZERO;
-            } else {
-                let dt = ui.input(|i| i.unstable_dt);
-                let half_life_factor = (-(2_f32.ln()) / half_life * dt).exp();
-                self.zoom = 1. + ((self.zoom - 1.) * half_life_factor);
-                self.rotation *= half_life_factor;
-                self.translation *= half_life_factor;
-                ui.ctx().request_repaint();
+            schedule.to_millis()
+        },
+    )
+}
+
+/// Paints the latest frame the parent surface produced for a child viewport, after handing it
+/// the input collected here.
+fn draw_viewport(
+    env: &mut JNIEnv,
+    native: &NativeSurface,
+    render: &mut RenderState,
+    link: ViewportLink,
+) {
+    // Input stays queued until there is a renderer to show its effect
+    if render.renderer_error.is_some() {
+        return;
+    }
+    let Some(parent) = NativeSurface::lookup(env, link.parent) else {
+        return;
+    };
+    let size_px = render.raw_surface_size.unwrap_or_default();
+    let input = FrameInput {
+        size_px: [size_px.x as u32, size_px.y as u32],
+        display: *native.display.lock(),
+        insets: *native.insets.lock(),
+        events: native.events.drain(),
+        // Posted to the root surface, which runs the app
+        messages: Vec::new(),
+        show_stats: false,
+        accessibility: false,
+    };
+    let new_renderer = render.viewport_renderer.is_none();
+    let (frame, required_extensions) = {
+        let mut parent_render = parent.render.lock();
+        let parent_render = &mut *parent_render;
+        let Some(surface) = parent_render.inner.as_mut() else {
+            return;
+        };
+        let Some(id) = link.find(surface) else {
+            return;
+        };
+        let size_px = input.size_px;
+        let has_events = !input.events.is_empty();
+        surface.set_viewport_input(id, input);
+        let frame = surface.viewport_frame(id, new_renderer);
+        let required_extensions = surface.app.required_gl_extensions().to_vec();
+        // Input and size changes need a new pass, which only the parent can run
+        let stale = frame.as_ref().is_none_or(|frame| frame.size_px != size_px);
+        if stale || has_events {
+            parent_render
+                .host
+                .request_repaint(RepaintSchedule::Immediately);
+        }
+        (frame, required_extensions)
+    };
+    // Only created along with the first frame, which brings every texture
+    let Some(frame) = frame else {
+        return;
+    };
+    if render.viewport_renderer.is_none() {
+        match create_renderer(&mut render.gl, &required_extensions) {
+            Ok(renderer) => render.viewport_renderer = Some(Box::new(renderer)),
+            Err(err) => {
+                link.renderer_lost(&parent);
+                return render.renderer_failed(err);
             }
         }
     }
+    if let Some(renderer) = &mut render.viewport_renderer {
+        frame.paint(renderer.as_mut());
+    }
 }
 
-#[no_mangle]
-pub extern "C" fn Java_com_foxhunter_egui_1view_ui_NativeGLRenderer_onDrawFrame0(
-    _: JNIEnv,
-    _: JClass,
-    native_surface: jlong,
-) {
-    trace!("onDrawFrame0 called");
-    let ptr = native_surface as usize as *mut NativeSurface;
-    let wrapper = unsafe { &mut *ptr };
-    if wrapper.inner.is_none() {
-        info!("Creating lazy RustSurface");
-        wrapper.inner = Some(RustSurface::new());
+/// Ties the surface of a child viewport to the surface whose app opened it.
+#[derive(Clone, Copy)]
+struct ViewportLink {
+    /// Handle of the parent [`NativeSurface`]
+    parent: jlong,
+    /// [`viewport_key`] of the viewport
+    key: jlong,
+}
+
+impl ViewportLink {
+    /// Tells `parent` this viewport has no renderer any more, so it stops keeping texture
+    /// changes for it.
+    fn renderer_lost(&self, parent: &NativeSurface) {
+        let mut render = parent.render.lock();
+        if let Some(surface) = render.inner.as_mut() {
+            if let Some(id) = self.find(surface) {
+                surface.viewport_renderer_lost(id);
+            }
+        }
     }
-    let surface = wrapper.inner.as_mut().unwrap();
-
-    // The size of the surface, in points
-    let surface_size = wrapper
-        .raw_surface_size
-        .map(|s| s / wrapper.native_pixels_per_point)
-        .unwrap_or_default();
-
-    let mut viewports = egui::viewport::ViewportIdMap::new();
-    viewports.insert(
-        egui::ViewportId::ROOT,
-        egui::ViewportInfo {
-            native_pixels_per_point: Some(wrapper.native_pixels_per_point),
-            monitor_size: Some(surface_size),
-            focused: Some(true),
-            ..Default::default()
-        },
-    );
-
-    let raw_input = egui::RawInput {
-        viewport_id: egui::ViewportId::ROOT,
-        viewports,
-        // TODO: Obtain value
-        max_texture_side: None,
-        screen_rect: Some(Rect::from_min_size(Default::default(), surface_size)),
-        time: Some(surface.timer.elapsed().as_secs_f64()),
-        ..Default::default()
-    };
 
-    let egui::FullOutput {
-        platform_output: _,
-        mut textures_delta,
-        shapes,
-        pixels_per_point,
-        viewport_output,
-    } = surface
-        .egui_ctx
-        .run(raw_input, |ctx| surface.app_state.draw(ctx));
-
-    assert!(
-        viewport_output.len() <= 1,
-        "Multiple viewports not yet supported by EguiGlow"
-    );
-
-    for (_, egui::ViewportOutput { commands: _, .. }) in viewport_output {
-        // TODO: handle
+    fn find(&self, surface: &RustSurface) -> Option<egui::ViewportId> {
+        surface
+            .viewport_ids()
+            .into_iter()
+            .find(|&id| viewport_key(id) == self.key)
     }
+}
 
-    // self.egui_winit.handle_platform_output(window, platform_output);
+/// How a viewport is identified to Kotlin.
+pub(crate) fn viewport_key(id: egui::ViewportId) -> jlong {
+    id.0.value() as jlong
+}
 
-    let window_size = wrapper.raw_surface_size.unwrap_or_default();
-    let window_size = [window_size.x as u32, window_size.y as u32];
-    let cc = surface.app_state.clear_color;
-    let clear_color = [cc[0], cc[1], cc[2], 1.0];
-    surface.painter.clear(window_size, clear_color);
+/// State mostly touched by the GL render thread. The UI thread also locks it to save and restore
+/// state and to destroy the surface, and child viewports lock their parent's from their own
+/// render threads, so keep each lock short.
+struct RenderState {
+    inner: Option<RustSurface>,
+    /// Set once the handle is destroyed, in case a frame was already in flight
+    destroyed: bool,
+    raw_surface_size: Option<egui::Vec2>,
+    host: JniHost,
+    keyboard: SoftKeyboardState,
+    platform: PlatformOutputHandler,
+    /// State to restore once `inner` is created
+    pending_restore: Option<SavedState>,
+    /// Paints the parent's frames on the surface of a child viewport. Unused otherwise.
+    viewport_renderer: Option<Box<dyn SurfaceRenderer>>,
+    /// Why the renderer couldn't be created. No frames are drawn until the host retries or the
+    /// EGL context is recreated.
+    renderer_error: Option<RendererError>,
+    /// GL functions of this surface's EGL context, loaded along with the first renderer
+    gl: Option<Arc<glow::Context>>,
+}
 
-    for (id, image_delta) in textures_delta.set {
-        info!("Setting texture: {id:?}");
-        surface.painter.set_texture(id, &image_delta);
+impl RenderState {
+    fn save_state(&mut self) -> Option<SavedState> {
+        match &mut self.inner {
+            Some(surface) => Some(SavedState::capture(&surface.egui_ctx, surface.app.as_mut())),
+            // Not shown yet, so nothing can have changed since the restore
+            None => self.pending_restore.clone(),
+        }
     }
 
-    let clipped_primitives = surface.egui_ctx.tessellate(shapes, pixels_per_point);
-    surface
-        .painter
-        .paint_primitives(window_size, pixels_per_point, &clipped_primitives);
+    fn restore_state(&mut self, state: SavedState) {
+        match &mut self.inner {
+            Some(surface) => state.restore(&surface.egui_ctx, surface.app.as_mut()),
+            None => self.pending_restore = Some(state),
+        }
+    }
 
-    for id in textures_delta.free.drain(..) {
-        surface.painter.free_texture(id);
+    /// Remembers why the renderer couldn't be created and tells the host, which may show a
+    /// fallback or retry.
+    fn renderer_failed(&mut self, err: RendererError) {
+        log::error!("Failed to create the renderer: {err}");
+        self.host.renderer_failed(&err);
+        self.renderer_error = Some(err);
     }
-}
 
-struct RustSurface {
-    egui_ctx: egui::Context,
-    painter: egui_glow::Painter,
-    timer: Instant,
-    app_state: AppState,
+    /// Drops everything that lived in the EGL context after it was destroyed. Makes no GL calls,
+    /// so it can run on any thread.
+    fn on_context_lost(&mut self) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.on_context_lost();
+        }
+        if let Some(renderer) = self.viewport_renderer.take() {
+            renderer.abandon();
+        }
+        self.gl = None;
+        // The next context may well work
+        self.renderer_error = None;
+    }
 }
 
-impl RustSurface {
-    pub fn new() -> Self {
-        let gl = get_glow_context();
+/// The object behind the `jlong` handle held by the Kotlin side.
+///
+/// Entry points run on both the Android UI thread and the GL render thread, so they only ever
+/// get a shared reference to this; anything mutable is behind a lock.
+struct NativeSurface {
+    render: Mutex<RenderState>,
+    /// Which registered [`SurfaceApp`] to show
+    app_id: String,
+    /// Updated from the UI thread whenever Android's configuration changes
+    display: Mutex<DisplayConfig>,
+    /// Updated from the UI thread whenever the window insets change
+    insets: Mutex<WindowInsets>,
+    run_mode: Mutex<RunMode>,
+    /// Whether to draw the frame time graph over the app
+    show_stats: Mutex<bool>,
+    /// Whether an accessibility service is enabled, so egui should build its AccessKit tree
+    accessibility_enabled: Mutex<bool>,
+    /// The tree from the last frame, for the UI thread's `AccessibilityNodeProvider`
+    accessibility: Mutex<AccessibilityTree>,
+    /// Copy of the render thread's [`RustSurface::gl_info`], readable from any thread
+    gl_info: Mutex<Option<Arc<GlInfo>>>,
+    touch: Mutex<TouchTranslator>,
+    ime: Mutex<ImeTranslator>,
+    /// Input collected since the last frame
+    events: EventQueue,
+    /// Messages from Kotlin for the app's next frame
+    messages: EventQueue<Message>,
+    /// Set if this surface shows a child viewport of another one
+    viewport: Option<ViewportLink>,
+}
 
+impl NativeSurface {
+    pub fn new(host: JniHost, app_id: String, display: DisplayConfig) -> Self {
         Self {
-            egui_ctx: egui::Context::default(),
-            timer: Instant::now(),
-            painter: egui_glow::Painter::new(Arc::clone(gl), "", None, false)
-                .expect("Failed to create glow painter"),
-            app_state: AppState::new(),
+            app_id,
+            render: Mutex::new(RenderState {
+                inner: None,
+                destroyed: false,
+                raw_surface_size: None,
+                host,
+                keyboard: SoftKeyboardState::default(),
+                platform: PlatformOutputHandler::default(),
+                pending_restore: None,
+                viewport_renderer: None,
+                renderer_error: None,
+                gl: None,
+            }),
+            display: Mutex::new(display),
+            insets: Mutex::new(WindowInsets::default()),
+            run_mode: Mutex::new(RunMode::default()),
+            show_stats: Mutex::new(false),
+            accessibility_enabled: Mutex::new(false),
+            accessibility: Mutex::default(),
+            gl_info: Mutex::new(None),
+            touch: Mutex::new(TouchTranslator::default()),
+            ime: Mutex::new(ImeTranslator::default()),
+            events: EventQueue::default(),
+            messages: EventQueue::default(),
+            viewport: None,
         }
     }
+
+    /// The encoded [`SavedState`], or `None` if there is nothing to save or it failed.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        let state = self.render.lock().save_state()?;
+        state
+            .to_bytes()
+            .inspect_err(|err| warn!("Failed to save state: {err}"))
+            .ok()
+    }
+
+    /// Queues `message` for the app. Child viewports don't run the app, so they drop it.
+    fn post_message(&self, message: Message) {
+        if self.viewport.is_some() {
+            warn!(
+                "Dropping message {:?} posted to a child viewport",
+                message.topic
+            );
+            return;
+        }
+        self.messages.push(message);
+    }
+
+    /// Resolves a handle passed in from Kotlin.
+    fn lookup(env: &mut JNIEnv, handle: jlong) -> Option<Arc<Self>> {
+        let result = SURFACES.lock().get(handle);
+        result
+            .inspect_err(|&err| report_handle_error(env, err, handle))
+            .ok()
+    }
 }
 
-#[derive(Default)]
-struct NativeSurface {
-    inner: Option<RustSurface>,
-    raw_surface_size: Option<egui::Vec2>,
-    native_pixels_per_point: f32,
+/// Stale and zero handles are expected while a view is being torn down, so those are only
+/// logged. A handle that was never issued means something is badly wrong on the Kotlin side and
+/// throws `IllegalArgumentException`.
+fn report_handle_error(env: &mut JNIEnv, err: HandleError, handle: jlong) {
+    match err {
+        HandleError::Null | HandleError::Stale | HandleError::Poisoned => {
+            warn!("Ignoring call on {err} {handle:#x}")
+        }
+        HandleError::Invalid => {
+            log::error!("Called with {err} {handle:#x}");
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("Invalid native surface handle {handle:#x}"),
+            );
+        }
+    }
 }
 
-impl NativeSurface {
-    pub fn new() -> Self {
-        Self {
-            inner: None,
-            raw_surface_size: None,
-            native_pixels_per_point: 3.0,
+static SURFACES: Mutex<HandleRegistry<NativeSurface>> = Mutex::new(HandleRegistry::new());
+
+/// Runs the body of an entry point, throwing a `RuntimeException` and returning `fallback` if it
+/// panics.
+fn catch_panics<'local, R>(
+    env: &mut JNIEnv<'local>,
+    fallback: R,
+    body: impl FnOnce(&mut JNIEnv<'local>) -> R,
+) -> R {
+    panics::catch(|| body(env)).unwrap_or_else(|report| {
+        throw_panic(env, &report);
+        fallback
+    })
+}
+
+/// Like [`catch_panics`] for an entry point working on `native_surface`, which is poisoned if
+/// the body panics: its state may be half-updated, so every later call on it is refused.
+fn guarded<'local, R>(
+    env: &mut JNIEnv<'local>,
+    native_surface: jlong,
+    fallback: R,
+    body: impl FnOnce(&mut JNIEnv<'local>) -> R,
+) -> R {
+    panics::catch(|| body(env)).unwrap_or_else(|report| {
+        log::error!("Poisoning native surface {native_surface:#x}");
+        if let Err(err) = SURFACES.lock().poison(native_surface) {
+            warn!("Failed to poison {native_surface:#x}: {err}");
         }
+        throw_panic(env, &report);
+        fallback
+    })
+}
+
+fn throw_panic(env: &mut JNIEnv, report: &panics::PanicReport) {
+    // A JNI call that failed may have left its own exception pending
+    if env.exception_check().unwrap_or(false) {
+        let _ = env.exception_clear();
+    }
+    if let Err(err) = env.throw_new("java/lang/RuntimeException", report.to_string()) {
+        log::error!("Failed to throw for {report}: {err}");
     }
 }
 
-#[no_mangle]
-pub extern "C" fn Java_com_foxhunter_egui_1view_ui_NativeGLRenderer_onSurfaceCreated0(
-    _: JNIEnv,
-    _: JClass,
-    _native_surface: jlong,
-) {
-    info!("onSurfaceCreated0 called");
+extern "C" fn on_surface_created(mut env: JNIEnv, _: JClass, native_surface: jlong) {
+    guarded(&mut env, native_surface, (), |env| {
+        info!("onSurfaceCreated0 called");
+        // GLSurfaceView only calls this with a brand new EGL context
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        info!("EGL context was recreated, rebuilding GL resources");
+        surface.render.lock().on_context_lost();
+        if let Some(link) = surface.viewport {
+            if let Some(parent) = NativeSurface::lookup(env, link.parent) {
+                link.renderer_lost(&parent);
+            }
+        }
+    })
 }
 
-#[no_mangle]
-pub extern "C" fn Java_com_foxhunter_egui_1view_ui_NativeGLRenderer_onSurfaceChanged0(
-    _: JNIEnv,
+/// Tries to create the renderer again on the next frame, after `onRendererFailed`.
+extern "C" fn retry_renderer(mut env: JNIEnv, _: JClass, native_surface: jlong) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let mut render = surface.render.lock();
+        if render.renderer_error.take().is_some() {
+            info!("Retrying renderer creation");
+            render.host.request_repaint(RepaintSchedule::Immediately);
+        }
+    })
+}
+
+extern "C" fn on_surface_changed(
+    mut env: JNIEnv,
     _: JClass,
     native_surface: jlong,
     width: jint,
     height: jint,
 ) {
-    info!("onSurfaceChanged0 called: width: {width}, height: {height}");
-    let ptr = native_surface as usize as *mut NativeSurface;
-    let surface = unsafe { &mut *ptr };
-    surface.raw_surface_size = Some(egui::Vec2::new(width as f32, height as f32));
-    info!("onSurfaceChanged0 done");
+    guarded(&mut env, native_surface, (), |env| {
+        info!("onSurfaceChanged0 called: width: {width}, height: {height}");
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        surface.render.lock().raw_surface_size = Some(egui::Vec2::new(width as f32, height as f32));
+        info!("onSurfaceChanged0 done");
+    })
 }
 
-#[no_mangle]
-pub extern "C" fn Java_com_foxhunter_egui_1view_ui_NativeGLSurfaceView_createNativeSurface0(
-    _: JNIEnv,
+extern "C" fn create_native_surface(
+    mut env: JNIEnv,
     _: JClass,
+    view: JObject,
+    app_id: JString,
+    density: jfloat,
+    font_scale: jfloat,
+    night_mode: jboolean,
 ) -> jlong {
-    info!("createNativeSurface called");
+    catch_panics(&mut env, 0, |env| {
+        info!("createNativeSurface called");
 
-    let ptr: *mut NativeSurface = Box::into_raw(Box::new(NativeSurface::new()));
-    info!("Allocated native surface {ptr:?}");
-    ptr as usize as jlong
-    //
+        let Some(app_id) = read_string(env, &app_id) else {
+            return 0;
+        };
+        if !app::is_registered(&app_id) {
+            log::error!("No app registered with id {app_id:?}");
+            let _ = env.throw_new(
+                "java/lang/IllegalArgumentException",
+                format!("No egui app registered with id \"{app_id}\""),
+            );
+            return 0;
+        }
+
+        let host = match JniHost::new(env, &view) {
+            Ok(host) => host,
+            Err(err) => {
+                log::error!("Failed to reference the host view: {err}");
+                return 0;
+            }
+        };
+        let display = DisplayConfig::new(density, font_scale, night_mode != 0);
+        let handle = SURFACES
+            .lock()
+            .insert(NativeSurface::new(host, app_id, display));
+        info!("Allocated native surface {handle:#x}");
+        handle
+    })
 }
 
-#[no_mangle]
-pub extern "C" fn Java_com_foxhunter_egui_1view_ui_NativeGLSurfaceView_destroyNativeSurface0(
-    _: JNIEnv,
+extern "C" fn destroy_native_surface(mut env: JNIEnv, _: JClass, native_surface: jlong) {
+    guarded(&mut env, native_surface, (), |env| {
+        info!("Destroying native surface {native_surface:#x}");
+        let result = SURFACES.lock().remove(native_surface);
+        let surface = match result {
+            Ok(surface) => surface,
+            Err(err) => {
+                report_handle_error(env, err, native_surface);
+                return;
+            }
+        };
+
+        // A frame may still be running on the render thread with its own reference; wait for it,
+        // then make sure no later frame touches the renderer. The view stopped its render thread
+        // before calling this, so the EGL context is gone and this isn't the GL thread.
+        let mut render = surface.render.lock();
+        render.destroyed = true;
+        render.on_context_lost();
+        render.inner = None;
+    })
+}
+
+/// Creates the surface for a child viewport that `parent` asked the view to open with
+/// `openViewport`. Input sent to it goes to that viewport.
+#[allow(clippy::too_many_arguments)]
+extern "C" fn create_viewport_surface(
+    mut env: JNIEnv,
+    _: JClass,
+    view: JObject,
+    parent: jlong,
+    viewport_key: jlong,
+    density: jfloat,
+    font_scale: jfloat,
+    night_mode: jboolean,
+) -> jlong {
+    catch_panics(&mut env, 0, |env| {
+        let Some(parent_surface) = NativeSurface::lookup(env, parent) else {
+            return 0;
+        };
+        let host = match JniHost::new(env, &view) {
+            Ok(host) => host,
+            Err(err) => {
+                log::error!("Failed to reference the viewport view: {err}");
+                return 0;
+            }
+        };
+        let display = DisplayConfig::new(density, font_scale, night_mode != 0);
+        let surface = NativeSurface {
+            viewport: Some(ViewportLink {
+                parent,
+                key: viewport_key,
+            }),
+            ..NativeSurface::new(host, parent_surface.app_id.clone(), display)
+        };
+        let handle = SURFACES.lock().insert(surface);
+        info!("Allocated viewport surface {handle:#x} for {viewport_key:#x} of {parent:#x}");
+        handle
+    })
+}
+
+/// The user dismissed the dialog of a child viewport. The app decides whether it actually closes.
+extern "C" fn on_viewport_close_requested(mut env: JNIEnv, _: JClass, native_surface: jlong) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let Some(link) = surface.viewport else {
+            warn!("Close requested on {native_surface:#x}, which isn't a viewport");
+            return;
+        };
+        let Some(parent) = NativeSurface::lookup(env, link.parent) else {
+            return;
+        };
+        let mut render = parent.render.lock();
+        let render = &mut *render;
+        let Some(inner) = render.inner.as_mut() else {
+            return;
+        };
+        if let Some(id) = link.find(inner) {
+            inner.request_viewport_close(id);
+            render.host.request_repaint(RepaintSchedule::Immediately);
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn on_touch_event(
+    mut env: JNIEnv,
     _: JClass,
     native_surface: jlong,
+    action: jint,
+    action_index: jint,
+    device_id: jint,
+    pointer_ids: JIntArray,
+    times: JLongArray,
+    samples: JFloatArray,
 ) {
-    let ptr = native_surface as usize as *mut NativeSurface;
-    info!("Destroying native surface {ptr:?}");
-    let mut surface = unsafe { Box::from_raw(ptr) };
+    guarded(&mut env, native_surface, (), |env| {
+        trace!("onTouchEvent0 called");
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
 
-    if let Some(inner) = surface.inner.as_mut() {
-        inner.painter.destroy();
-    }
+        let read = || -> jni::errors::Result<_> {
+            let mut ids = vec![0; env.get_array_length(&pointer_ids)? as usize];
+            env.get_int_array_region(&pointer_ids, 0, &mut ids)?;
+            let mut time_buf = vec![0; env.get_array_length(&times)? as usize];
+            env.get_long_array_region(&times, 0, &mut time_buf)?;
+            let mut sample_buf = vec![0.0; env.get_array_length(&samples)? as usize];
+            env.get_float_array_region(&samples, 0, &mut sample_buf)?;
+            Ok((ids, time_buf, sample_buf))
+        };
+        let (ids, time_buf, sample_buf) = match read() {
+            Ok(arrays) => arrays,
+            Err(err) => {
+                warn!("Failed to read touch event arrays: {err}");
+                return;
+            }
+        };
+
+        let Some(event) = MotionEvent::from_packed(
+            action,
+            action_index,
+            device_id,
+            &ids,
+            &time_buf,
+            &sample_buf,
+        ) else {
+            warn!("Dropping malformed touch event");
+            return;
+        };
+        let pixels_per_point = surface.display.lock().pixels_per_point();
+        let mut events = Vec::new();
+        surface
+            .touch
+            .lock()
+            .translate(&event, pixels_per_point, &mut events);
+        surface.events.extend(events);
+    })
+}
+
+extern "C" fn set_continuous_rendering(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    continuous: jboolean,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        *surface.run_mode.lock() = if continuous != 0 {
+            RunMode::Continuous
+        } else {
+            RunMode::Reactive
+        };
+    })
 }
 
-static GL_FUNCTIONS: std::sync::OnceLock<Arc<glow::Context>> = OnceLock::new();
+/// Called from `View.onConfigurationChanged`, e.g. when the user changes the font size or dark
+/// mode while the view is alive.
+extern "C" fn on_configuration_changed(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    density: jfloat,
+    font_scale: jfloat,
+    night_mode: jboolean,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let display = DisplayConfig::new(density, font_scale, night_mode != 0);
+        info!("Display configuration changed: {display:?}");
+        *surface.display.lock() = display;
+    })
+}
+
+/// Called whenever the system bars, display cutout or soft keyboard cover a different part of the
+/// view. See [`WindowInsets::from_packed`] for the layout of `insets`.
+extern "C" fn on_window_insets_changed(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    insets: JIntArray,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let read = || -> jni::errors::Result<_> {
+            let mut values = vec![0; env.get_array_length(&insets)? as usize];
+            env.get_int_array_region(&insets, 0, &mut values)?;
+            Ok(values)
+        };
+        let values = match read() {
+            Ok(values) => values,
+            Err(err) => {
+                warn!("Failed to read window insets: {err}");
+                return;
+            }
+        };
+        let Some(insets) = WindowInsets::from_packed(&values) else {
+            warn!("Dropping malformed window insets");
+            return;
+        };
+        trace!("Window insets changed: {insets:?}");
+        *surface.insets.lock() = insets;
+    })
+}
 
-pub fn get_glow_context() -> &'static Arc<glow::Context> {
-    GL_FUNCTIONS.get_or_init(|| {
-        info!("Creating glow wrapper");
-        fn load_gl_func(symbol_name: &str) -> *const c_void {
-            let c_str = CString::new(symbol_name).unwrap();
-            // SAFETY: function provided by android
-            unsafe { eglGetProcAddress(c_str.as_ptr().cast()) }
+/// Serializes the app and egui's memory for `onSaveInstanceState`. Returns `null` on failure.
+extern "C" fn save_state(mut env: JNIEnv, _: JClass, native_surface: jlong) -> jbyteArray {
+    guarded(&mut env, native_surface, std::ptr::null_mut(), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return std::ptr::null_mut();
+        };
+        let Some(bytes) = surface.save_state() else {
+            return std::ptr::null_mut();
+        };
+        match env.byte_array_from_slice(&bytes) {
+            Ok(array) => array.into_raw(),
+            Err(err) => {
+                warn!("Failed to hand saved state to Java: {err}");
+                std::ptr::null_mut()
+            }
         }
-        let glow_context = unsafe { glow::Context::from_loader_function(load_gl_func) };
-        Arc::new(glow_context)
     })
 }
 
-extern "C" {
-    fn eglGetProcAddress(procname: *const i8) -> *const c_void;
+/// Restores a blob from `saveState0`. Applied before the first frame if the app isn't running yet.
+extern "C" fn restore_state(mut env: JNIEnv, _: JClass, native_surface: jlong, state: JByteArray) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let bytes = match env.convert_byte_array(&state) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Failed to read saved state: {err}");
+                return;
+            }
+        };
+        match SavedState::from_bytes(&bytes) {
+            Ok(state) => surface.render.lock().restore_state(state),
+            Err(err) => warn!("Ignoring saved state: {err}"),
+        }
+    })
 }
 
-#[no_mangle]
-pub extern "C" fn JNI_OnLoad(vm: jni::JavaVM, res: *mut std::os::raw::c_void) -> jni::sys::jint {
-    android_logger::init_once(
-        Config::default()
-            .with_tag("com.foxhunter.egui_view")
-            .format(|f, record| write!(f, "jni: {}", record.args()))
-            .with_max_level(LevelFilter::Debug),
-    );
+/// Writes the same blob as `saveState0` to `path`. Returns whether it succeeded.
+extern "C" fn save_state_to_file(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    path: JString,
+) -> jboolean {
+    guarded(&mut env, native_surface, JNI_FALSE, |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return JNI_FALSE;
+        };
+        let Some(path) = read_string(env, &path) else {
+            return JNI_FALSE;
+        };
+        let Some(bytes) = surface.save_state() else {
+            return JNI_FALSE;
+        };
+        match persistence::write_file(Path::new(&path), &bytes) {
+            Ok(()) => JNI_TRUE,
+            Err(err) => {
+                warn!("Failed to write state to {path}: {err}");
+                JNI_FALSE
+            }
+        }
+    })
+}
+
+/// Restores state written by `saveStateToFile0`, if `path` exists.
+extern "C" fn restore_state_from_file(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    path: JString,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let Some(path) = read_string(env, &path) else {
+            return;
+        };
+        match persistence::read_file(Path::new(&path)) {
+            Ok(Some(state)) => surface.render.lock().restore_state(state),
+            Ok(None) => {}
+            Err(err) => warn!("Ignoring state file {path}: {err}"),
+        }
+    })
+}
+
+extern "C" fn set_stats_overlay(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    visible: jboolean,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        *surface.show_stats.lock() = visible != 0;
+    })
+}
+
+/// Returns the recent frame history as [`stats::FrameStats::to_packed`] records, oldest first,
+/// or `null` on failure.
+extern "C" fn get_frame_stats(mut env: JNIEnv, _: JClass, native_surface: jlong) -> jlongArray {
+    guarded(&mut env, native_surface, std::ptr::null_mut(), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return std::ptr::null_mut();
+        };
+        let packed = match &surface.render.lock().inner {
+            Some(inner) => inner.stats().to_packed(),
+            None => Vec::new(),
+        };
+        let array = env.new_long_array(packed.len() as i32).and_then(|array| {
+            env.set_long_array_region(&array, 0, &packed)?;
+            Ok(array)
+        });
+        match array {
+            Ok(array) => array.into_raw(),
+            Err(err) => {
+                warn!("Failed to hand frame stats to Java: {err}");
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns a description of the surface's GL context, or `null` before the first frame.
+extern "C" fn get_gl_diagnostics(mut env: JNIEnv, _: JClass, native_surface: jlong) -> jstring {
+    guarded(&mut env, native_surface, std::ptr::null_mut(), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return std::ptr::null_mut();
+        };
+        let Some(info) = surface.gl_info.lock().clone() else {
+            return std::ptr::null_mut();
+        };
+        match env.new_string(info.summary()) {
+            Ok(text) => text.into_raw(),
+            Err(err) => {
+                warn!("Failed to create diagnostics string: {err}");
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+extern "C" fn set_accessibility_enabled(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    enabled: jboolean,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        *surface.accessibility_enabled.lock() = enabled != 0;
+    })
+}
+
+/// Returns an `AccessibilityNode` describing `virtualId` in the last frame, or `null` if it
+/// isn't shown.
+extern "C" fn get_accessibility_node(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    virtual_id: jint,
+) -> jobject {
+    guarded(&mut env, native_surface, std::ptr::null_mut(), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return std::ptr::null_mut();
+        };
+        let Some(node) = surface.accessibility.lock().node(virtual_id).cloned() else {
+            return std::ptr::null_mut();
+        };
+        match accessibility_node_to_java(env, &node) {
+            Ok(node) => node.into_raw(),
+            Err(err) => {
+                warn!("Failed to hand accessibility node to Java: {err}");
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Must match the `AccessibilityNode` constructor in Kotlin.
+fn accessibility_node_to_java<'local>(
+    env: &mut JNIEnv<'local>,
+    node: &AccessNode,
+) -> jni::errors::Result<JObject<'local>> {
+    let children = env.new_int_array(node.children.len() as i32)?;
+    env.set_int_array_region(&children, 0, &node.children)?;
+    let actions = env.new_int_array(node.actions.len() as i32)?;
+    env.set_int_array_region(&actions, 0, &node.actions)?;
+    let class_name = env.new_string(node.class_name())?;
+    let label = match &node.label {
+        Some(label) => JObject::from(env.new_string(label)?),
+        None => JObject::null(),
+    };
+    let value = match &node.value {
+        Some(value) => JObject::from(env.new_string(value)?),
+        None => JObject::null(),
+    };
+    let range = match node.range {
+        Some(range) => {
+            let array = env.new_float_array(3)?;
+            let values = [range.current, range.min, range.max].map(|value| value as f32);
+            env.set_float_array_region(&array, 0, &values)?;
+            JObject::from(array)
+        }
+        None => JObject::null(),
+    };
+    let bounds = node.bounds.unwrap_or(egui::Rect::ZERO);
+    let Some(class) = natives::accessibility_node_class() else {
+        return Ok(JObject::null());
+    };
+    env.new_object(
+        <&JClass>::from(class.as_obj()),
+        "(I[ILjava/lang/String;Ljava/lang/String;Ljava/lang/String;IIIII[I[F)V",
+        &[
+            JValue::Int(node.parent.unwrap_or(accessibility::INVALID_ID)),
+            JValue::Object(&children),
+            JValue::Object(&class_name),
+            JValue::Object(&label),
+            JValue::Object(&value),
+            JValue::Int(bounds.left().round() as i32),
+            JValue::Int(bounds.top().round() as i32),
+            JValue::Int(bounds.right().round() as i32),
+            JValue::Int(bounds.bottom().round() as i32),
+            JValue::Int(node.packed_flags()),
+            JValue::Object(&actions),
+            JValue::Object(&range),
+        ],
+    )
+}
+
+/// Returns the innermost node at (`x`, `y`) in view pixels, or `INVALID_ID`.
+extern "C" fn find_accessibility_node_at(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    x: jfloat,
+    y: jfloat,
+) -> jint {
+    guarded(&mut env, native_surface, accessibility::INVALID_ID, |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return accessibility::INVALID_ID;
+        };
+        let tree = surface.accessibility.lock();
+        tree.node_at(egui::pos2(x, y))
+            .unwrap_or(accessibility::INVALID_ID)
+    })
+}
+
+/// Queues an accessibility action for egui's next frame. `argument` is NaN for actions without
+/// one. Returns whether the node supports the action.
+extern "C" fn perform_accessibility_action(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    virtual_id: jint,
+    action: jint,
+    argument: jfloat,
+) -> jboolean {
+    guarded(&mut env, native_surface, JNI_FALSE, |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return JNI_FALSE;
+        };
+        let argument = (!argument.is_nan()).then_some(f64::from(argument));
+        let request = surface
+            .accessibility
+            .lock()
+            .action_request(virtual_id, action, argument);
+        match request {
+            Some(request) => {
+                surface
+                    .events
+                    .extend([egui::Event::AccessKitActionRequest(request)]);
+                JNI_TRUE
+            }
+            None => JNI_FALSE,
+        }
+    })
+}
+
+/// Paints the last frame again, scaled to fit `width` x `height` pixels, and returns it as a PNG,
+/// or `null` if nothing has been drawn yet or it failed. See
+/// [`RustSurface::repaint_last_frame_scaled`] for why this isn't a new layout at that size. Must
+/// be called on the render thread.
+extern "C" fn render_to_png(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    width: jint,
+    height: jint,
+) -> jbyteArray {
+    guarded(&mut env, native_surface, std::ptr::null_mut(), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return std::ptr::null_mut();
+        };
+        let (Ok(width), Ok(height)) = (u32::try_from(width), u32::try_from(height)) else {
+            warn!("Invalid image size {width}x{height}");
+            return std::ptr::null_mut();
+        };
+        let image = {
+            let mut render = surface.render.lock();
+            if render.destroyed {
+                return std::ptr::null_mut();
+            }
+            render
+                .inner
+                .as_mut()
+                .and_then(|inner| inner.repaint_last_frame_scaled([width, height]))
+        };
+        let Some(image) = image else {
+            return std::ptr::null_mut();
+        };
+        let bytes = match screenshot::encode_png(&image) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Failed to encode image: {err}");
+                return std::ptr::null_mut();
+            }
+        };
+        match env.byte_array_from_slice(&bytes) {
+            Ok(array) => array.into_raw(),
+            Err(err) => {
+                warn!("Failed to hand image to Java: {err}");
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+extern "C" fn on_key_event(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    action: jint,
+    key_code: jint,
+    meta_state: jint,
+    unicode_char: jint,
+    repeat_count: jint,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        trace!("onKeyEvent0 called: action: {action}, key_code: {key_code}");
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let mut events = Vec::new();
+        keyboard::key_events(
+            action,
+            key_code,
+            meta_state,
+            unicode_char,
+            repeat_count,
+            &mut events,
+        );
+        surface.events.extend(events);
+    })
+}
 
-    std::panic::set_hook(Box::new(|info| {
-        let (file, line) = {
-            if let Some(location) = info.location() {
-                (location.file(), location.line())
-            } else {
-                ("<unknown>", 0)
+extern "C" fn on_commit_text(mut env: JNIEnv, _: JClass, native_surface: jlong, text: JString) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let Some(text) = read_string(env, &text) else {
+            return;
+        };
+        let mut events = Vec::new();
+        surface.ime.lock().commit_text(&text, &mut events);
+        surface.events.extend(events);
+    })
+}
+
+extern "C" fn on_composing_text(mut env: JNIEnv, _: JClass, native_surface: jlong, text: JString) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let Some(text) = read_string(env, &text) else {
+            return;
+        };
+        let mut events = Vec::new();
+        surface.ime.lock().set_composing_text(&text, &mut events);
+        surface.events.extend(events);
+    })
+}
+
+extern "C" fn on_finish_composing_text(mut env: JNIEnv, _: JClass, native_surface: jlong) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let mut events = Vec::new();
+        surface.ime.lock().finish_composing_text(&mut events);
+        surface.events.extend(events);
+    })
+}
+
+/// Queues a JSON message for the app's next frame.
+extern "C" fn post_json_message(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    topic: JString,
+    json: JString,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let (Some(topic), Some(json)) = (read_string(env, &topic), read_string(env, &json)) else {
+            return;
+        };
+        surface.post_message(Message::json(topic, json));
+    })
+}
+
+/// Queues a message with a byte payload for the app's next frame.
+extern "C" fn post_bytes_message(
+    mut env: JNIEnv,
+    _: JClass,
+    native_surface: jlong,
+    topic: JString,
+    bytes: JByteArray,
+) {
+    guarded(&mut env, native_surface, (), |env| {
+        let Some(surface) = NativeSurface::lookup(env, native_surface) else {
+            return;
+        };
+        let Some(topic) = read_string(env, &topic) else {
+            return;
+        };
+        let bytes = match env.convert_byte_array(&bytes) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Failed to read message bytes: {err}");
+                return;
             }
         };
+        surface.post_message(Message::bytes(topic, bytes));
+    })
+}
 
-        let msg = match info.payload().downcast_ref::<&'static str>() {
-            Some(s) => *s,
-            None => match info.payload().downcast_ref::<String>() {
-                Some(s) => &s[..],
-                None => "Box<Any>",
-            },
+/// Replaces the log filters, e.g. `info,native_gl_surface::touch=trace`. Returns whether they
+/// parsed; if not, the old ones stay.
+extern "C" fn set_log_filters(mut env: JNIEnv, _: JClass, filters: JString) -> jboolean {
+    catch_panics(&mut env, JNI_FALSE, |env| {
+        let Some(filters) = read_string(env, &filters) else {
+            return JNI_FALSE;
         };
+        let Some(logger) = egui_logging::logger() else {
+            return JNI_FALSE;
+        };
+        match logger.set_filters(&filters) {
+            Ok(()) => {
+                info!("Log filters set to {filters:?}");
+                JNI_TRUE
+            }
+            Err(err) => {
+                warn!("Ignoring log filters {filters:?}: {err}");
+                JNI_FALSE
+            }
+        }
+    })
+}
 
-        log::error!("foxhunterjni PANICKED: {msg}, at {file}:{line}");
-    }));
+/// Sets the directory panics are written to as crash reports, or stops writing them if `dir` is
+/// `null`.
+extern "C" fn set_crash_report_dir(mut env: JNIEnv, _: JClass, dir: JString) {
+    catch_panics(&mut env, (), |env| {
+        if dir.is_null() {
+            crash::set_report_store(None);
+            return;
+        }
+        let Some(dir) = read_string(env, &dir) else {
+            return;
+        };
+        info!("Writing crash reports to {dir:?}");
+        crash::set_report_store(Some(CrashReportStore::new(dir)));
+    })
+}
+
+/// Returns the contents of the crash reports not deleted yet, oldest first, or `null` if they
+/// can't be listed.
+extern "C" fn get_pending_crash_reports(mut env: JNIEnv, _: JClass) -> jobjectArray {
+    catch_panics(&mut env, std::ptr::null_mut(), |env| {
+        let reports = match crash::report_store().map(|store| store.pending()) {
+            Some(Ok(reports)) => reports,
+            None => Vec::new(),
+            Some(Err(err)) => {
+                warn!("Failed to list crash reports: {err}");
+                return std::ptr::null_mut();
+            }
+        };
+        let array = (|| {
+            let array =
+                env.new_object_array(reports.len() as i32, "java/lang/String", JObject::null())?;
+            for (i, report) in reports.iter().enumerate() {
+                let text = env.new_string(&report.contents)?;
+                env.set_object_array_element(&array, i as i32, text)?;
+            }
+            Ok::<_, jni::errors::Error>(array)
+        })();
+        match array {
+            Ok(array) => array.into_raw(),
+            Err(err) => {
+                warn!("Failed to create crash report array: {err}");
+                std::ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Deletes all crash reports. Returns whether that worked.
+extern "C" fn clear_crash_reports(mut env: JNIEnv, _: JClass) -> jboolean {
+    catch_panics(&mut env, JNI_FALSE, |_| {
+        let Some(store) = crash::report_store() else {
+            return JNI_TRUE;
+        };
+        match store.clear() {
+            Ok(()) => JNI_TRUE,
+            Err(err) => {
+                warn!("Failed to delete crash reports: {err}");
+                JNI_FALSE
+            }
+        }
+    })
+}
+
+fn read_string(env: &mut JNIEnv, text: &JString) -> Option<String> {
+    match env.get_string(text) {
+        Ok(text) => Some(text.into()),
+        Err(err) => {
+            warn!("Failed to read Java string: {err}");
+            None
+        }
+    }
+}
+
+/// The GL function table for the current EGL context, loaded into `gl` on first use.
+fn glow_context(gl: &mut Option<Arc<glow::Context>>) -> Result<Arc<glow::Context>, RendererError> {
+    // SAFETY: function provided by android
+    if unsafe { eglGetCurrentContext() }.is_null() {
+        return Err(RendererError::NoContext);
+    }
+    if let Some(gl) = gl {
+        return Ok(Arc::clone(gl));
+    }
+    fn load_gl_func(symbol_name: &str) -> *const c_void {
+        let Ok(c_str) = CString::new(symbol_name) else {
+            return std::ptr::null();
+        };
+        // SAFETY: function provided by android
+        unsafe { eglGetProcAddress(c_str.as_ptr().cast()) }
+    }
+    // glow calls whatever it loaded without checking, starting with this one
+    if load_gl_func("glGetString").is_null() {
+        return Err(RendererError::NoContext);
+    }
+    info!("Creating glow wrapper");
+    let glow_context = unsafe { glow::Context::from_loader_function(load_gl_func) };
+    Ok(Arc::clone(gl.insert(Arc::new(glow_context))))
+}
+
+/// A renderer for the current EGL context with `required_extensions`. `gl` is the surface's
+/// function table for that context, see [`RenderState::gl`].
+fn create_renderer(
+    gl: &mut Option<Arc<glow::Context>>,
+    required_extensions: &[&str],
+) -> Result<GlowRenderer, RendererError> {
+    GlowRenderer::new(glow_context(gl)?, required_extensions)
+}
+
+extern "C" {
+    fn eglGetProcAddress(procname: *const i8) -> *const c_void;
+    fn eglGetCurrentContext() -> *mut c_void;
+}
+
+/// Java system property with the logcat tag. Read when the library is loaded, like the ones
+/// naming the classes in [`natives`], so set it before `System.loadLibrary`.
+const LOG_TAG_PROPERTY: &str = "egui_view.log_tag";
+/// Java system property with the initial `RUST_LOG`-style filters, read along with
+/// [`LOG_TAG_PROPERTY`]. They can be changed later with `setLogFilters`.
+const LOG_FILTERS_PROPERTY: &str = "egui_view.log_filters";
+
+/// Logging as configured by [`LOG_TAG_PROPERTY`] and [`LOG_FILTERS_PROPERTY`], falling back to
+/// the tag given by `EGUI_VIEW_LOG_TAG` when the library was built and `info`.
+fn log_config(env: Option<&mut JNIEnv>) -> LogConfig {
+    let mut tag = option_env!("EGUI_VIEW_LOG_TAG")
+        .unwrap_or("egui_view")
+        .to_owned();
+    let mut filters = None;
+    if let Some(env) = env {
+        tag = natives::system_property(env, LOG_TAG_PROPERTY).unwrap_or(tag);
+        filters = natives::system_property(env, LOG_FILTERS_PROPERTY);
+    }
+    let config = LogConfig::new(tag);
+    match filters {
+        Some(filters) => config.with_filters(filters),
+        None => config,
+    }
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn JNI_OnLoad(vm: jni::JavaVM, res: *mut std::os::raw::c_void) -> jni::sys::jint {
+    let mut env = vm.get_env();
+    egui_logging::init(log_config(env.as_mut().ok()));
+
+    panics::install_hook();
+
+    let registered = env.map_err(|err| err.to_string()).and_then(|mut env| {
+        let classes = NativeClasses::load(&mut env);
+        natives::register(&mut env, &classes).map_err(|err| err.to_string())
+    });
+    if let Err(err) = registered {
+        // Makes System.loadLibrary throw, rather than failing at the first native call
+        log::error!("Can't bind the native methods: {err}");
+        return jni::sys::JNI_ERR;
+    }
 
     let vm = vm.get_java_vm_pointer() as *mut std::ffi::c_void;
     info!("Java VM pointer: {vm:?}");
@@ -0,0 +1,103 @@
+//! Messages between the app and the Android code hosting it.
+//!
+//! Kotlin posts messages to a surface with `NativeGLSurfaceView.postMessage`; they arrive in
+//! [`crate::surface::FrameInput::messages`] and the app gets them through
+//! [`SurfaceApp::on_message`](crate::app::SurfaceApp::on_message) right before its next update.
+//! The other way round, the app calls [`send`] and everything sent during a frame goes to the
+//! surface's [`MessageHost`] once the frame is done. Without a host, the messages are dropped.
+//!
+//! [`ChannelMessageHost`] stands in for Kotlin when there's no JVM, e.g. in tests.
+
+use std::sync::mpsc;
+
+/// What a message carries. The surface doesn't look inside either kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Payload {
+    /// JSON text, for `org.json` or kotlinx.serialization on the other side
+    Json(String),
+    Bytes(Vec<u8>),
+}
+
+/// One message, in either direction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// What the message is about, e.g. `"clear_color"`. The app and host agree on these.
+    pub topic: String,
+    pub payload: Payload,
+}
+
+impl Message {
+    pub fn json(topic: impl Into<String>, json: impl Into<String>) -> Self {
+        Self {
+            topic: topic.into(),
+            payload: Payload::Json(json.into()),
+        }
+    }
+
+    pub fn bytes(topic: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Self {
+            topic: topic.into(),
+            payload: Payload::Bytes(bytes.into()),
+        }
+    }
+
+    /// The JSON text, if that's what this carries.
+    pub fn as_json(&self) -> Option<&str> {
+        match &self.payload {
+            Payload::Json(json) => Some(json),
+            Payload::Bytes(_) => None,
+        }
+    }
+
+    /// The bytes, if that's what this carries.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.payload {
+            Payload::Json(_) => None,
+            Payload::Bytes(bytes) => Some(bytes),
+        }
+    }
+}
+
+/// Where the app's messages go.
+pub trait MessageHost: Send {
+    fn deliver(&mut self, message: Message);
+}
+
+fn outbox_id() -> egui::Id {
+    egui::Id::new("native_gl_surface::outbox")
+}
+
+/// Queues `message` for the host. It's delivered after the current frame, so calls from other
+/// threads should be followed by [`egui::Context::request_repaint`].
+pub fn send(ctx: &egui::Context, message: Message) {
+    ctx.data_mut(|d| {
+        d.get_temp_mut_or_default::<Vec<Message>>(outbox_id())
+            .push(message)
+    });
+}
+
+/// Takes everything passed to [`send`] so far, oldest first.
+pub fn take_sent(ctx: &egui::Context) -> Vec<Message> {
+    ctx.data_mut(|d| d.remove_temp::<Vec<Message>>(outbox_id()))
+        .unwrap_or_default()
+}
+
+/// A [`MessageHost`] that passes messages to a channel, the in-process stand-in for Kotlin.
+pub struct ChannelMessageHost {
+    sender: mpsc::Sender<Message>,
+}
+
+impl ChannelMessageHost {
+    /// The host, and the receiving end the app's messages come out of.
+    pub fn new() -> (Self, mpsc::Receiver<Message>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self { sender }, receiver)
+    }
+}
+
+impl MessageHost for ChannelMessageHost {
+    fn deliver(&mut self, message: Message) {
+        // Nobody listening any more is the same as no host
+        let _ = self.sender.send(message);
+    }
+}
@@ -0,0 +1,469 @@
+//! Binding the Kotlin classes' `external fun`s to the Rust functions implementing them.
+//!
+//! Rather than exporting `Java_<package>_<class>_<method>` symbols, which would tie the library
+//! to one package, `JNI_OnLoad` hands the whole table to `RegisterNatives`. Which classes it goes
+//! to is decided by [`NativeClasses::load`]: Java system properties set before
+//! `System.loadLibrary`, falling back to the names the library was built with. The Kotlin
+//! `NativeGLSurfaceView` sets the properties to its own names, so a copy of it in another package
+//! finds the prebuilt `.so` without any configuration.
+//!
+//! The table is versioned: the surface view class must declare a static `NATIVES_VERSION` equal
+//! to [`NATIVES_VERSION`], and loading the library fails if it doesn't, instead of the first
+//! changed call throwing `UnsatisfiedLinkError` some time later.
+
+use std::{ffi::c_void, fmt, sync::OnceLock};
+
+use jni::{
+    objects::{GlobalRef, JClass, JString},
+    JNIEnv, NativeMethod,
+};
+use log::{info, warn};
+
+/// Bumped whenever a method is added, removed or changes its signature. Must match
+/// `NativeGLSurfaceView.NATIVES_VERSION` in Kotlin.
+pub const NATIVES_VERSION: i32 = 2;
+
+/// The Kotlin classes the library talks to, as JNI class names like `com/example/Foo`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NativeClasses {
+    /// Declares the surface's `external fun`s and [`NATIVES_VERSION`]
+    pub surface_view: String,
+    /// Declares the `GLSurfaceView.Renderer` callbacks
+    pub renderer: String,
+    /// Built by `getAccessibilityNode0`
+    pub accessibility_node: String,
+}
+
+/// System properties read by [`NativeClasses::load`], one per field. Dotted Java names are
+/// accepted.
+pub const SURFACE_VIEW_PROPERTY: &str = "egui_view.surface_view_class";
+pub const RENDERER_PROPERTY: &str = "egui_view.renderer_class";
+pub const ACCESSIBILITY_NODE_PROPERTY: &str = "egui_view.accessibility_node_class";
+
+impl NativeClasses {
+    /// The classes given by `EGUI_VIEW_SURFACE_VIEW_CLASS`, `EGUI_VIEW_RENDERER_CLASS` and
+    /// `EGUI_VIEW_ACCESSIBILITY_NODE_CLASS` when the library was built, or this repo's.
+    pub fn built_in() -> Self {
+        Self {
+            surface_view: class_name(
+                option_env!("EGUI_VIEW_SURFACE_VIEW_CLASS")
+                    .unwrap_or("com/foxhunter/egui_view/ui/NativeGLSurfaceView"),
+            ),
+            renderer: class_name(
+                option_env!("EGUI_VIEW_RENDERER_CLASS")
+                    .unwrap_or("com/foxhunter/egui_view/ui/NativeGLRenderer"),
+            ),
+            accessibility_node: class_name(
+                option_env!("EGUI_VIEW_ACCESSIBILITY_NODE_CLASS")
+                    .unwrap_or("com/foxhunter/egui_view/ui/AccessibilityNode"),
+            ),
+        }
+    }
+
+    /// [`Self::built_in`], with any class named by a system property replaced.
+    pub fn load(env: &mut JNIEnv) -> Self {
+        let mut classes = Self::built_in();
+        for (property, class) in [
+            (SURFACE_VIEW_PROPERTY, &mut classes.surface_view),
+            (RENDERER_PROPERTY, &mut classes.renderer),
+            (ACCESSIBILITY_NODE_PROPERTY, &mut classes.accessibility_node),
+        ] {
+            if let Some(name) = system_property(env, property) {
+                *class = class_name(&name);
+            }
+        }
+        classes
+    }
+}
+
+/// `com.example.Foo` to `com/example/Foo`; names already in JNI form are kept.
+pub fn class_name(name: &str) -> String {
+    name.replace('.', "/")
+}
+
+/// The Java system property `name`, or `None` if it isn't set or can't be read.
+pub(crate) fn system_property(env: &mut JNIEnv, name: &str) -> Option<String> {
+    let result = (|| {
+        let name = env.new_string(name)?;
+        let value = env
+            .call_static_method(
+                "java/lang/System",
+                "getProperty",
+                "(Ljava/lang/String;)Ljava/lang/String;",
+                &[(&name).into()],
+            )?
+            .l()?;
+        if value.is_null() {
+            return Ok(None);
+        }
+        let value = JString::from(value);
+        let value = String::from(env.get_string(&value)?);
+        Ok::<_, jni::errors::Error>(Some(value))
+    })();
+    result.unwrap_or_else(|err| {
+        clear_exception(env);
+        warn!("Failed to read system property {name}: {err}");
+        None
+    })
+}
+
+/// Which class a [`NativeMethodSpec`] belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NativeClass {
+    SurfaceView,
+    Renderer,
+}
+
+/// One entry of the table passed to `RegisterNatives`.
+#[derive(Clone, Debug)]
+pub struct NativeMethodSpec {
+    pub class: NativeClass,
+    /// The `external fun`'s name
+    pub name: &'static str,
+    /// JNI descriptor, e.g. `(JI)Z`
+    pub sig: String,
+    fn_ptr: *mut c_void,
+}
+
+/// Every `external fun` the library implements, with signatures naming `classes`.
+pub fn methods(classes: &NativeClasses) -> Vec<NativeMethodSpec> {
+    use NativeClass::{Renderer, SurfaceView};
+
+    let view = format!("L{};", classes.surface_view);
+    let node = format!("L{};", classes.accessibility_node);
+    macro_rules! method {
+        ($class:expr, $name:literal, $sig:expr, $function:path) => {
+            NativeMethodSpec {
+                class: $class,
+                name: $name,
+                sig: $sig.to_string(),
+                fn_ptr: $function as *mut c_void,
+            }
+        };
+    }
+    vec![
+        method!(Renderer, "onDrawFrame0", "(J)J", crate::on_draw_frame),
+        method!(
+            Renderer,
+            "onSurfaceCreated0",
+            "(J)V",
+            crate::on_surface_created
+        ),
+        method!(
+            Renderer,
+            "onSurfaceChanged0",
+            "(JII)V",
+            crate::on_surface_changed
+        ),
+        method!(
+            SurfaceView,
+            "setLogFilters0",
+            "(Ljava/lang/String;)Z",
+            crate::set_log_filters
+        ),
+        method!(
+            SurfaceView,
+            "setCrashReportDir0",
+            "(Ljava/lang/String;)V",
+            crate::set_crash_report_dir
+        ),
+        method!(
+            SurfaceView,
+            "getPendingCrashReports0",
+            "()[Ljava/lang/String;",
+            crate::get_pending_crash_reports
+        ),
+        method!(
+            SurfaceView,
+            "clearCrashReports0",
+            "()Z",
+            crate::clear_crash_reports
+        ),
+        method!(
+            SurfaceView,
+            "createNativeSurface0",
+            format!("({view}Ljava/lang/String;FFZ)J"),
+            crate::create_native_surface
+        ),
+        method!(
+            SurfaceView,
+            "createViewportSurface0",
+            format!("({view}JJFFZ)J"),
+            crate::create_viewport_surface
+        ),
+        method!(
+            SurfaceView,
+            "destroyNativeSurface0",
+            "(J)V",
+            crate::destroy_native_surface
+        ),
+        method!(
+            SurfaceView,
+            "onViewportCloseRequested0",
+            "(J)V",
+            crate::on_viewport_close_requested
+        ),
+        method!(SurfaceView, "retryRenderer0", "(J)V", crate::retry_renderer),
+        method!(
+            SurfaceView,
+            "postJsonMessage0",
+            "(JLjava/lang/String;Ljava/lang/String;)V",
+            crate::post_json_message
+        ),
+        method!(
+            SurfaceView,
+            "postBytesMessage0",
+            "(JLjava/lang/String;[B)V",
+            crate::post_bytes_message
+        ),
+        method!(SurfaceView, "saveState0", "(J)[B", crate::save_state),
+        method!(SurfaceView, "restoreState0", "(J[B)V", crate::restore_state),
+        method!(
+            SurfaceView,
+            "saveStateToFile0",
+            "(JLjava/lang/String;)Z",
+            crate::save_state_to_file
+        ),
+        method!(
+            SurfaceView,
+            "restoreStateFromFile0",
+            "(JLjava/lang/String;)V",
+            crate::restore_state_from_file
+        ),
+        method!(
+            SurfaceView,
+            "onTouchEvent0",
+            "(JIII[I[J[F)V",
+            crate::on_touch_event
+        ),
+        method!(
+            SurfaceView,
+            "onConfigurationChanged0",
+            "(JFFZ)V",
+            crate::on_configuration_changed
+        ),
+        method!(
+            SurfaceView,
+            "onWindowInsetsChanged0",
+            "(J[I)V",
+            crate::on_window_insets_changed
+        ),
+        method!(
+            SurfaceView,
+            "setContinuousRendering0",
+            "(JZ)V",
+            crate::set_continuous_rendering
+        ),
+        method!(
+            SurfaceView,
+            "setStatsOverlay0",
+            "(JZ)V",
+            crate::set_stats_overlay
+        ),
+        method!(
+            SurfaceView,
+            "getFrameStats0",
+            "(J)[J",
+            crate::get_frame_stats
+        ),
+        method!(
+            SurfaceView,
+            "getGlDiagnostics0",
+            "(J)Ljava/lang/String;",
+            crate::get_gl_diagnostics
+        ),
+        method!(
+            SurfaceView,
+            "setAccessibilityEnabled0",
+            "(JZ)V",
+            crate::set_accessibility_enabled
+        ),
+        method!(
+            SurfaceView,
+            "getAccessibilityNode0",
+            format!("(JI){node}"),
+            crate::get_accessibility_node
+        ),
+        method!(
+            SurfaceView,
+            "findAccessibilityNodeAt0",
+            "(JFF)I",
+            crate::find_accessibility_node_at
+        ),
+        method!(
+            SurfaceView,
+            "performAccessibilityAction0",
+            "(JIIF)Z",
+            crate::perform_accessibility_action
+        ),
+        method!(SurfaceView, "renderToPng0", "(JII)[B", crate::render_to_png),
+        method!(SurfaceView, "onKeyEvent0", "(JIIIII)V", crate::on_key_event),
+        method!(
+            SurfaceView,
+            "onCommitText0",
+            "(JLjava/lang/String;)V",
+            crate::on_commit_text
+        ),
+        method!(
+            SurfaceView,
+            "onComposingText0",
+            "(JLjava/lang/String;)V",
+            crate::on_composing_text
+        ),
+        method!(
+            SurfaceView,
+            "onFinishComposingText0",
+            "(J)V",
+            crate::on_finish_composing_text
+        ),
+    ]
+}
+
+/// Why [`register`] failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegisterError {
+    /// A class isn't there, or couldn't be loaded.
+    ClassNotFound { class: String },
+    /// The surface view has no static `int NATIVES_VERSION`.
+    MissingVersion { class: String },
+    /// The surface view was written for another version of the table.
+    VersionMismatch {
+        class: String,
+        expected: i32,
+        found: i32,
+    },
+    /// `RegisterNatives` refused the table, usually because a method is missing or has another
+    /// signature.
+    Rejected { class: String, reason: String },
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ClassNotFound { class } => write!(f, "class {class} not found"),
+            Self::MissingVersion { class } => {
+                write!(f, "{class} doesn't declare NATIVES_VERSION")
+            }
+            Self::VersionMismatch {
+                class,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{class} declares NATIVES_VERSION {found}, but this library implements version \
+                 {expected}"
+            ),
+            Self::Rejected { class, reason } => {
+                write!(f, "failed to register natives on {class}: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+static NODE_CLASS: OnceLock<GlobalRef> = OnceLock::new();
+
+/// Checks the surface view's [`NATIVES_VERSION`] and registers every method in [`methods`] on
+/// `classes`. Any Java exception this causes is cleared.
+pub fn register(env: &mut JNIEnv, classes: &NativeClasses) -> Result<(), RegisterError> {
+    let surface_view = find_class(env, &classes.surface_view)?;
+    check_version(env, &surface_view, &classes.surface_view)?;
+    let renderer = find_class(env, &classes.renderer)?;
+    let node = find_class(env, &classes.accessibility_node)?;
+    let node = env
+        .new_global_ref(node)
+        .map_err(|err| RegisterError::Rejected {
+            class: classes.accessibility_node.clone(),
+            reason: err.to_string(),
+        })?;
+    let _ = NODE_CLASS.set(node);
+
+    let methods = methods(classes);
+    for (kind, class, name) in [
+        (
+            NativeClass::SurfaceView,
+            &surface_view,
+            &classes.surface_view,
+        ),
+        (NativeClass::Renderer, &renderer, &classes.renderer),
+    ] {
+        let table: Vec<NativeMethod> = methods
+            .iter()
+            .filter(|method| method.class == kind)
+            .map(|method| NativeMethod {
+                name: method.name.into(),
+                sig: method.sig.as_str().into(),
+                fn_ptr: method.fn_ptr,
+            })
+            .collect();
+        if let Err(err) = env.register_native_methods(class, &table) {
+            let reason = describe_exception(env).unwrap_or_else(|| err.to_string());
+            return Err(RegisterError::Rejected {
+                class: name.clone(),
+                reason,
+            });
+        }
+        info!("Registered {} natives on {name}", table.len());
+    }
+    Ok(())
+}
+
+/// The accessibility node class found by [`register`].
+pub(crate) fn accessibility_node_class() -> Option<&'static GlobalRef> {
+    NODE_CLASS.get()
+}
+
+fn find_class<'local>(
+    env: &mut JNIEnv<'local>,
+    class: &str,
+) -> Result<JClass<'local>, RegisterError> {
+    env.find_class(class).map_err(|_| {
+        clear_exception(env);
+        RegisterError::ClassNotFound {
+            class: class.to_owned(),
+        }
+    })
+}
+
+fn check_version(env: &mut JNIEnv, class: &JClass, name: &str) -> Result<(), RegisterError> {
+    let found = env
+        .get_static_field(class, "NATIVES_VERSION", "I")
+        .and_then(|value| value.i())
+        .map_err(|_| {
+            clear_exception(env);
+            RegisterError::MissingVersion {
+                class: name.to_owned(),
+            }
+        })?;
+    if found != NATIVES_VERSION {
+        return Err(RegisterError::VersionMismatch {
+            class: name.to_owned(),
+            expected: NATIVES_VERSION,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// Clears the pending exception and returns its `toString()`, if there was one.
+fn describe_exception(env: &mut JNIEnv) -> Option<String> {
+    let exception = env.exception_occurred().ok()?;
+    if exception.is_null() {
+        return None;
+    }
+    let _ = env.exception_clear();
+    let text = env
+        .call_method(&exception, "toString", "()Ljava/lang/String;", &[])
+        .and_then(|value| value.l())
+        .ok()?;
+    let text = JString::from(text);
+    let text = env.get_string(&text).ok()?.into();
+    Some(text)
+}
+
+fn clear_exception(env: &mut JNIEnv) {
+    if env.exception_check().unwrap_or(false) {
+        let _ = env.exception_clear();
+    }
+}
@@ -0,0 +1,77 @@
+//! Keeping panics from unwinding into the JVM.
+//!
+//! Unwinding out of an `extern "C"` function is undefined behaviour, so every entry point runs
+//! its body through [`catch`]. The hook from [`install_hook`] logs each panic and remembers where
+//! it happened, which the payload alone doesn't say, and writes a crash report if
+//! [`crate::crash::set_report_store`] was given somewhere to put it.
+
+use std::{any::Any, cell::RefCell, fmt, panic::AssertUnwindSafe, sync::Once};
+
+thread_local! {
+    /// Location of the latest panic on this thread, until [`catch`] picks it up
+    static LAST_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A panic caught by [`catch`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanicReport {
+    pub message: String,
+    /// `file:line:column`, if the hook was installed
+    pub location: Option<String>,
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rust panic: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Logs every panic, records its location for [`catch`] and writes a crash report, then runs the
+/// hook that was set before. Only installs itself once.
+pub fn install_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let location = info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_else(|| "<unknown>".to_owned());
+            let message = payload_message(info.payload());
+            log::error!("PANICKED: {message}, at {location}");
+            crate::crash::on_panic(PanicReport {
+                message: message.to_owned(),
+                location: Some(location.clone()),
+            });
+            LAST_LOCATION.with_borrow_mut(|last| *last = Some(location));
+            previous(info);
+        }));
+    });
+}
+
+/// Runs `body`, turning a panic into a [`PanicReport`] instead of unwinding further.
+///
+/// Whatever `body` borrows may be left half-updated by the panic, so callers have to stop using
+/// it; see [`crate::handles::HandleRegistry::poison`].
+pub fn catch<R>(body: impl FnOnce() -> R) -> Result<R, PanicReport> {
+    LAST_LOCATION.with_borrow_mut(|last| *last = None);
+    std::panic::catch_unwind(AssertUnwindSafe(body)).map_err(|payload| PanicReport {
+        message: payload_message(payload.as_ref()).to_owned(),
+        location: LAST_LOCATION.with_borrow_mut(Option::take),
+    })
+}
+
+/// The message passed to `panic!`, if it was a string.
+fn payload_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
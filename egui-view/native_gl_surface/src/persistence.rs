@@ -0,0 +1,128 @@
+//! Saving the hosted app and egui's memory, so they survive the surface being recreated.
+//!
+//! Everything is packed into one blob: a magic number, a format version, then RON. The blob goes
+//! into the view's instance state on rotation, and into a file in the app's files dir so it also
+//! survives process death.
+
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::app::SurfaceApp;
+
+const MAGIC: &[u8; 4] = b"EGST";
+
+/// The only version so far. Fields added to [`SavedState`] get `#[serde(default)]` instead of a
+/// new version; bump this only for changes older blobs can't be read as, and add a case for the
+/// old layout to [`SavedState::from_bytes`] then.
+pub const FORMAT_VERSION: u16 = 1;
+
+#[derive(Debug)]
+pub enum PersistError {
+    /// Too short, or doesn't start with our magic number
+    NotSavedState,
+    /// Written by a newer version of this library
+    UnsupportedVersion(u16),
+    Encode(ron::Error),
+    Decode(ron::error::SpannedError),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for PersistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotSavedState => write!(f, "not a saved egui state"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "saved state has format version {version}, newest supported is {FORMAT_VERSION}"
+            ),
+            Self::Encode(err) => write!(f, "failed to encode state: {err}"),
+            Self::Decode(err) => write!(f, "failed to decode state: {err}"),
+            Self::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {}
+
+impl From<io::Error> for PersistError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Everything that is restored when a surface is recreated.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedState {
+    /// Whatever [`SurfaceApp::save`] returned
+    pub app: Option<Vec<u8>>,
+    /// Window positions, collapsed headers, scroll offsets, ...
+    pub memory: Option<egui::Memory>,
+}
+
+impl SavedState {
+    pub fn capture(ctx: &egui::Context, app: &mut dyn SurfaceApp) -> Self {
+        Self {
+            app: app.save(),
+            memory: Some(ctx.memory(|mem| mem.clone())),
+        }
+    }
+
+    /// Loads the app's state and replaces egui's whole [`egui::Memory`] with the saved one, so
+    /// anything live in `ctx` is lost: focus, open popups, ongoing drags. Meant for a surface that
+    /// was just created, before its first frame.
+    pub fn restore(self, ctx: &egui::Context, app: &mut dyn SurfaceApp) {
+        if let Some(data) = &self.app {
+            app.load(data);
+        }
+        if let Some(memory) = self.memory {
+            ctx.memory_mut(|mem| *mem = memory);
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PersistError> {
+        let body = ron::to_string(self).map_err(PersistError::Encode)?;
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + body.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(body.as_bytes());
+        Ok(bytes)
+    }
+
+    /// Parses a blob from [`Self::to_bytes`]. Fails with [`PersistError::UnsupportedVersion`] if
+    /// a newer version of this library wrote it.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PersistError> {
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or(PersistError::NotSavedState)?;
+        let (version, body) = match rest {
+            [lo, hi, body @ ..] => (u16::from_le_bytes([*lo, *hi]), body),
+            _ => return Err(PersistError::NotSavedState),
+        };
+        match version {
+            // Fields added since are filled in by `#[serde(default)]`
+            1 => ron::de::from_bytes(body).map_err(PersistError::Decode),
+            _ => Err(PersistError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Writes `bytes` to `path`, replacing it atomically so a crash never leaves half a file.
+pub fn write_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Reads a state file written by [`write_file`], or `None` if there is none yet.
+pub fn read_file(path: &Path) -> Result<Option<SavedState>, PersistError> {
+    match std::fs::read(path) {
+        Ok(bytes) => SavedState::from_bytes(&bytes).map(Some),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,77 @@
+//! Everything egui asks of the platform outside of rendering: clipboard, links, cursor and
+//! feedback on widget interaction.
+
+use egui::{output::OutputEvent, CursorIcon, Key, OpenUrl, PlatformOutput};
+
+/// The services egui needs from whoever embeds it.
+pub trait PlatformHost {
+    /// Put text on the system clipboard.
+    fn copy_text(&mut self, text: &str);
+
+    /// The current text on the system clipboard, if any.
+    fn paste_text(&mut self) -> Option<String>;
+
+    fn open_url(&mut self, open_url: &OpenUrl);
+
+    /// Only called when the icon changes.
+    fn set_cursor_icon(&mut self, icon: CursorIcon);
+
+    /// A widget was clicked, focused, edited… Hosts use this for haptics.
+    fn widget_event(&mut self, event: &OutputEvent);
+}
+
+/// Turns clipboard shortcuts into the events egui expects, reading the clipboard for pastes.
+///
+/// Android doesn't have a separate paste notification; pastes arrive as `KEYCODE_PASTE` or
+/// Ctrl+V, so this looks for those in the frame's input.
+pub fn clipboard_events(events: &mut Vec<egui::Event>, host: &mut dyn PlatformHost) {
+    let mut i = 0;
+    while i < events.len() {
+        let extra = match &events[i] {
+            egui::Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+                ..
+            } => match key {
+                Key::Copy => Some(egui::Event::Copy),
+                Key::Cut => Some(egui::Event::Cut),
+                Key::C if modifiers.command => Some(egui::Event::Copy),
+                Key::X if modifiers.command => Some(egui::Event::Cut),
+                Key::Paste => host.paste_text().map(egui::Event::Paste),
+                Key::V if modifiers.command => host.paste_text().map(egui::Event::Paste),
+                _ => None,
+            },
+            _ => None,
+        };
+        i += 1;
+        if let Some(extra) = extra {
+            events.insert(i, extra);
+            i += 1;
+        }
+    }
+}
+
+/// Forwards a frame's [`PlatformOutput`] to a [`PlatformHost`].
+#[derive(Default)]
+pub struct PlatformOutputHandler {
+    cursor_icon: CursorIcon,
+}
+
+impl PlatformOutputHandler {
+    pub fn handle(&mut self, output: &PlatformOutput, host: &mut dyn PlatformHost) {
+        if !output.copied_text.is_empty() {
+            host.copy_text(&output.copied_text);
+        }
+        if let Some(open_url) = &output.open_url {
+            host.open_url(open_url);
+        }
+        if output.cursor_icon != self.cursor_icon {
+            self.cursor_icon = output.cursor_icon;
+            host.set_cursor_icon(output.cursor_icon);
+        }
+        for event in &output.events {
+            host.widget_event(event);
+        }
+    }
+}
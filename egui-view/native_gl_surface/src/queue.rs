@@ -0,0 +1,35 @@
+//! Hand-off of input from the Android UI thread to the GL render thread.
+
+use parking_lot::Mutex;
+
+/// Items waiting for the next frame: events to feed into the next [`egui::RawInput`], or
+/// [messages](crate::messages::Message) for the app.
+///
+/// Any thread may push; the render thread takes everything at the start of a frame.
+pub struct EventQueue<T = egui::Event> {
+    items: Mutex<Vec<T>>,
+}
+
+impl<T> Default for EventQueue<T> {
+    fn default() -> Self {
+        Self {
+            items: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl<T> EventQueue<T> {
+    pub fn push(&self, item: T) {
+        self.items.lock().push(item);
+    }
+
+    /// Appends all items at once, so they are never split across two frames.
+    pub fn extend(&self, items: impl IntoIterator<Item = T>) {
+        self.items.lock().extend(items);
+    }
+
+    /// Takes every queued item, oldest first.
+    pub fn drain(&self) -> Vec<T> {
+        std::mem::take(&mut *self.items.lock())
+    }
+}
@@ -0,0 +1,242 @@
+//! What [`RustSurface`](crate::surface::RustSurface) draws with.
+//!
+//! The frame loop only talks to [`SurfaceRenderer`], so it can run against the real GL painter,
+//! a `testing::RecordingRenderer` in tests, or another backend later on.
+
+use std::sync::Arc;
+
+use egui::{epaint::ImageDelta, ClippedPrimitive, ColorImage, TextureId};
+use glow::HasContext as _;
+use log::{info, warn};
+
+use crate::gl_info::GlInfo;
+
+/// Paints tessellated egui output into the surface.
+///
+/// Every method is called on the render thread, with the renderer's context current.
+pub trait SurfaceRenderer: Send {
+    /// Largest texture side egui may allocate.
+    fn max_texture_side(&self) -> usize;
+
+    /// The GL context this renders with, for renderers that have one.
+    fn gl_info(&self) -> Option<Arc<GlInfo>> {
+        None
+    }
+
+    fn clear(&mut self, size_px: [u32; 2], color: [f32; 4]);
+
+    /// Uploads a full texture, or a partial update if `delta.pos` is set.
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta);
+
+    fn paint(&mut self, size_px: [u32; 2], pixels_per_point: f32, primitives: &[ClippedPrimitive]);
+
+    fn free_texture(&mut self, id: TextureId);
+
+    /// Reads back what the last [`Self::paint`] drew, top row first. `None` if the backend
+    /// can't.
+    fn read_pixels(&mut self, _size_px: [u32; 2]) -> Option<ColorImage> {
+        None
+    }
+
+    /// Paints `primitives` into an offscreen target of `size_px` and reads it back, leaving the
+    /// surface untouched. `None` if the backend can't.
+    fn paint_offscreen(
+        &mut self,
+        _size_px: [u32; 2],
+        _clear_color: [f32; 4],
+        _pixels_per_point: f32,
+        _primitives: &[ClippedPrimitive],
+    ) -> Option<ColorImage> {
+        None
+    }
+
+    /// Releases the backend's resources. Called once, before the renderer is dropped.
+    fn destroy(&mut self);
+
+    /// Drops the renderer once its context is gone, instead of [`Self::destroy`]. May run on any
+    /// thread, so it must not make GL calls; the objects died with the context anyway.
+    fn abandon(self: Box<Self>) {}
+}
+
+/// Why a [`GlowRenderer`] couldn't be created.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RendererError {
+    /// No EGL context is current, or its GL functions couldn't be loaded.
+    NoContext,
+    /// The context's shading language is older than egui's shaders need.
+    UnsupportedGlsl { version: String },
+    /// egui's shaders failed to compile or link.
+    ShaderCompile { log: String },
+    /// The app needs a GL extension the context doesn't have.
+    MissingExtension { name: String },
+}
+
+impl RendererError {
+    /// How the error is identified to Kotlin. Must match `RendererError` there.
+    pub fn status_code(&self) -> i32 {
+        match self {
+            Self::NoContext => 1,
+            Self::UnsupportedGlsl { .. } => 2,
+            Self::ShaderCompile { .. } => 3,
+            Self::MissingExtension { .. } => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoContext => write!(f, "no current GL context"),
+            Self::UnsupportedGlsl { version } => write!(f, "unsupported GLSL version {version:?}"),
+            Self::ShaderCompile { log } => write!(f, "failed to build egui's shaders: {log}"),
+            Self::MissingExtension { name } => write!(f, "missing GL extension {name}"),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+/// Renders with [`egui_glow::Painter`] into whatever framebuffer is bound.
+pub struct GlowRenderer {
+    painter: egui_glow::Painter,
+    gl_info: Arc<GlInfo>,
+}
+
+impl GlowRenderer {
+    /// Must be called on the thread `gl` is current on. Fails if the context can't run egui's
+    /// shaders or lacks one of `required_extensions`.
+    pub fn new(
+        gl: Arc<glow::Context>,
+        required_extensions: &[&str],
+    ) -> Result<Self, RendererError> {
+        info!("Creating glow painter");
+        let gl_info = Arc::new(GlInfo::query(&gl));
+        info!(
+            "GL context: {}, GLSL {}, max texture size {}",
+            gl_info.version, gl_info.shading_language_version, gl_info.max_texture_side
+        );
+        if !gl_info.supports_egui_shaders() {
+            return Err(RendererError::UnsupportedGlsl {
+                version: gl_info.shading_language_version.clone(),
+            });
+        }
+        if let Some(name) = required_extensions
+            .iter()
+            .find(|name| !gl_info.has_extension(name))
+        {
+            return Err(RendererError::MissingExtension {
+                name: (*name).to_owned(),
+            });
+        }
+        // With the version checked, building the shader program is all that's left to fail
+        let painter = egui_glow::Painter::new(gl, "", None, false).map_err(|err| {
+            RendererError::ShaderCompile {
+                log: err.to_string(),
+            }
+        })?;
+        Ok(Self { painter, gl_info })
+    }
+}
+
+impl SurfaceRenderer for GlowRenderer {
+    fn max_texture_side(&self) -> usize {
+        self.gl_info.max_texture_side
+    }
+
+    fn gl_info(&self) -> Option<Arc<GlInfo>> {
+        Some(Arc::clone(&self.gl_info))
+    }
+
+    fn clear(&mut self, size_px: [u32; 2], color: [f32; 4]) {
+        self.painter.clear(size_px, color);
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        self.painter.set_texture(id, delta);
+    }
+
+    fn paint(&mut self, size_px: [u32; 2], pixels_per_point: f32, primitives: &[ClippedPrimitive]) {
+        self.painter
+            .paint_primitives(size_px, pixels_per_point, primitives);
+    }
+
+    fn free_texture(&mut self, id: TextureId) {
+        self.painter.free_texture(id);
+    }
+
+    fn read_pixels(&mut self, size_px: [u32; 2]) -> Option<ColorImage> {
+        Some(self.painter.read_screen_rgba(size_px))
+    }
+
+    fn paint_offscreen(
+        &mut self,
+        size_px: [u32; 2],
+        clear_color: [f32; 4],
+        pixels_per_point: f32,
+        primitives: &[ClippedPrimitive],
+    ) -> Option<ColorImage> {
+        let max_side = self.gl_info.max_texture_side as u32;
+        if size_px.contains(&0) || size_px.iter().any(|&side| side > max_side) {
+            warn!("Can't render a {size_px:?} image, the limit is {max_side}");
+            return None;
+        }
+        let gl = Arc::clone(self.painter.gl());
+        // SAFETY: plain object creation on the current context. Whatever framebuffer was bound
+        // is bound again before returning.
+        unsafe {
+            let previous = gl.get_parameter_framebuffer(glow::FRAMEBUFFER_BINDING);
+            let framebuffer = gl
+                .create_framebuffer()
+                .inspect_err(|err| warn!("Failed to create an offscreen framebuffer: {err}"))
+                .ok()?;
+            let renderbuffer = match gl.create_renderbuffer() {
+                Ok(renderbuffer) => renderbuffer,
+                Err(err) => {
+                    warn!("Failed to create an offscreen renderbuffer: {err}");
+                    gl.delete_framebuffer(framebuffer);
+                    return None;
+                }
+            };
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+            gl.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                glow::RGBA8,
+                size_px[0] as i32,
+                size_px[1] as i32,
+            );
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(renderbuffer),
+            );
+
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            let image = if status == glow::FRAMEBUFFER_COMPLETE {
+                self.painter.clear(size_px, clear_color);
+                self.painter
+                    .paint_primitives(size_px, pixels_per_point, primitives);
+                Some(self.painter.read_screen_rgba(size_px))
+            } else {
+                warn!("Offscreen framebuffer incomplete: {status:#x}");
+                None
+            };
+
+            gl.bind_framebuffer(glow::FRAMEBUFFER, previous);
+            gl.delete_framebuffer(framebuffer);
+            gl.delete_renderbuffer(renderbuffer);
+            image
+        }
+    }
+
+    fn destroy(&mut self) {
+        self.painter.destroy();
+    }
+
+    fn abandon(self: Box<Self>) {
+        // Dropping the painter without `destroy` only logs a warning about leaked objects, but
+        // `destroy` makes GL calls. Leak its bookkeeping instead, this happens once per context.
+        std::mem::forget(self.painter);
+    }
+}
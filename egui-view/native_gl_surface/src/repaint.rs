@@ -0,0 +1,61 @@
+//! Deciding when the host needs to render the next frame.
+
+use std::time::Duration;
+
+/// How often the surface is repainted, like `RunMode` in eframe's backend panel.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RunMode {
+    /// Only repaint on input, animations, or when the app calls
+    /// [`egui::Context::request_repaint`]. Lets the view use `RENDERMODE_WHEN_DIRTY`.
+    #[default]
+    Reactive,
+
+    /// Request a new frame at the end of every frame, i.e. render at the display refresh rate.
+    Continuous,
+}
+
+/// When the host should call `onDrawFrame0` next.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RepaintSchedule {
+    Immediately,
+    After(Duration),
+    /// Nothing to do until new input arrives.
+    Idle,
+}
+
+impl RepaintSchedule {
+    /// Interprets [`egui::ViewportOutput::repaint_delay`].
+    pub fn from_delay(delay: Duration) -> Self {
+        if delay.is_zero() {
+            Self::Immediately
+        } else if delay == Duration::MAX {
+            Self::Idle
+        } else {
+            Self::After(delay)
+        }
+    }
+
+    /// The value handed back to Kotlin: `0` for now, `-1` for idle, otherwise a delay in ms.
+    pub fn to_millis(self) -> i64 {
+        match self {
+            Self::Immediately => 0,
+            // Round up so a 0.5ms delay doesn't turn into "immediately"
+            Self::After(delay) => i64::try_from(delay.as_micros().div_ceil(1000))
+                .unwrap_or(i64::MAX)
+                .max(1),
+            Self::Idle => -1,
+        }
+    }
+}
+
+/// Wakes the host up when egui wants a new frame, from any thread.
+pub trait RepaintSignal: Send + Sync {
+    fn request_repaint(&self, schedule: RepaintSchedule);
+}
+
+/// Routes [`egui::Context::request_repaint`] (and friends) to `signal`.
+pub fn install_repaint_callback(ctx: &egui::Context, signal: impl RepaintSignal + 'static) {
+    ctx.set_request_repaint_callback(move |info| {
+        signal.request_repaint(RepaintSchedule::from_delay(info.delay));
+    });
+}
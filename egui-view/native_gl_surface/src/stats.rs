@@ -0,0 +1,237 @@
+//! Where each frame's time goes, kept for the last few seconds.
+//!
+//! Every phase is also wrapped in an ATrace section on Android, so it shows up in Perfetto and
+//! systrace captures next to the rest of the app.
+
+use std::{
+    collections::VecDeque,
+    ffi::CStr,
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
+
+use egui::{epaint::Primitive, ClippedPrimitive};
+
+/// How many frames [`FrameHistory`] keeps: five seconds at 60 Hz.
+pub const HISTORY_LEN: usize = 300;
+
+/// Timings and sizes of one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Turning host input into [`egui::RawInput`]
+    pub input: Duration,
+    /// [`egui::Context::run`], i.e. the app's UI code
+    pub run: Duration,
+    pub tessellate: Duration,
+    /// Uploading and freeing textures
+    pub textures: Duration,
+    /// Clearing and `paint_primitives`
+    pub paint: Duration,
+    pub total: Duration,
+    pub primitives: usize,
+    pub vertices: usize,
+    pub indices: usize,
+    /// Estimated GPU memory of every live texture
+    pub texture_bytes: usize,
+}
+
+impl FrameStats {
+    /// The number of values [`Self::to_packed`] produces per frame.
+    pub const PACKED_LEN: usize = 10;
+
+    /// Counts the meshes egui handed to the renderer.
+    pub fn count_primitives(&mut self, primitives: &[ClippedPrimitive]) {
+        self.primitives = primitives.len();
+        (self.vertices, self.indices) =
+            primitives
+                .iter()
+                .fold((0, 0), |(v, i), primitive| match &primitive.primitive {
+                    Primitive::Mesh(mesh) => (v + mesh.vertices.len(), i + mesh.indices.len()),
+                    Primitive::Callback(_) => (v, i),
+                });
+    }
+
+    /// Flattened for JNI: durations in microseconds, then the counts. Must match
+    /// `FrameStats.fromPacked` in Kotlin.
+    pub fn to_packed(&self) -> [i64; Self::PACKED_LEN] {
+        let micros = |d: Duration| i64::try_from(d.as_micros()).unwrap_or(i64::MAX);
+        let count = |n: usize| i64::try_from(n).unwrap_or(i64::MAX);
+        [
+            micros(self.input),
+            micros(self.run),
+            micros(self.tessellate),
+            micros(self.textures),
+            micros(self.paint),
+            micros(self.total),
+            count(self.primitives),
+            count(self.vertices),
+            count(self.indices),
+            count(self.texture_bytes),
+        ]
+    }
+}
+
+/// The most recent [`HISTORY_LEN`] frames, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct FrameHistory {
+    frames: VecDeque<FrameStats>,
+}
+
+impl FrameHistory {
+    pub fn push(&mut self, stats: FrameStats) {
+        if self.frames.len() == HISTORY_LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(stats);
+    }
+
+    pub fn latest(&self) -> Option<&FrameStats> {
+        self.frames.back()
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &FrameStats> {
+        self.frames.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Every frame's [`FrameStats::to_packed`], concatenated.
+    pub fn to_packed(&self) -> Vec<i64> {
+        self.frames.iter().flat_map(FrameStats::to_packed).collect()
+    }
+
+    /// Draws the frame times as stacked bars in a corner of the screen.
+    pub fn overlay(&self, ctx: &egui::Context) {
+        egui::Area::new(egui::Id::new("native_gl_surface::frame_stats"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+            .interactable(false)
+            .order(egui::Order::Debug)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| self.overlay_ui(ui));
+            });
+    }
+
+    fn overlay_ui(&self, ui: &mut egui::Ui) {
+        let phases = [
+            ("input", egui::Color32::LIGHT_GRAY),
+            ("run", egui::Color32::from_rgb(90, 160, 255)),
+            ("tessellate", egui::Color32::from_rgb(255, 200, 60)),
+            ("textures", egui::Color32::from_rgb(200, 110, 255)),
+            ("paint", egui::Color32::from_rgb(90, 220, 120)),
+        ];
+        let durations = |stats: &FrameStats| {
+            [
+                stats.input,
+                stats.run,
+                stats.tessellate,
+                stats.textures,
+                stats.paint,
+            ]
+        };
+
+        if let Some(latest) = self.latest() {
+            ui.label(format!(
+                "{:.1} ms, {} vertices, {} KiB textures",
+                latest.total.as_secs_f32() * 1000.0,
+                latest.vertices,
+                latest.texture_bytes / 1024,
+            ));
+        }
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(180.0, 60.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        // Full height is two 60 Hz frames, with a line at the 16.7 ms budget
+        let full_scale = 2.0 / 60.0;
+        let y_for = |secs: f32| rect.bottom() - rect.height() * (secs / full_scale).min(1.0);
+        let bar_width = rect.width() / HISTORY_LEN as f32;
+        for (i, stats) in self.frames.iter().enumerate() {
+            let x = rect.right() - (self.frames.len() - i) as f32 * bar_width;
+            let mut bottom = 0.0;
+            for (duration, (_, color)) in durations(stats).iter().zip(phases) {
+                let top = bottom + duration.as_secs_f32();
+                painter.rect_filled(
+                    egui::Rect::from_x_y_ranges(x..=x + bar_width, y_for(top)..=y_for(bottom)),
+                    0.0,
+                    color,
+                );
+                bottom = top;
+            }
+        }
+        painter.hline(
+            rect.x_range(),
+            y_for(1.0 / 60.0),
+            egui::Stroke::new(1.0, egui::Color32::RED),
+        );
+
+        ui.horizontal_wrapped(|ui| {
+            for (name, color) in phases {
+                ui.colored_label(color, name);
+            }
+        });
+    }
+}
+
+/// Measures one phase of a frame and marks it for Perfetto.
+///
+/// The trace section ends when the span is dropped, so an early return or a panic in the phase
+/// doesn't leave it open. Use [`Self::end`] to also get the duration.
+pub struct Span {
+    start: Instant,
+    /// Sections have to end on the thread they began on
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Span {
+    pub fn begin(name: &'static CStr) -> Self {
+        atrace::begin_section(name);
+        Self {
+            start: Instant::now(),
+            _not_send: PhantomData,
+        }
+    }
+
+    pub fn end(self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        atrace::end_section();
+    }
+}
+
+#[cfg(target_os = "android")]
+mod atrace {
+    extern "C" {
+        fn ATrace_beginSection(section_name: *const std::ffi::c_char);
+        fn ATrace_endSection();
+    }
+
+    pub fn begin_section(name: &std::ffi::CStr) {
+        // SAFETY: `name` is a valid C string
+        unsafe { ATrace_beginSection(name.as_ptr()) }
+    }
+
+    pub fn end_section() {
+        // SAFETY: only called when a `Span` drops, on the thread that began its section
+        unsafe { ATrace_endSection() }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+mod atrace {
+    pub fn begin_section(_name: &std::ffi::CStr) {}
+
+    pub fn end_section() {}
+}
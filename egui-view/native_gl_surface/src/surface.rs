@@ -0,0 +1,485 @@
+//! One hosted app, its egui context and the GL painter that draws it.
+//!
+//! This is everything `onDrawFrame0` does that doesn't involve Java, so the same pipeline can be
+//! driven by a headless GL context in tests.
+
+use std::{sync::Arc, time::Instant};
+
+use egui::{ClippedPrimitive, ColorImage, Rect, TexturesDelta, ViewportCommand, ViewportId};
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use crate::{
+    app::{self, SurfaceApp},
+    commands::{self, ViewportCommandHost},
+    crash, demo_app,
+    display::DisplayConfig,
+    gl_info::GlInfo,
+    insets::{self, ImeScroller, WindowInsets},
+    keyboard,
+    messages::{self, Message, MessageHost},
+    renderer::SurfaceRenderer,
+    stats::{FrameHistory, FrameStats, Span},
+    textures::TextureCache,
+    viewports::{ChildrenOutput, ViewportFrame, ViewportHost, Viewports},
+};
+
+/// What a frame needs from the host.
+#[derive(Clone, Debug, Default)]
+pub struct FrameInput {
+    /// Size of the GL surface in physical pixels
+    pub size_px: [u32; 2],
+    pub display: DisplayConfig,
+    pub insets: WindowInsets,
+    /// Input collected since the last frame
+    pub events: Vec<egui::Event>,
+    /// Posted by the host since the last frame, for [`SurfaceApp::on_message`]
+    pub messages: Vec<Message>,
+    /// Draw the [`FrameHistory`] graph on top of the app
+    pub show_stats: bool,
+    /// Whether an accessibility service wants the root's AccessKit tree in
+    /// [`egui::PlatformOutput::accesskit_update`]
+    pub accessibility: bool,
+}
+
+/// What the host has to act on after a frame. The frame itself has already been painted.
+pub struct FrameOutput {
+    /// Output of the root and every child viewport
+    pub platform_output: egui::PlatformOutput,
+    pub viewport_output: egui::ViewportIdMap<egui::ViewportOutput>,
+}
+
+pub struct RustSurface {
+    pub egui_ctx: egui::Context,
+    /// `None` until the host provides one, and after the EGL context is lost
+    renderer: Option<Box<dyn SurfaceRenderer>>,
+    /// Everything uploaded to `renderer`, so a new one can be brought up to date
+    textures: TextureCache,
+    timer: Instant,
+    ime_scroller: ImeScroller,
+    stats: FrameHistory,
+    /// Child viewports shown on surfaces of their own
+    viewports: Arc<Mutex<Viewports>>,
+    /// Carries out viewport commands. Without one, they are all unsupported.
+    command_host: Option<Box<dyn ViewportCommandHost>>,
+    /// Receives what the app passes to [`messages::send`]. Without one, it's dropped.
+    message_host: Option<Box<dyn MessageHost>>,
+    /// The app sent [`ViewportCommand::Screenshot`] and the next paint should be read back
+    screenshot_requested: bool,
+    /// Input for the next frame that didn't come from the host, i.e. screenshots
+    pending_events: Vec<egui::Event>,
+    /// The last frame painted, so it can be painted again at another size
+    last_paint: Option<LastPaint>,
+    /// A text field had focus last frame, so AccessKit is on to tell which keyboard it needs
+    text_focused: bool,
+    pub app: Box<dyn SurfaceApp>,
+}
+
+impl RustSurface {
+    pub fn new(app: Box<dyn SurfaceApp>) -> Self {
+        Self {
+            egui_ctx: egui::Context::default(),
+            renderer: None,
+            textures: TextureCache::default(),
+            timer: Instant::now(),
+            ime_scroller: ImeScroller::default(),
+            stats: FrameHistory::default(),
+            viewports: Arc::default(),
+            command_host: None,
+            message_host: None,
+            screenshot_requested: false,
+            pending_events: Vec::new(),
+            last_paint: None,
+            text_focused: false,
+            app,
+        }
+    }
+
+    /// Hosts the app registered under `app_id`, falling back to the demo.
+    pub fn for_app_id(app_id: &str) -> Self {
+        let app = app::create_app(app_id).unwrap_or_else(|| {
+            log::error!("App {app_id:?} is not registered, showing the demo instead");
+            Box::new(demo_app::AppState::new())
+        });
+        Self::new(app)
+    }
+
+    /// The GL context currently rendered with, if any.
+    pub fn gl_info(&self) -> Option<Arc<GlInfo>> {
+        self.renderer
+            .as_ref()
+            .and_then(|renderer| renderer.gl_info())
+    }
+
+    /// Timings of recent frames.
+    pub fn stats(&self) -> &FrameHistory {
+        &self.stats
+    }
+
+    pub fn has_renderer(&self) -> bool {
+        self.renderer.is_some()
+    }
+
+    /// Draws subsequent frames with `renderer`, first uploading every texture egui thinks exists.
+    pub fn set_renderer(&mut self, mut renderer: Box<dyn SurfaceRenderer>) {
+        if let Some(gl_info) = renderer.gl_info() {
+            gl_info.store(&self.egui_ctx);
+        }
+        for (id, image_delta) in self.textures.full_deltas() {
+            renderer.set_texture(id, image_delta);
+        }
+        if let Some(mut old) = self.renderer.replace(renderer) {
+            old.destroy();
+        }
+    }
+
+    /// Shows child viewports on surfaces from `host`. Without one, or once it declines a
+    /// viewport, they are embedded in the root surface, which only looks right for viewports
+    /// opened with [`viewports::show_deferred`] or [`viewports::show_immediate`].
+    ///
+    /// [`viewports::show_deferred`]: crate::viewports::show_deferred
+    /// [`viewports::show_immediate`]: crate::viewports::show_immediate
+    pub fn set_viewport_host(&mut self, host: Box<dyn ViewportHost>) {
+        self.viewports.lock().set_host(&self.egui_ctx, host);
+    }
+
+    /// Carries out the [`ViewportCommand`]s the app sends from now on.
+    pub fn set_command_host(&mut self, host: Box<dyn ViewportCommandHost>) {
+        self.command_host = Some(host);
+    }
+
+    /// Delivers the messages the app sends from now on.
+    pub fn set_message_host(&mut self, host: Box<dyn MessageHost>) {
+        self.message_host = Some(host);
+    }
+
+    /// The child viewports currently shown on host surfaces.
+    pub fn viewport_ids(&self) -> Vec<ViewportId> {
+        self.viewports.lock().ids().collect()
+    }
+
+    /// Input from the surface showing `id`, for its next pass. Events are added to any that
+    /// haven't been processed yet.
+    pub fn set_viewport_input(&mut self, id: ViewportId, input: FrameInput) {
+        self.viewports.lock().set_input(id, input);
+    }
+
+    /// The user wants to close the surface showing `id`. The app sees
+    /// [`egui::ViewportInfo::close_requested`] in the viewport's next pass.
+    pub fn request_viewport_close(&mut self, id: ViewportId) {
+        self.viewports.lock().request_close(id);
+    }
+
+    /// The surface showing `id` lost its renderer with its EGL context, or failed to create one.
+    /// Its texture changes are dropped until [`Self::viewport_frame`] is called for it again.
+    pub fn viewport_renderer_lost(&mut self, id: ViewportId) {
+        self.viewports.lock().renderer_lost(id);
+    }
+
+    /// The latest frame for the surface showing `id`, with the texture changes since the last
+    /// call. A new renderer passes `all_textures` to get every live texture instead.
+    pub fn viewport_frame(&mut self, id: ViewportId, all_textures: bool) -> Option<ViewportFrame> {
+        let textures = all_textures.then(|| TexturesDelta {
+            set: self
+                .textures
+                .full_deltas()
+                .map(|(id, image_delta)| (id, image_delta.clone()))
+                .collect(),
+            free: Vec::new(),
+        });
+        self.viewports.lock().frame(id, textures)
+    }
+
+    /// Runs the app and paints the result with the current renderer.
+    ///
+    /// Without a renderer the app still runs and texture updates are kept for later, but nothing
+    /// is drawn.
+    pub fn frame(&mut self, input: FrameInput) -> FrameOutput {
+        let total = Span::begin(c"egui frame");
+        let mut stats = FrameStats::default();
+
+        let span = Span::begin(c"egui input");
+        let max_texture_side = self
+            .renderer
+            .as_ref()
+            .map(|renderer| renderer.max_texture_side());
+
+        let display = input.display;
+        display.apply(&self.egui_ctx);
+        crash::record_frame(crash::FrameContext {
+            size_px: input.size_px,
+            display,
+            pass_nr: self.egui_ctx.cumulative_pass_nr(),
+        });
+        if input.accessibility || self.text_focused {
+            self.egui_ctx.enable_accesskit();
+        } else {
+            self.egui_ctx.disable_accesskit();
+        }
+
+        // The size of the surface, in points
+        let [width_px, height_px] = input.size_px;
+        let surface_size =
+            egui::vec2(width_px as f32, height_px as f32) / display.pixels_per_point();
+        let screen_rect = Rect::from_min_size(Default::default(), surface_size);
+
+        insets::store_safe_area(
+            &self.egui_ctx,
+            ViewportId::ROOT,
+            input
+                .insets
+                .safe_area(screen_rect, display.pixels_per_point()),
+        );
+
+        let mut viewports = egui::viewport::ViewportIdMap::default();
+        viewports.insert(
+            ViewportId::ROOT,
+            egui::ViewportInfo {
+                native_pixels_per_point: Some(display.density),
+                monitor_size: Some(surface_size),
+                focused: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let raw_input = egui::RawInput {
+            viewport_id: ViewportId::ROOT,
+            viewports,
+            max_texture_side,
+            screen_rect: Some(screen_rect),
+            time: Some(self.timer.elapsed().as_secs_f64()),
+            system_theme: Some(display.theme()),
+            events: std::mem::take(&mut self.pending_events)
+                .into_iter()
+                .chain(input.events)
+                .collect(),
+            ..Default::default()
+        };
+        self.viewports
+            .lock()
+            .begin_frame(&self.egui_ctx, &self.viewports, display);
+        stats.input = span.end();
+
+        let mut posted = input.messages;
+        let span = Span::begin(c"egui run");
+        let egui::FullOutput {
+            mut platform_output,
+            textures_delta,
+            shapes,
+            pixels_per_point,
+            mut viewport_output,
+        } = self.egui_ctx.run(raw_input, |ctx| {
+            self.ime_scroller.begin_pass(ctx, &input.insets);
+            for message in posted.drain(..) {
+                self.app.on_message(ctx, message);
+            }
+            self.app.update(ctx);
+            if input.show_stats {
+                self.stats.overlay(ctx);
+            }
+        });
+        self.text_focused = platform_output.ime.is_some();
+        if let Some(update) = &platform_output.accesskit_update {
+            keyboard::detect_input_type(&self.egui_ctx, update);
+        }
+        if !input.accessibility {
+            platform_output.accesskit_update = None;
+        }
+        self.run_deferred_viewports(textures_delta, &viewport_output);
+        self.deliver_messages();
+        stats.run = span.end();
+
+        let clear_color = self.app.clear_color(&self.egui_ctx.style().visuals);
+        let ChildrenOutput {
+            textures: textures_deltas,
+            platform_output: children_platform_output,
+            commands: children_commands,
+        } = self.viewports.lock().end_frame(clear_color);
+        // Children have trees of their own, but only the root's is exposed
+        let accesskit_update = platform_output.accesskit_update.take();
+        platform_output.append(children_platform_output);
+        platform_output.accesskit_update = accesskit_update;
+        for (id, commands) in children_commands {
+            if let Some(output) = viewport_output.get_mut(&id) {
+                output.commands.extend(commands);
+            }
+        }
+        self.handle_commands(&mut viewport_output);
+        for textures_delta in &textures_deltas {
+            for (id, image_delta) in &textures_delta.set {
+                self.textures.set(*id, image_delta);
+            }
+            for id in &textures_delta.free {
+                self.textures.free(*id);
+            }
+        }
+        stats.texture_bytes = self.textures.byte_size();
+
+        if let Some(renderer) = &mut self.renderer {
+            let span = Span::begin(c"egui paint");
+            renderer.clear(input.size_px, clear_color);
+            stats.paint = span.end();
+
+            let span = Span::begin(c"egui textures");
+            for (id, image_delta) in textures_deltas.iter().flat_map(|delta| &delta.set) {
+                info!("Setting texture: {id:?}");
+                renderer.set_texture(*id, image_delta);
+            }
+            stats.textures = span.end();
+
+            let span = Span::begin(c"egui tessellate");
+            let clipped_primitives = self.egui_ctx.tessellate(shapes, pixels_per_point);
+            stats.tessellate = span.end();
+            stats.count_primitives(&clipped_primitives);
+
+            let span = Span::begin(c"egui paint");
+            renderer.paint(input.size_px, pixels_per_point, &clipped_primitives);
+            stats.paint += span.end();
+
+            if std::mem::take(&mut self.screenshot_requested) {
+                let span = Span::begin(c"egui screenshot");
+                match renderer.read_pixels(input.size_px) {
+                    Some(image) => {
+                        self.pending_events.push(egui::Event::Screenshot {
+                            viewport_id: ViewportId::ROOT,
+                            image: Arc::new(image),
+                        });
+                        self.egui_ctx.request_repaint();
+                    }
+                    None => warn!("The renderer can't take screenshots"),
+                }
+                stats.paint += span.end();
+            }
+
+            let span = Span::begin(c"egui textures");
+            for id in textures_deltas.iter().flat_map(|delta| &delta.free) {
+                renderer.free_texture(*id);
+            }
+            stats.textures += span.end();
+
+            self.last_paint = Some(LastPaint {
+                size_px: input.size_px,
+                pixels_per_point,
+                clear_color,
+                primitives: clipped_primitives,
+            });
+        }
+
+        stats.total = total.end();
+        self.stats.push(stats);
+
+        FrameOutput {
+            platform_output,
+            viewport_output,
+        }
+    }
+
+    /// Opens and closes host surfaces to match the viewports the app showed, then runs a pass
+    /// of each deferred one.
+    fn run_deferred_viewports(
+        &mut self,
+        root_textures: TexturesDelta,
+        viewport_output: &egui::ViewportIdMap<egui::ViewportOutput>,
+    ) {
+        let deferred = {
+            let mut viewports = self.viewports.lock();
+            viewports.push_textures(root_textures);
+            viewports.sync(&self.egui_ctx, viewport_output);
+            viewports.deferred()
+        };
+        for (id, ui_cb) in deferred {
+            let raw_input = self.viewports.lock().raw_input(&self.egui_ctx, id);
+            // Not locked while the app runs, it may open more viewports
+            let output = self.egui_ctx.run(raw_input, |ctx| ui_cb(ctx));
+            self.viewports
+                .lock()
+                .store_output(&self.egui_ctx, id, output);
+        }
+    }
+
+    /// Passes what the app sent this frame to the message host.
+    fn deliver_messages(&mut self) {
+        let sent = messages::take_sent(&self.egui_ctx);
+        match &mut self.message_host {
+            Some(host) => {
+                for message in sent {
+                    host.deliver(message);
+                }
+            }
+            None if !sent.is_empty() => {
+                warn!("No message host, dropping {} messages", sent.len());
+            }
+            None => {}
+        }
+    }
+
+    /// Sends every viewport's commands to the command host, and tells the app which ones it
+    /// couldn't carry out.
+    fn handle_commands(&mut self, viewport_output: &mut egui::ViewportIdMap<egui::ViewportOutput>) {
+        for (&id, output) in viewport_output {
+            let mut commands = std::mem::take(&mut output.commands);
+            if id == ViewportId::ROOT && commands.contains(&ViewportCommand::Screenshot) {
+                // Read back right after this frame is painted
+                commands.retain(|command| *command != ViewportCommand::Screenshot);
+                self.screenshot_requested = true;
+            }
+            if id != ViewportId::ROOT && commands.contains(&ViewportCommand::Close) {
+                // Same as the user closing its dialog, the app stops showing it if it agrees
+                commands.retain(|command| *command != ViewportCommand::Close);
+                self.viewports.lock().request_close(id);
+                self.egui_ctx.request_repaint_of(id);
+            }
+            let pixels_per_point = self.egui_ctx.input_for(id, |i| i.pixels_per_point);
+            let unsupported = match &mut self.command_host {
+                Some(host) => commands::dispatch(id, commands, pixels_per_point, host.as_mut()),
+                None => commands,
+            };
+            if !unsupported.is_empty() {
+                warn!("Unsupported commands for viewport {id:?}: {unsupported:?}");
+            }
+            commands::store_unsupported(&self.egui_ctx, id, unsupported);
+        }
+    }
+
+    /// Paints the last frame again, scaled to fit `size_px`, into an offscreen image. Any space
+    /// left over because the aspect ratio differs is filled with the clear color.
+    ///
+    /// This is a scaled copy, not a new frame: the layout doesn't reflow to the new aspect ratio,
+    /// and text and anti-aliasing stay rasterized for the on-screen size, so they get blurry when
+    /// scaled up.
+    ///
+    /// `None` before the first frame was painted, or if the renderer can't.
+    pub fn repaint_last_frame_scaled(&mut self, size_px: [u32; 2]) -> Option<ColorImage> {
+        let last = self
+            .last_paint
+            .as_ref()
+            .filter(|last| !last.size_px.contains(&0))?;
+        let renderer = self.renderer.as_mut()?;
+        let scale = (size_px[0] as f32 / last.size_px[0] as f32)
+            .min(size_px[1] as f32 / last.size_px[1] as f32);
+        renderer.paint_offscreen(
+            size_px,
+            last.clear_color,
+            last.pixels_per_point * scale,
+            &last.primitives,
+        )
+    }
+
+    /// Drops the renderer after the EGL context was destroyed, without GL calls: its objects
+    /// died with the context, and their names may already be reused in a new one. The app and
+    /// egui's memory are kept; the host hands over a renderer for the new context with
+    /// [`Self::set_renderer`].
+    pub fn on_context_lost(&mut self) {
+        if let Some(renderer) = self.renderer.take() {
+            renderer.abandon();
+        }
+    }
+}
+
+/// What [`RustSurface::repaint_last_frame_scaled`] paints.
+struct LastPaint {
+    size_px: [u32; 2],
+    pixels_per_point: f32,
+    clear_color: [f32; 4],
+    primitives: Vec<ClippedPrimitive>,
+}
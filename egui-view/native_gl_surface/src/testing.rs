@@ -0,0 +1,333 @@
+//! Stand-ins for the Android side, so surfaces can be driven without a device.
+//!
+//! Each one records what it was asked to do. Only built for this crate's own tests and with the
+//! `test-support` feature.
+
+use std::sync::Arc;
+
+use egui::{
+    epaint::ImageDelta, output::OutputEvent, ClippedPrimitive, Color32, ColorImage, CursorIcon,
+    OpenUrl, Rect, TextureId, UserAttentionType, ViewportBuilder, ViewportCommand, ViewportId,
+};
+use parking_lot::Mutex;
+
+use crate::{
+    commands::ViewportCommandHost,
+    keyboard::{SoftKeyboard, TextInputType},
+    platform::PlatformHost,
+    renderer::SurfaceRenderer,
+    viewports::ViewportHost,
+};
+
+/// Calls recorded by one of the stand-ins. Clones share the log, so keep one to inspect after
+/// handing the other to a surface.
+struct CallLog<T>(Arc<Mutex<Vec<T>>>);
+
+impl<T> Clone for CallLog<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> Default for CallLog<T> {
+    fn default() -> Self {
+        Self(Arc::default())
+    }
+}
+
+impl<T> CallLog<T> {
+    fn record(&self, call: T) {
+        self.0.lock().push(call);
+    }
+
+    fn take(&self) -> Vec<T> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+/// A call made on a [`RecordingRenderer`].
+#[derive(Clone)]
+pub enum RenderCall {
+    Clear {
+        size_px: [u32; 2],
+        color: [f32; 4],
+    },
+    SetTexture {
+        id: TextureId,
+        delta: ImageDelta,
+    },
+    Paint {
+        size_px: [u32; 2],
+        pixels_per_point: f32,
+        primitives: Vec<ClippedPrimitive>,
+    },
+    FreeTexture(TextureId),
+    ReadPixels {
+        size_px: [u32; 2],
+    },
+    PaintOffscreen {
+        size_px: [u32; 2],
+        pixels_per_point: f32,
+        primitives: Vec<ClippedPrimitive>,
+    },
+    Destroy,
+}
+
+/// A [`SurfaceRenderer`] that only remembers what it was asked to do. Images it is asked for
+/// are blank.
+///
+/// Clones share their log.
+#[derive(Clone)]
+pub struct RecordingRenderer {
+    pub max_texture_side: usize,
+    calls: CallLog<RenderCall>,
+}
+
+impl Default for RecordingRenderer {
+    fn default() -> Self {
+        Self {
+            // The minimum GLES 3.0 guarantees
+            max_texture_side: 2048,
+            calls: CallLog::default(),
+        }
+    }
+}
+
+impl RecordingRenderer {
+    /// Everything recorded since the last call.
+    pub fn take_calls(&self) -> Vec<RenderCall> {
+        self.calls.take()
+    }
+}
+
+impl SurfaceRenderer for RecordingRenderer {
+    fn max_texture_side(&self) -> usize {
+        self.max_texture_side
+    }
+
+    fn clear(&mut self, size_px: [u32; 2], color: [f32; 4]) {
+        self.calls.record(RenderCall::Clear { size_px, color });
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        self.calls.record(RenderCall::SetTexture {
+            id,
+            delta: delta.clone(),
+        });
+    }
+
+    fn paint(&mut self, size_px: [u32; 2], pixels_per_point: f32, primitives: &[ClippedPrimitive]) {
+        self.calls.record(RenderCall::Paint {
+            size_px,
+            pixels_per_point,
+            primitives: primitives.to_vec(),
+        });
+    }
+
+    fn free_texture(&mut self, id: TextureId) {
+        self.calls.record(RenderCall::FreeTexture(id));
+    }
+
+    fn read_pixels(&mut self, size_px: [u32; 2]) -> Option<ColorImage> {
+        self.calls.record(RenderCall::ReadPixels { size_px });
+        Some(blank_image(size_px))
+    }
+
+    fn paint_offscreen(
+        &mut self,
+        size_px: [u32; 2],
+        _clear_color: [f32; 4],
+        pixels_per_point: f32,
+        primitives: &[ClippedPrimitive],
+    ) -> Option<ColorImage> {
+        self.calls.record(RenderCall::PaintOffscreen {
+            size_px,
+            pixels_per_point,
+            primitives: primitives.to_vec(),
+        });
+        Some(blank_image(size_px))
+    }
+
+    fn destroy(&mut self) {
+        self.calls.record(RenderCall::Destroy);
+    }
+}
+
+fn blank_image([width, height]: [u32; 2]) -> ColorImage {
+    ColorImage::new([width as usize, height as usize], Color32::TRANSPARENT)
+}
+
+/// A call made on a [`RecordingViewportHost`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViewportCall {
+    Open {
+        id: ViewportId,
+        title: Option<String>,
+    },
+    Close(ViewportId),
+    FrameReady(ViewportId),
+}
+
+/// A [`ViewportHost`] that remembers what it was asked to do, and accepts viewports if `accept`.
+///
+/// Clones share their log.
+#[derive(Clone, Default)]
+pub struct RecordingViewportHost {
+    pub accept: bool,
+    calls: CallLog<ViewportCall>,
+}
+
+impl RecordingViewportHost {
+    pub fn accepting() -> Self {
+        Self {
+            accept: true,
+            ..Default::default()
+        }
+    }
+
+    /// Everything recorded since the last call.
+    pub fn take_calls(&self) -> Vec<ViewportCall> {
+        self.calls.take()
+    }
+}
+
+impl ViewportHost for RecordingViewportHost {
+    fn open_viewport(&mut self, id: ViewportId, builder: &ViewportBuilder) -> bool {
+        self.calls.record(ViewportCall::Open {
+            id,
+            title: builder.title.clone(),
+        });
+        self.accept
+    }
+
+    fn close_viewport(&mut self, id: ViewportId) {
+        self.calls.record(ViewportCall::Close(id));
+    }
+
+    fn viewport_frame_ready(&mut self, id: ViewportId) {
+        self.calls.record(ViewportCall::FrameReady(id));
+    }
+}
+
+/// A [`ViewportCommandHost`] that remembers what it was asked to do, and carries it out if
+/// `supported`. IME rects are recorded in pixels.
+///
+/// Clones share their log.
+#[derive(Clone, Default)]
+pub struct RecordingCommandHost {
+    pub supported: bool,
+    calls: CallLog<(ViewportId, ViewportCommand)>,
+}
+
+impl RecordingCommandHost {
+    pub fn supporting() -> Self {
+        Self {
+            supported: true,
+            ..Default::default()
+        }
+    }
+
+    /// Everything recorded since the last call.
+    pub fn take_calls(&self) -> Vec<(ViewportId, ViewportCommand)> {
+        self.calls.take()
+    }
+
+    fn record(&self, viewport: ViewportId, command: ViewportCommand) -> bool {
+        self.calls.record((viewport, command));
+        self.supported
+    }
+}
+
+impl ViewportCommandHost for RecordingCommandHost {
+    fn close(&mut self, viewport: ViewportId) -> bool {
+        self.record(viewport, ViewportCommand::Close)
+    }
+
+    fn set_title(&mut self, viewport: ViewportId, title: &str) -> bool {
+        self.record(viewport, ViewportCommand::Title(title.to_owned()))
+    }
+
+    fn set_fullscreen(&mut self, viewport: ViewportId, fullscreen: bool) -> bool {
+        self.record(viewport, ViewportCommand::Fullscreen(fullscreen))
+    }
+
+    fn set_minimized(&mut self, viewport: ViewportId, minimized: bool) -> bool {
+        self.record(viewport, ViewportCommand::Minimized(minimized))
+    }
+
+    fn focus(&mut self, viewport: ViewportId) -> bool {
+        self.record(viewport, ViewportCommand::Focus)
+    }
+
+    fn request_user_attention(
+        &mut self,
+        viewport: ViewportId,
+        attention: UserAttentionType,
+    ) -> bool {
+        self.record(viewport, ViewportCommand::RequestUserAttention(attention))
+    }
+
+    fn set_ime_allowed(&mut self, viewport: ViewportId, allowed: bool) -> bool {
+        self.record(viewport, ViewportCommand::IMEAllowed(allowed))
+    }
+
+    fn set_ime_rect(&mut self, viewport: ViewportId, rect_px: Rect) -> bool {
+        self.record(viewport, ViewportCommand::IMERect(rect_px))
+    }
+}
+
+/// A call made on a [`RecordingSoftKeyboard`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyboardRequest {
+    Show(TextInputType),
+    Hide,
+}
+
+/// A [`SoftKeyboard`] that only remembers what it was asked to do.
+#[derive(Default)]
+pub struct RecordingSoftKeyboard {
+    pub requests: Vec<KeyboardRequest>,
+}
+
+impl SoftKeyboard for RecordingSoftKeyboard {
+    fn show(&mut self, input_type: TextInputType) {
+        self.requests.push(KeyboardRequest::Show(input_type));
+    }
+
+    fn hide(&mut self) {
+        self.requests.push(KeyboardRequest::Hide);
+    }
+}
+
+/// A [`PlatformHost`] that records every request, with a settable clipboard.
+#[derive(Default)]
+pub struct RecordingPlatformHost {
+    pub clipboard: Option<String>,
+    pub copied: Vec<String>,
+    pub opened_urls: Vec<OpenUrl>,
+    pub cursor_icons: Vec<CursorIcon>,
+    pub widget_events: Vec<OutputEvent>,
+}
+
+impl PlatformHost for RecordingPlatformHost {
+    fn copy_text(&mut self, text: &str) {
+        self.copied.push(text.to_owned());
+        self.clipboard = Some(text.to_owned());
+    }
+
+    fn paste_text(&mut self) -> Option<String> {
+        self.clipboard.clone()
+    }
+
+    fn open_url(&mut self, open_url: &OpenUrl) {
+        self.opened_urls.push(open_url.clone());
+    }
+
+    fn set_cursor_icon(&mut self, icon: CursorIcon) {
+        self.cursor_icons.push(icon);
+    }
+
+    fn widget_event(&mut self, event: &OutputEvent) {
+        self.widget_events.push(event.clone());
+    }
+}
@@ -0,0 +1,73 @@
+//! A CPU-side copy of every texture egui has uploaded.
+//!
+//! When Android throws away the EGL context, every GL texture goes with it, but egui assumes its
+//! textures (including the font atlas) still exist and will only ever send partial updates.
+//! Keeping the pixels around lets us rebuild the GPU side from scratch.
+
+use std::collections::HashMap;
+
+use egui::{epaint::ImageDelta, ImageData, TextureId};
+
+#[derive(Default)]
+pub struct TextureCache {
+    textures: HashMap<TextureId, ImageDelta>,
+}
+
+impl TextureCache {
+    /// Records a texture update, merging partial updates into the stored full image.
+    pub fn set(&mut self, id: TextureId, delta: &ImageDelta) {
+        let Some(pos) = delta.pos else {
+            self.textures.insert(id, delta.clone());
+            return;
+        };
+        let Some(stored) = self.textures.get_mut(&id) else {
+            log::warn!("Partial update of unknown texture {id:?}");
+            return;
+        };
+        stored.options = delta.options;
+        match (&mut stored.image, &delta.image) {
+            (ImageData::Color(dst), ImageData::Color(src)) => {
+                let dst = std::sync::Arc::make_mut(dst);
+                blit(&mut dst.pixels, dst.size, &src.pixels, src.size, pos);
+            }
+            (ImageData::Font(dst), ImageData::Font(src)) => {
+                blit(&mut dst.pixels, dst.size, &src.pixels, src.size, pos);
+            }
+            _ => log::warn!("Partial update of {id:?} changed its image type"),
+        }
+    }
+
+    pub fn free(&mut self, id: TextureId) {
+        self.textures.remove(&id);
+    }
+
+    /// Roughly how much GPU memory the textures use. Everything is uploaded as RGBA8.
+    pub fn byte_size(&self) -> usize {
+        self.textures
+            .values()
+            .map(|delta| delta.image.width() * delta.image.height() * 4)
+            .sum()
+    }
+
+    /// Full uploads that recreate every live texture.
+    pub fn full_deltas(&self) -> impl Iterator<Item = (TextureId, &ImageDelta)> {
+        self.textures.iter().map(|(id, delta)| (*id, delta))
+    }
+}
+
+/// Copies `src` into `dst` at `pos`, clipping anything that falls outside of `dst`.
+fn blit<T: Copy>(
+    dst: &mut [T],
+    dst_size: [usize; 2],
+    src: &[T],
+    src_size: [usize; 2],
+    pos: [usize; 2],
+) {
+    let width = src_size[0].min(dst_size[0].saturating_sub(pos[0]));
+    let height = src_size[1].min(dst_size[1].saturating_sub(pos[1]));
+    for y in 0..height {
+        let dst_start = (pos[1] + y) * dst_size[0] + pos[0];
+        let src_start = y * src_size[0];
+        dst[dst_start..dst_start + width].copy_from_slice(&src[src_start..src_start + width]);
+    }
+}
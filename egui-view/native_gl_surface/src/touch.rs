@@ -0,0 +1,242 @@
+//! Translation of Android `MotionEvent`s into egui input events.
+//!
+//! Nothing in here touches JNI, so the translation can be exercised on the host.
+
+use egui::{pos2, Modifiers, Pos2, TouchDeviceId, TouchId, TouchPhase};
+
+/// `MotionEvent.getActionMasked()`, as far as we care about it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MotionAction {
+    Down,
+    Up,
+    Move,
+    Cancel,
+    PointerDown,
+    PointerUp,
+    Other(i32),
+}
+
+impl MotionAction {
+    pub fn from_raw(action_masked: i32) -> Self {
+        // Values from `android.view.MotionEvent.ACTION_*`
+        match action_masked {
+            0 => Self::Down,
+            1 => Self::Up,
+            2 => Self::Move,
+            3 => Self::Cancel,
+            5 => Self::PointerDown,
+            6 => Self::PointerUp,
+            other => Self::Other(other),
+        }
+    }
+}
+
+/// Where one pointer was at one point in time, in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointerSample {
+    pub id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+}
+
+impl PointerSample {
+    fn pos(&self, pixels_per_point: f32) -> Pos2 {
+        pos2(self.x / pixels_per_point, self.y / pixels_per_point)
+    }
+}
+
+/// All pointers of a `MotionEvent` at a single timestamp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotionBatch {
+    /// `MotionEvent.getEventTime()` or `getHistoricalEventTime()`, in milliseconds.
+    pub time_ms: i64,
+    pub pointers: Vec<PointerSample>,
+}
+
+/// The parts of an Android `MotionEvent` needed to drive egui.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MotionEvent {
+    pub action: MotionAction,
+    /// Index into [`MotionBatch::pointers`] of the pointer that went up or down.
+    pub action_index: usize,
+    pub device_id: i32,
+    /// Batched samples older than [`Self::current`], oldest first.
+    pub history: Vec<MotionBatch>,
+    pub current: MotionBatch,
+}
+
+impl MotionEvent {
+    /// Unpacks the flat arrays the Kotlin side sends over JNI.
+    ///
+    /// `times` has one entry per historical sample followed by the event time, and `samples`
+    /// holds `[x, y, pressure]` for every pointer at every one of those times.
+    /// Returns `None` if the array lengths don't line up.
+    pub fn from_packed(
+        action_masked: i32,
+        action_index: i32,
+        device_id: i32,
+        pointer_ids: &[i32],
+        times: &[i64],
+        samples: &[f32],
+    ) -> Option<Self> {
+        let pointer_count = pointer_ids.len();
+        if pointer_count == 0
+            || times.is_empty()
+            || samples.len() != times.len() * pointer_count * 3
+        {
+            return None;
+        }
+        let action_index = usize::try_from(action_index)
+            .ok()
+            .filter(|&i| i < pointer_count)?;
+
+        let mut batches = times
+            .iter()
+            .zip(samples.chunks_exact(pointer_count * 3))
+            .map(|(&time_ms, batch)| MotionBatch {
+                time_ms,
+                pointers: pointer_ids
+                    .iter()
+                    .zip(batch.chunks_exact(3))
+                    .map(|(&id, s)| PointerSample {
+                        id,
+                        x: s[0],
+                        y: s[1],
+                        pressure: s[2],
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        let current = batches.pop()?;
+
+        Some(Self {
+            action: MotionAction::from_raw(action_masked),
+            action_index,
+            device_id,
+            history: batches,
+            current,
+        })
+    }
+}
+
+/// Turns a stream of [`MotionEvent`]s into [`egui::Event::Touch`] events, plus the synthetic
+/// pointer events egui expects for the first finger that went down.
+#[derive(Default)]
+pub struct TouchTranslator {
+    /// The pointer id currently driving egui's pointer, if any.
+    primary: Option<i32>,
+    /// Time of the newest sample already translated, to skip replayed history.
+    last_time_ms: Option<i64>,
+}
+
+impl TouchTranslator {
+    pub fn translate(
+        &mut self,
+        event: &MotionEvent,
+        pixels_per_point: f32,
+        out: &mut Vec<egui::Event>,
+    ) {
+        let device_id = TouchDeviceId(event.device_id as u64);
+
+        for batch in &event.history {
+            if self.last_time_ms.is_some_and(|last| batch.time_ms <= last) {
+                continue;
+            }
+            for sample in &batch.pointers {
+                self.moved(device_id, sample, pixels_per_point, out);
+            }
+            self.last_time_ms = Some(batch.time_ms);
+        }
+
+        let pointers = &event.current.pointers;
+        match event.action {
+            MotionAction::Down | MotionAction::PointerDown => {
+                let acting = &pointers[event.action_index];
+                let pos = acting.pos(pixels_per_point);
+                out.push(touch(device_id, acting, TouchPhase::Start, pos));
+                if self.primary.is_none() {
+                    self.primary = Some(acting.id);
+                    out.push(egui::Event::PointerMoved(pos));
+                    out.push(egui::Event::PointerButton {
+                        pos,
+                        button: egui::PointerButton::Primary,
+                        pressed: true,
+                        modifiers: Modifiers::NONE,
+                    });
+                }
+            }
+            MotionAction::Move => {
+                for sample in pointers {
+                    self.moved(device_id, sample, pixels_per_point, out);
+                }
+            }
+            MotionAction::Up | MotionAction::PointerUp => {
+                let acting = &pointers[event.action_index];
+                let pos = acting.pos(pixels_per_point);
+                out.push(touch(device_id, acting, TouchPhase::End, pos));
+                if self.primary == Some(acting.id) {
+                    self.primary = None;
+                    release(pos, out);
+                }
+            }
+            MotionAction::Cancel => {
+                for sample in pointers {
+                    let pos = sample.pos(pixels_per_point);
+                    out.push(touch(device_id, sample, TouchPhase::Cancel, pos));
+                }
+                if let Some(primary) = self.primary.take() {
+                    // `PointerGone` doesn't release the button, so drags would stick
+                    let sample = pointers
+                        .iter()
+                        .find(|sample| sample.id == primary)
+                        .unwrap_or(&pointers[0]);
+                    release(sample.pos(pixels_per_point), out);
+                }
+            }
+            MotionAction::Other(_) => {}
+        }
+
+        self.last_time_ms = Some(event.current.time_ms);
+    }
+
+    fn moved(
+        &self,
+        device_id: TouchDeviceId,
+        sample: &PointerSample,
+        pixels_per_point: f32,
+        out: &mut Vec<egui::Event>,
+    ) {
+        let pos = sample.pos(pixels_per_point);
+        out.push(touch(device_id, sample, TouchPhase::Move, pos));
+        if self.primary == Some(sample.id) {
+            out.push(egui::Event::PointerMoved(pos));
+        }
+    }
+}
+
+fn touch(
+    device_id: TouchDeviceId,
+    sample: &PointerSample,
+    phase: TouchPhase,
+    pos: Pos2,
+) -> egui::Event {
+    egui::Event::Touch {
+        device_id,
+        id: TouchId(sample.id as u64),
+        phase,
+        pos,
+        force: Some(sample.pressure.clamp(0.0, 1.0)),
+    }
+}
+
+/// Releases the primary button at `pos` and lets egui know the pointer left.
+fn release(pos: Pos2, out: &mut Vec<egui::Event>) {
+    out.push(egui::Event::PointerButton {
+        pos,
+        button: egui::PointerButton::Primary,
+        pressed: false,
+        modifiers: Modifiers::NONE,
+    });
+    out.push(egui::Event::PointerGone);
+}
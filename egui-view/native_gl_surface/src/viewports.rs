@@ -0,0 +1,513 @@
+//! Child viewports, from [`egui::Context::show_viewport_deferred`] and
+//! [`egui::Context::show_viewport_immediate`], shown on surfaces the host provides.
+//!
+//! Android only lets a surface be drawn from its own render thread, and egui can only run one
+//! pass at a time, so every viewport's pass runs on the root's render thread: deferred ones right
+//! after the root pass, immediate ones in the middle of it. Each pass gets its own
+//! [`egui::ViewportInfo`] and the input its surface collected. The result is kept as a
+//! [`ViewportFrame`] until the child surface's render thread picks it up and paints it.
+//!
+//! A host that can't show another surface declines in [`ViewportHost::open_viewport`]. From then
+//! on the context embeds viewports: egui calls the app's viewport callback in the middle of the
+//! root pass, with [`egui::ViewportClass::Embedded`], and whatever it draws lands on the root
+//! surface. The surface can't wrap that in a window for the app, since the callback draws with the
+//! whole [`Context`]. Apps either open viewports with [`show_deferred`] and [`show_immediate`],
+//! which put the content in an [`egui::Window`] then, or check the class themselves when calling
+//! [`Context::show_viewport_deferred`] or [`Context::show_viewport_immediate`] directly.
+
+use std::sync::Arc;
+
+use egui::{
+    ClippedPrimitive, Context, DeferredViewportUiCallback, FullOutput, ImmediateViewport, Rect,
+    TexturesDelta, ViewportBuilder, ViewportClass, ViewportId, ViewportIdMap, ViewportInfo,
+    ViewportOutput,
+};
+use log::{info, warn};
+use parking_lot::Mutex;
+
+use crate::{display::DisplayConfig, insets, renderer::SurfaceRenderer, surface::FrameInput};
+
+/// Size of a viewport, in points, until its surface reports one, if the app didn't ask for one.
+const DEFAULT_SIZE: egui::Vec2 = egui::vec2(320.0, 240.0);
+
+/// Provides surfaces for child viewports.
+pub trait ViewportHost: Send {
+    /// Shows a new surface for `id`, e.g. in a dialog. Returning `false` makes every viewport
+    /// embedded in the root surface from then on; see the [module docs](self) for what apps
+    /// have to do about that.
+    fn open_viewport(&mut self, id: ViewportId, builder: &ViewportBuilder) -> bool;
+
+    /// The app stopped showing `id`, so its surface can go.
+    fn close_viewport(&mut self, id: ViewportId);
+
+    /// A new frame for `id` is ready; see [`RustSurface::viewport_frame`].
+    ///
+    /// [`RustSurface::viewport_frame`]: crate::surface::RustSurface::viewport_frame
+    fn viewport_frame_ready(&mut self, id: ViewportId);
+}
+
+/// One frame of a child viewport, tessellated and ready to paint.
+#[derive(Clone)]
+pub struct ViewportFrame {
+    pub size_px: [u32; 2],
+    pub pixels_per_point: f32,
+    pub clear_color: [f32; 4],
+    pub primitives: Vec<ClippedPrimitive>,
+    /// Texture changes the child's renderer hasn't seen yet, oldest first
+    pub textures: Vec<TexturesDelta>,
+}
+
+impl ViewportFrame {
+    /// Applies the texture changes and draws the frame.
+    pub fn paint(&self, renderer: &mut dyn SurfaceRenderer) {
+        for delta in &self.textures {
+            for (id, image_delta) in &delta.set {
+                renderer.set_texture(*id, image_delta);
+            }
+        }
+        renderer.clear(self.size_px, self.clear_color);
+        renderer.paint(self.size_px, self.pixels_per_point, &self.primitives);
+        for delta in &self.textures {
+            for id in &delta.free {
+                renderer.free_texture(*id);
+            }
+        }
+    }
+}
+
+/// A viewport the host accepted.
+struct Child {
+    parent: ViewportId,
+    builder: ViewportBuilder,
+    /// `None` for immediate viewports, which run inside their parent's pass
+    ui_cb: Option<Arc<DeferredViewportUiCallback>>,
+    /// Latest input from the child's surface. `events` are taken by each pass.
+    input: FrameInput,
+    /// Whether the surface has reported its size yet
+    has_surface: bool,
+    close_requested: bool,
+    /// The latest frame, without textures
+    frame: Option<ViewportFrame>,
+    /// Texture changes since the child's renderer last took a frame
+    textures: Vec<TexturesDelta>,
+    /// Whether the child's surface has a renderer that took a frame. Texture changes are only
+    /// kept for it then; a new renderer gets every live texture instead.
+    has_renderer: bool,
+    /// `frame` changed since the host was last told
+    frame_ready: bool,
+}
+
+impl Child {
+    fn raw_input(
+        &mut self,
+        ctx: &Context,
+        id: ViewportId,
+        time: f64,
+        max_texture_side: usize,
+    ) -> egui::RawInput {
+        let display = self.input.display;
+        let size = self.size_points();
+        let screen_rect = Rect::from_min_size(Default::default(), size);
+        insets::store_safe_area(
+            ctx,
+            id,
+            self.input
+                .insets
+                .safe_area(screen_rect, display.pixels_per_point()),
+        );
+
+        let mut events = Vec::new();
+        if std::mem::take(&mut self.close_requested) {
+            events.push(egui::ViewportEvent::Close);
+        }
+        let info = ViewportInfo {
+            parent: Some(self.parent),
+            title: self.builder.title.clone(),
+            events,
+            native_pixels_per_point: Some(display.density),
+            monitor_size: Some(size),
+            inner_rect: Some(screen_rect),
+            focused: Some(self.has_surface),
+            ..Default::default()
+        };
+
+        egui::RawInput {
+            viewport_id: id,
+            viewports: std::iter::once((id, info)).collect(),
+            max_texture_side: Some(max_texture_side),
+            screen_rect: Some(screen_rect),
+            time: Some(time),
+            system_theme: Some(display.theme()),
+            events: std::mem::take(&mut self.input.events),
+            ..Default::default()
+        }
+    }
+
+    fn size_points(&self) -> egui::Vec2 {
+        if self.has_surface {
+            let [width_px, height_px] = self.input.size_px;
+            egui::vec2(width_px as f32, height_px as f32) / self.input.display.pixels_per_point()
+        } else {
+            self.builder.inner_size.unwrap_or(DEFAULT_SIZE)
+        }
+    }
+}
+
+/// Every child viewport of one surface, shared with [`render_immediate`] through the context.
+#[derive(Default)]
+pub(crate) struct Viewports {
+    /// `None` once viewports are embedded
+    host: Option<Box<dyn ViewportHost>>,
+    children: ViewportIdMap<Child>,
+    /// What children start out with, before their surface reports its own
+    display: DisplayConfig,
+    /// Texture changes from every pass of the current frame, in order
+    textures: Vec<TexturesDelta>,
+    /// Output of child passes, for the root to act on
+    platform_output: egui::PlatformOutput,
+    /// Commands sent during child passes
+    commands: ViewportIdMap<Vec<egui::ViewportCommand>>,
+}
+
+/// What the passes of child viewports left for the root's frame to handle.
+pub(crate) struct ChildrenOutput {
+    /// Texture changes of every pass this frame, the root's included, in order
+    pub textures: Vec<TexturesDelta>,
+    pub platform_output: egui::PlatformOutput,
+    pub commands: ViewportIdMap<Vec<egui::ViewportCommand>>,
+}
+
+impl Viewports {
+    fn id() -> egui::Id {
+        egui::Id::new("native_gl_surface::viewports")
+    }
+
+    pub fn set_host(&mut self, ctx: &Context, host: Box<dyn ViewportHost>) {
+        ctx.set_embed_viewports(false);
+        self.host = Some(host);
+    }
+
+    /// Prepares for the root pass. `shared` must be the mutex around `self`.
+    pub fn begin_frame(
+        &mut self,
+        ctx: &Context,
+        shared: &Arc<Mutex<Self>>,
+        display: DisplayConfig,
+    ) {
+        self.display = display;
+        if self.host.is_some() {
+            // Both are per thread or per memory, and either may have been replaced since the
+            // last frame
+            Context::set_immediate_viewport_renderer(render_immediate);
+            ctx.data_mut(|d| d.insert_temp(Self::id(), Arc::clone(shared)));
+        }
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = ViewportId> + '_ {
+        self.children.keys().copied()
+    }
+
+    pub fn set_input(&mut self, id: ViewportId, mut input: FrameInput) {
+        let Some(child) = self.children.get_mut(&id) else {
+            return;
+        };
+        let events = std::mem::take(&mut child.input.events);
+        input.events.splice(0..0, events);
+        child.input = input;
+        child.has_surface = true;
+    }
+
+    pub fn request_close(&mut self, id: ViewportId) {
+        if let Some(child) = self.children.get_mut(&id) {
+            child.close_requested = true;
+        }
+    }
+
+    /// The latest frame for `id`, with the texture changes since the last call, or `textures`
+    /// instead if given.
+    pub fn frame(
+        &mut self,
+        id: ViewportId,
+        textures: Option<TexturesDelta>,
+    ) -> Option<ViewportFrame> {
+        let child = self.children.get_mut(&id)?;
+        let mut frame = child.frame.clone()?;
+        let pending = std::mem::take(&mut child.textures);
+        frame.textures = match textures {
+            Some(all) => vec![all],
+            None => pending,
+        };
+        child.has_renderer = true;
+        Some(frame)
+    }
+
+    /// The surface showing `id` lost its renderer, or couldn't create one. Texture changes
+    /// aren't kept for it until it takes a frame again.
+    pub fn renderer_lost(&mut self, id: ViewportId) {
+        if let Some(child) = self.children.get_mut(&id) {
+            child.has_renderer = false;
+            child.textures = Vec::new();
+        }
+    }
+
+    /// Opens surfaces for new viewports in `outputs` and closes those of viewports that are gone.
+    pub fn sync(&mut self, ctx: &Context, outputs: &ViewportIdMap<ViewportOutput>) {
+        let Some(host) = &mut self.host else {
+            return;
+        };
+        self.children.retain(|id, _| {
+            let keep = outputs.contains_key(id);
+            if !keep {
+                info!("Closing viewport {id:?}");
+                host.close_viewport(*id);
+            }
+            keep
+        });
+
+        for (&id, output) in outputs {
+            if id == ViewportId::ROOT {
+                continue;
+            }
+            match self.children.get_mut(&id) {
+                Some(child) => {
+                    child.parent = output.parent;
+                    child.builder = output.builder.clone();
+                    child.ui_cb = output.viewport_ui_cb.clone();
+                }
+                // Immediate viewports are opened as they run, so this is a new deferred one
+                None => {
+                    if let Some(ui_cb) = &output.viewport_ui_cb {
+                        self.open(ctx, id, output.parent, &output.builder, Some(ui_cb.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The deferred viewports to run after the root pass.
+    pub fn deferred(&self) -> Vec<(ViewportId, Arc<DeferredViewportUiCallback>)> {
+        self.children
+            .iter()
+            .filter_map(|(&id, child)| Some((id, child.ui_cb.clone()?)))
+            .collect()
+    }
+
+    /// Input for `id`'s next pass. Viewports without a surface get a throwaway one, so the app's
+    /// code still runs but nothing is shown.
+    pub fn raw_input(&mut self, ctx: &Context, id: ViewportId) -> egui::RawInput {
+        match self.children.get_mut(&id) {
+            Some(child) => {
+                let (time, max_texture_side) = ctx.input(|i| (i.time, i.max_texture_side));
+                child.raw_input(ctx, id, time, max_texture_side)
+            }
+            None => offscreen_input(ctx, id),
+        }
+    }
+
+    /// Keeps the result of a pass of `id`.
+    pub fn store_output(&mut self, ctx: &Context, id: ViewportId, output: FullOutput) {
+        let FullOutput {
+            platform_output,
+            textures_delta,
+            shapes,
+            pixels_per_point,
+            viewport_output,
+        } = output;
+        self.platform_output.append(platform_output);
+        // A deferred pass is the outermost one, so egui hands it the commands for every viewport
+        for (id, output) in viewport_output {
+            if !output.commands.is_empty() {
+                self.commands.entry(id).or_default().extend(output.commands);
+            }
+        }
+        self.textures.push(textures_delta);
+
+        let Some(child) = self.children.get_mut(&id) else {
+            return;
+        };
+        let size = child.size_points() * pixels_per_point;
+        child.frame = Some(ViewportFrame {
+            size_px: [size.x.round() as u32, size.y.round() as u32],
+            pixels_per_point,
+            clear_color: [0.0; 4],
+            primitives: ctx.tessellate(shapes, pixels_per_point),
+            textures: Vec::new(),
+        });
+        child.frame_ready = true;
+    }
+
+    /// Adds the root pass's texture changes, which come after those of immediate viewports.
+    pub fn push_textures(&mut self, textures_delta: TexturesDelta) {
+        self.textures.push(textures_delta);
+    }
+
+    /// Hands every texture change of this frame to the children, tells the host which frames
+    /// are ready and returns what's left for the root.
+    pub fn end_frame(&mut self, clear_color: [f32; 4]) -> ChildrenOutput {
+        let textures = std::mem::take(&mut self.textures);
+        for (&id, child) in &mut self.children {
+            if child.has_renderer {
+                child.textures.extend(textures.iter().cloned());
+            }
+            if std::mem::take(&mut child.frame_ready) {
+                if let Some(frame) = &mut child.frame {
+                    frame.clear_color = clear_color;
+                }
+                if let Some(host) = &mut self.host {
+                    host.viewport_frame_ready(id);
+                }
+            }
+        }
+        ChildrenOutput {
+            textures,
+            platform_output: std::mem::take(&mut self.platform_output),
+            commands: std::mem::take(&mut self.commands),
+        }
+    }
+
+    /// Asks the host for a surface, switching to embedded viewports if it declines.
+    fn open(
+        &mut self,
+        ctx: &Context,
+        id: ViewportId,
+        parent: ViewportId,
+        builder: &ViewportBuilder,
+        ui_cb: Option<Arc<DeferredViewportUiCallback>>,
+    ) {
+        let Some(host) = &mut self.host else {
+            return;
+        };
+        if !host.open_viewport(id, builder) {
+            warn!("Host declined viewport {id:?}, embedding viewports from now on");
+            self.embed(ctx);
+            return;
+        }
+        info!("Opened viewport {id:?} ({:?})", builder.title);
+        self.children.insert(
+            id,
+            Child {
+                parent,
+                builder: builder.clone(),
+                ui_cb,
+                input: FrameInput {
+                    display: self.display,
+                    ..Default::default()
+                },
+                has_surface: false,
+                close_requested: false,
+                frame: None,
+                textures: Vec::new(),
+                has_renderer: false,
+                frame_ready: false,
+            },
+        );
+    }
+
+    fn embed(&mut self, ctx: &Context) {
+        ctx.set_embed_viewports(true);
+        if let Some(mut host) = self.host.take() {
+            for id in self.children.keys() {
+                host.close_viewport(*id);
+            }
+        }
+        self.children.clear();
+        ctx.request_repaint();
+    }
+}
+
+/// Runs an immediate viewport's pass in the middle of its parent's.
+fn render_immediate(ctx: &Context, viewport: ImmediateViewport<'_>) {
+    let ImmediateViewport {
+        ids,
+        builder,
+        mut viewport_ui_cb,
+    } = viewport;
+    let shared = ctx.data(|d| d.get_temp::<Arc<Mutex<Viewports>>>(Viewports::id()));
+    let raw_input = match &shared {
+        Some(shared) => {
+            let mut viewports = shared.lock();
+            match viewports.children.get_mut(&ids.this) {
+                Some(child) => {
+                    child.parent = ids.parent;
+                    child.builder = builder;
+                }
+                None => viewports.open(ctx, ids.this, ids.parent, &builder, None),
+            }
+            viewports.raw_input(ctx, ids.this)
+        }
+        None => offscreen_input(ctx, ids.this),
+    };
+    // The lock must not be held here, the app may open more viewports
+    let output = ctx.run(raw_input, |ctx| viewport_ui_cb(ctx));
+    if let Some(shared) = shared {
+        shared.lock().store_output(ctx, ids.this, output);
+    }
+}
+
+/// Input for a pass of `id` whose output is thrown away.
+fn offscreen_input(ctx: &Context, id: ViewportId) -> egui::RawInput {
+    let (time, max_texture_side) = ctx.input(|i| (i.time, i.max_texture_side));
+    egui::RawInput {
+        viewport_id: id,
+        viewports: std::iter::once((id, ViewportInfo::default())).collect(),
+        max_texture_side: Some(max_texture_side),
+        screen_rect: Some(Rect::from_min_size(Default::default(), DEFAULT_SIZE)),
+        time: Some(time),
+        ..Default::default()
+    }
+}
+
+/// Shows `add_contents` in a deferred viewport, or in an [`egui::Window`] if viewports are
+/// embedded.
+///
+/// Prefer this to [`Context::show_viewport_deferred`], whose callback gets the root's whole
+/// context once the host declines a viewport.
+pub fn show_deferred(
+    ctx: &Context,
+    id: ViewportId,
+    builder: ViewportBuilder,
+    add_contents: impl Fn(&mut egui::Ui) + Send + Sync + 'static,
+) {
+    let title = builder.title.clone().unwrap_or_default();
+    let size = builder.inner_size;
+    ctx.show_viewport_deferred(id, builder, move |ctx, class| {
+        show_contents(ctx, id, class, &title, size, &add_contents);
+    });
+}
+
+/// Shows `add_contents` in an immediate viewport, or in an [`egui::Window`] if viewports are
+/// embedded.
+///
+/// Prefer this to [`Context::show_viewport_immediate`], for the same reason as
+/// [`show_deferred`].
+pub fn show_immediate(
+    ctx: &Context,
+    id: ViewportId,
+    builder: ViewportBuilder,
+    mut add_contents: impl FnMut(&mut egui::Ui),
+) {
+    let title = builder.title.clone().unwrap_or_default();
+    let size = builder.inner_size;
+    ctx.show_viewport_immediate(id, builder, |ctx, class| {
+        show_contents(ctx, id, class, &title, size, &mut add_contents);
+    });
+}
+
+fn show_contents(
+    ctx: &Context,
+    id: ViewportId,
+    class: ViewportClass,
+    title: &str,
+    size: Option<egui::Vec2>,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) {
+    if class == ViewportClass::Embedded {
+        let mut window = egui::Window::new(title).id(id.0);
+        if let Some(size) = size {
+            window = window.default_size(size);
+        }
+        window.show(ctx, add_contents);
+    } else {
+        egui::CentralPanel::default().show(ctx, add_contents);
+    }
+}
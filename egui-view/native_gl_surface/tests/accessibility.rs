@@ -0,0 +1,174 @@
+use egui::accesskit::Role;
+use native_gl_surface::{
+    accessibility::{android_action, AccessNode, AccessibilityTree, HOST_VIEW_ID},
+    demo_app::AppState,
+    display::DisplayConfig,
+    surface::{FrameInput, RustSurface},
+};
+
+fn input(events: Vec<egui::Event>) -> FrameInput {
+    FrameInput {
+        size_px: [720, 1280],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        events,
+        accessibility: true,
+        ..Default::default()
+    }
+}
+
+/// Runs the demo and keeps its tree up to date.
+struct Demo {
+    surface: RustSurface,
+    tree: AccessibilityTree,
+}
+
+impl Demo {
+    fn new() -> Self {
+        let mut demo = Self {
+            surface: RustSurface::new(Box::new(AppState::new())),
+            tree: AccessibilityTree::default(),
+        };
+        // Layout settles after the first frame
+        demo.frame(Vec::new());
+        demo.frame(Vec::new());
+        demo
+    }
+
+    /// Returns whether the tree changed.
+    fn frame(&mut self, events: Vec<egui::Event>) -> bool {
+        let update = self
+            .surface
+            .frame(input(events))
+            .platform_output
+            .accesskit_update
+            .expect("AccessKit update");
+        self.tree.update(update)
+    }
+
+    fn find(&self, role: Role, label: &str) -> AccessNode {
+        self.tree
+            .nodes()
+            .find(|node| node.role == role && node.label.as_deref() == Some(label))
+            .unwrap_or_else(|| panic!("no {role:?} labelled {label:?}"))
+            .clone()
+    }
+
+    /// Performs `action` on `node` the way the Kotlin provider does.
+    fn perform(&mut self, node: &AccessNode, action: i32, argument: Option<f64>) {
+        let request = self
+            .tree
+            .action_request(node.virtual_id, action, argument)
+            .expect("supported action");
+        self.frame(vec![egui::Event::AccessKitActionRequest(request)]);
+    }
+
+    fn age(&self) -> f64 {
+        self.find(Role::Slider, "age").range.unwrap().current
+    }
+}
+
+#[test]
+fn tree_describes_the_demo_widgets() {
+    let demo = Demo::new();
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(720.0, 1280.0));
+
+    let root = demo.tree.node(HOST_VIEW_ID).expect("root");
+    assert_eq!(root.parent, None);
+    assert!(!root.children.is_empty());
+
+    let button = demo.find(Role::Button, "Increment");
+    assert_eq!(button.class_name(), "android.widget.Button");
+    assert!(button.is_clickable());
+    assert!(button.enabled);
+    let bounds = button.bounds.unwrap();
+    assert!(screen.contains_rect(bounds), "{bounds:?}");
+    // In pixels, so twice the points egui laid it out in
+    assert!(bounds.height() > 30.0, "{bounds:?}");
+
+    let slider = demo.find(Role::Slider, "age");
+    assert_eq!(slider.class_name(), "android.widget.SeekBar");
+    let range = slider.range.unwrap();
+    assert_eq!((range.current, range.min, range.max), (69.0, 0.0, 120.0));
+    for action in [
+        android_action::SCROLL_FORWARD,
+        android_action::SCROLL_BACKWARD,
+        android_action::SET_PROGRESS,
+    ] {
+        assert!(slider.actions.contains(&action), "{action:#x}");
+    }
+
+    let name = demo.find(Role::TextInput, "Your name: ");
+    assert!(name.is_editable());
+    assert_eq!(name.value.as_deref(), Some("Bob"));
+
+    let parent = demo.tree.node(button.parent.unwrap()).unwrap();
+    assert!(parent.children.contains(&button.virtual_id));
+}
+
+#[test]
+fn ids_are_stable_while_nothing_changes() {
+    let mut demo = Demo::new();
+    let before = demo.find(Role::Button, "Increment");
+    assert!(
+        !demo.frame(Vec::new()),
+        "an identical frame changed the tree"
+    );
+    assert_eq!(demo.find(Role::Button, "Increment"), before);
+}
+
+#[test]
+fn click_action_presses_the_button() {
+    let mut demo = Demo::new();
+    let button = demo.find(Role::Button, "Increment");
+    demo.perform(&button, android_action::CLICK, None);
+    assert!(demo.frame(Vec::new()));
+    assert_eq!(demo.age(), 70.0);
+}
+
+#[test]
+fn slider_actions_change_its_value() {
+    let mut demo = Demo::new();
+    let slider = demo.find(Role::Slider, "age");
+
+    demo.perform(&slider, android_action::SCROLL_FORWARD, None);
+    demo.frame(Vec::new());
+    assert_eq!(demo.age(), 70.0);
+
+    demo.perform(&slider, android_action::SET_PROGRESS, Some(30.0));
+    demo.frame(Vec::new());
+    assert_eq!(demo.age(), 30.0);
+}
+
+#[test]
+fn unsupported_actions_are_refused() {
+    let demo = Demo::new();
+    assert!(demo
+        .tree
+        .action_request(HOST_VIEW_ID, android_action::CLICK, None)
+        .is_none());
+    let slider = demo.find(Role::Slider, "age");
+    assert!(
+        demo.tree
+            .action_request(slider.virtual_id, android_action::SET_PROGRESS, None)
+            .is_none(),
+        "no value to set"
+    );
+}
+
+#[test]
+fn node_at_finds_the_innermost_widget() {
+    let demo = Demo::new();
+    let button = demo.find(Role::Button, "Increment");
+    let center = button.bounds.unwrap().center();
+    assert_eq!(demo.tree.node_at(center), Some(button.virtual_id));
+}
+
+#[test]
+fn no_tree_unless_enabled() {
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    let output = surface.frame(FrameInput {
+        accessibility: false,
+        ..input(Vec::new())
+    });
+    assert!(output.platform_output.accesskit_update.is_none());
+}
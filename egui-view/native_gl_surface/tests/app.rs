@@ -0,0 +1,33 @@
+use native_gl_surface::{
+    app::{self, SurfaceApp, DEMO_APP_ID},
+    demo_app::AppState,
+};
+
+struct Blank;
+
+impl SurfaceApp for Blank {
+    fn update(&mut self, _ctx: &egui::Context) {}
+}
+
+#[test]
+fn registered_apps_can_be_created() {
+    assert!(app::is_registered(DEMO_APP_ID));
+    assert!(app::create_app(DEMO_APP_ID).is_some());
+    assert!(!app::is_registered("missing"));
+    assert!(app::create_app("missing").is_none());
+
+    app::register_app("blank", || Blank);
+    assert!(app::is_registered("blank"));
+    assert!(app::create_app("blank").is_some());
+}
+
+#[test]
+fn factories_may_use_the_registry() {
+    app::register_app("wrapper", || {
+        app::register_app("inner", || Blank);
+        app::create_app(DEMO_APP_ID).expect("demo");
+        AppState::new()
+    });
+    assert!(app::create_app("wrapper").is_some());
+    assert!(app::is_registered("inner"));
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use egui::{ViewportCommand, ViewportId};
+use native_gl_surface::{
+    app::SurfaceApp,
+    commands,
+    display::DisplayConfig,
+    surface::{FrameInput, RustSurface},
+    testing::{RecordingCommandHost, RecordingViewportHost},
+};
+use parking_lot::Mutex;
+
+fn input() -> FrameInput {
+    FrameInput {
+        size_px: [400, 600],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        ..Default::default()
+    }
+}
+
+fn child_id() -> ViewportId {
+    ViewportId::from_hash_of("child")
+}
+
+/// Sends `commands` once from the root, and `child_commands` once from a deferred child
+/// viewport shown while `show_child` is set, remembering what
+/// [`commands::unsupported_commands`] said in each.
+#[derive(Clone, Default)]
+struct CommandApp {
+    commands: Arc<Mutex<Vec<ViewportCommand>>>,
+    child_commands: Arc<Mutex<Vec<ViewportCommand>>>,
+    show_child: Arc<Mutex<bool>>,
+    unsupported: Arc<Mutex<Vec<ViewportCommand>>>,
+    child_unsupported: Arc<Mutex<Vec<ViewportCommand>>>,
+    child_close_requested: Arc<Mutex<bool>>,
+}
+
+impl SurfaceApp for CommandApp {
+    fn update(&mut self, ctx: &egui::Context) {
+        *self.unsupported.lock() = commands::unsupported_commands(ctx);
+        for command in self.commands.lock().drain(..) {
+            ctx.send_viewport_cmd(command);
+        }
+        if !*self.show_child.lock() {
+            return;
+        }
+        let app = self.clone();
+        ctx.show_viewport_deferred(
+            child_id(),
+            egui::ViewportBuilder::default(),
+            move |ctx, _| {
+                *app.child_unsupported.lock() = commands::unsupported_commands(ctx);
+                *app.child_close_requested.lock() = ctx.input(|i| i.viewport().close_requested());
+                for command in app.child_commands.lock().drain(..) {
+                    ctx.send_viewport_cmd(command);
+                }
+            },
+        );
+    }
+}
+
+fn surface_with(app: &CommandApp, host: RecordingCommandHost) -> RustSurface {
+    let mut surface = RustSurface::new(Box::new(app.clone()));
+    surface.set_viewport_host(Box::new(RecordingViewportHost::accepting()));
+    surface.set_command_host(Box::new(host));
+    surface
+}
+
+#[test]
+fn supported_commands_go_to_the_host() {
+    let app = CommandApp::default();
+    let host = RecordingCommandHost::supporting();
+    let mut surface = surface_with(&app, host.clone());
+    *app.commands.lock() = vec![
+        ViewportCommand::Title("Hello".to_owned()),
+        ViewportCommand::Fullscreen(true),
+        ViewportCommand::Focus,
+    ];
+
+    surface.frame(input());
+    assert_eq!(
+        host.take_calls(),
+        [
+            (ViewportId::ROOT, ViewportCommand::Title("Hello".to_owned())),
+            (ViewportId::ROOT, ViewportCommand::Fullscreen(true)),
+            (ViewportId::ROOT, ViewportCommand::Focus),
+        ]
+    );
+
+    surface.frame(input());
+    assert!(app.unsupported.lock().is_empty());
+}
+
+#[test]
+fn unsupported_commands_are_reported_for_one_frame() {
+    let app = CommandApp::default();
+    let host = RecordingCommandHost::default();
+    let mut surface = surface_with(&app, host.clone());
+    *app.commands.lock() = vec![
+        ViewportCommand::Minimized(true),
+        ViewportCommand::Decorations(false),
+    ];
+
+    surface.frame(input());
+    // Turned down by the host, and not something hosts are asked about
+    assert_eq!(
+        host.take_calls(),
+        [(ViewportId::ROOT, ViewportCommand::Minimized(true))]
+    );
+
+    surface.frame(input());
+    assert_eq!(
+        *app.unsupported.lock(),
+        [
+            ViewportCommand::Minimized(true),
+            ViewportCommand::Decorations(false),
+        ]
+    );
+
+    surface.frame(input());
+    assert!(app.unsupported.lock().is_empty());
+}
+
+#[test]
+fn without_a_host_every_command_is_unsupported() {
+    let app = CommandApp::default();
+    let mut surface = RustSurface::new(Box::new(app.clone()));
+    *app.commands.lock() = vec![ViewportCommand::Focus];
+
+    surface.frame(input());
+    surface.frame(input());
+    assert_eq!(*app.unsupported.lock(), [ViewportCommand::Focus]);
+}
+
+#[test]
+fn ime_rect_is_sent_in_pixels() {
+    let app = CommandApp::default();
+    let host = RecordingCommandHost::supporting();
+    let mut surface = surface_with(&app, host.clone());
+    *app.commands.lock() = vec![ViewportCommand::IMERect(egui::Rect::from_min_size(
+        egui::pos2(10.0, 20.0),
+        egui::vec2(30.0, 5.0),
+    ))];
+
+    surface.frame(input());
+    assert_eq!(
+        host.take_calls(),
+        [(
+            ViewportId::ROOT,
+            ViewportCommand::IMERect(egui::Rect::from_min_size(
+                egui::pos2(20.0, 40.0),
+                egui::vec2(60.0, 10.0),
+            ))
+        )]
+    );
+}
+
+#[test]
+fn child_viewport_commands_carry_its_id() {
+    let app = CommandApp::default();
+    let host = RecordingCommandHost::default();
+    let mut surface = surface_with(&app, host.clone());
+    *app.show_child.lock() = true;
+    *app.child_commands.lock() = vec![ViewportCommand::Title("Child".to_owned())];
+
+    surface.frame(input());
+    assert_eq!(
+        host.take_calls(),
+        [(child_id(), ViewportCommand::Title("Child".to_owned()))]
+    );
+
+    surface.frame(input());
+    assert_eq!(
+        *app.child_unsupported.lock(),
+        [ViewportCommand::Title("Child".to_owned())]
+    );
+    assert!(app.unsupported.lock().is_empty());
+}
+
+#[test]
+fn closing_a_child_viewport_asks_the_app() {
+    let app = CommandApp::default();
+    let host = RecordingCommandHost::supporting();
+    let mut surface = surface_with(&app, host.clone());
+    *app.show_child.lock() = true;
+    *app.child_commands.lock() = vec![ViewportCommand::Close];
+
+    surface.frame(input());
+    assert!(host.take_calls().is_empty(), "closed the activity");
+
+    surface.frame(input());
+    assert!(*app.child_close_requested.lock());
+}
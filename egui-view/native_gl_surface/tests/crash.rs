@@ -0,0 +1,159 @@
+use std::{path::PathBuf, time::SystemTime};
+
+use native_gl_surface::{
+    crash::{self, CrashReport, CrashReportDialog, CrashReportStore, FrameContext},
+    display::DisplayConfig,
+    panics::{self, PanicReport},
+};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "native_gl_surface-crash-{}-{name}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn report(message: &str) -> CrashReport {
+    CrashReport {
+        panic: PanicReport {
+            message: message.to_owned(),
+            location: Some("src/app.rs:1:1".to_owned()),
+        },
+        thread: "GLThread 42".to_owned(),
+        time: SystemTime::now(),
+        backtrace: "0: native_gl_surface::app::draw".to_owned(),
+        frame: Some(FrameContext {
+            size_px: [1080, 2400],
+            display: DisplayConfig::new(2.75, 1.0, true),
+            pass_nr: 99,
+        }),
+        recent_logs: vec!["0.100 INFO app: Started".to_owned()],
+    }
+}
+
+#[test]
+fn store_lists_reports_oldest_first() {
+    let store = CrashReportStore::new(scratch_dir("list"));
+    assert!(store.pending().unwrap().is_empty(), "missing dir");
+
+    let first = store.write(&report("first")).unwrap();
+    let second = store.write(&report("second")).unwrap();
+    let pending = store.pending().unwrap();
+    let names: Vec<&str> = pending.iter().map(|report| report.name.as_str()).collect();
+    assert_eq!(names, [first.as_str(), second.as_str()]);
+
+    let text = &pending[0].contents;
+    for expected in [
+        "Rust panic: first at src/app.rs:1:1",
+        "Thread: GLThread 42",
+        "Surface: 1080x2400 px, density 2.75, font scale 1, night mode",
+        "Frame: 99",
+        "0: native_gl_surface::app::draw",
+        "0.100 INFO app: Started",
+    ] {
+        assert!(
+            text.contains(expected),
+            "{expected:?} missing from:\n{text}"
+        );
+    }
+
+    store.remove(&first).unwrap();
+    assert_eq!(store.pending().unwrap().len(), 1);
+    store.clear().unwrap();
+    assert!(store.pending().unwrap().is_empty());
+}
+
+#[test]
+fn store_only_touches_reports() {
+    let dir = scratch_dir("other-files");
+    let store = CrashReportStore::new(&dir);
+    store.write(&report("kept")).unwrap();
+    std::fs::write(dir.join("notes.txt"), "mine").unwrap();
+
+    assert_eq!(store.pending().unwrap().len(), 1);
+    assert!(store.remove("notes.txt").is_err());
+    assert!(store.remove("crash-../../notes.txt").is_err());
+
+    store.clear().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("notes.txt")).unwrap(),
+        "mine"
+    );
+}
+
+#[test]
+fn hook_writes_a_report() {
+    egui_logging::init(egui_logging::LogConfig::new("test"));
+    panics::install_hook();
+    let store = CrashReportStore::new(scratch_dir("hook"));
+    crash::set_report_store(Some(store.clone()));
+    crash::record_frame(FrameContext {
+        size_px: [640, 480],
+        display: DisplayConfig::new(2.0, 1.5, false),
+        pass_nr: 7,
+    });
+
+    let line = line!() + 1;
+    let _ = panics::catch(|| panic!("lost the painter"));
+    crash::set_report_store(None);
+
+    let pending = store.pending().unwrap();
+    assert_eq!(pending.len(), 1);
+    let text = &pending[0].contents;
+    for expected in [
+        format!("Rust panic: lost the painter at tests/crash.rs:{line}:"),
+        "Surface: 640x480 px, density 2, font scale 1.5\n".to_owned(),
+        "Frame: 7".to_owned(),
+        "Backtrace:".to_owned(),
+        "PANICKED: lost the painter".to_owned(),
+    ] {
+        assert!(
+            text.contains(&expected),
+            "{expected:?} missing from:\n{text}"
+        );
+    }
+
+    let _ = panics::catch(|| panic!("not written"));
+    assert_eq!(store.pending().unwrap().len(), 1, "store was unset");
+}
+
+#[test]
+fn frames_are_recorded_per_thread() {
+    let frame = |pass_nr| FrameContext {
+        size_px: [640, 480],
+        display: DisplayConfig::default(),
+        pass_nr,
+    };
+    crash::record_frame(frame(1));
+    std::thread::spawn(move || {
+        assert_eq!(crash::last_frame(), None);
+        crash::record_frame(frame(2));
+        assert_eq!(crash::last_frame(), Some(frame(2)));
+    })
+    .join()
+    .unwrap();
+    assert_eq!(crash::last_frame(), Some(frame(1)));
+}
+
+#[test]
+fn dialog_opens_only_with_reports() {
+    let empty = CrashReportDialog::new(Some(CrashReportStore::new(scratch_dir("none"))));
+    assert!(!empty.open);
+    assert!(!CrashReportDialog::new(None).open);
+
+    let store = CrashReportStore::new(scratch_dir("dialog"));
+    store.write(&report("shown")).unwrap();
+    let mut dialog = CrashReportDialog::new(Some(store.clone()));
+    assert!(dialog.open);
+    assert_eq!(dialog.reports().len(), 1);
+
+    let ctx = egui::Context::default();
+    let output = ctx.run(Default::default(), |ctx| dialog.show(ctx));
+    assert!(!output.shapes.is_empty());
+
+    // Reports written after startup belong to the next one
+    store.write(&report("later")).unwrap();
+    assert_eq!(dialog.reports().len(), 1);
+}
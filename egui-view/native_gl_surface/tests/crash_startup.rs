@@ -0,0 +1,56 @@
+//! The startup dialog is process-wide, so it gets a test binary to itself.
+
+use std::time::SystemTime;
+
+use native_gl_surface::{
+    crash::{self, CrashReport, CrashReportStore},
+    panics::PanicReport,
+};
+
+fn report(message: &str) -> CrashReport {
+    CrashReport {
+        panic: PanicReport {
+            message: message.to_owned(),
+            location: None,
+        },
+        thread: "GLThread 1".to_owned(),
+        time: SystemTime::now(),
+        backtrace: String::new(),
+        frame: None,
+        recent_logs: Vec::new(),
+    }
+}
+
+fn push_text(shape: &egui::Shape, out: &mut String) {
+    match shape {
+        egui::Shape::Text(shape) => out.push_str(shape.galley.text()),
+        egui::Shape::Vec(shapes) => shapes.iter().for_each(|shape| push_text(shape, out)),
+        _ => {}
+    }
+}
+
+#[test]
+fn startup_dialog_only_lists_earlier_reports() {
+    let dir = std::env::temp_dir().join(format!(
+        "native_gl_surface-crash-startup-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = CrashReportStore::new(dir);
+    store.write(&report("last run")).unwrap();
+
+    crash::set_report_store(Some(store.clone()));
+    store.write(&report("this run")).unwrap();
+    crash::set_report_store(Some(store.clone()));
+
+    // New windows are invisible for their first frame, while egui measures them
+    let ctx = egui::Context::default();
+    let _ = ctx.run(Default::default(), crash::show_startup_dialog);
+    let output = ctx.run(Default::default(), crash::show_startup_dialog);
+    let mut text = String::new();
+    for clipped in &output.shapes {
+        push_text(&clipped.shape, &mut text);
+    }
+    assert!(text.contains("The app crashed last time."), "{text:?}");
+    store.clear().unwrap();
+}
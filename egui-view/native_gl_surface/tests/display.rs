@@ -0,0 +1,57 @@
+use native_gl_surface::display::DisplayConfig;
+
+#[test]
+fn nonsense_values_fall_back_to_one() {
+    let cases = [
+        ((2.75, 1.3), (2.75, 1.3)),
+        ((0.0, 1.0), (1.0, 1.0)),
+        ((-2.0, 1.0), (1.0, 1.0)),
+        ((f32::NAN, 1.0), (1.0, 1.0)),
+        ((f32::INFINITY, 1.0), (1.0, 1.0)),
+        ((2.0, 0.0), (2.0, 1.0)),
+        ((2.0, f32::NAN), (2.0, 1.0)),
+        ((2.0, f32::NEG_INFINITY), (2.0, 1.0)),
+    ];
+    for ((density, font_scale), expected) in cases {
+        let config = DisplayConfig::new(density, font_scale, false);
+        assert_eq!(
+            (config.density, config.font_scale),
+            expected,
+            "{density} {font_scale}"
+        );
+    }
+}
+
+#[test]
+fn font_scale_zooms_the_whole_ui() {
+    let cases = [
+        (DisplayConfig::new(1.0, 1.0, false), 1.0),
+        (DisplayConfig::new(2.0, 1.0, false), 2.0),
+        (DisplayConfig::new(2.0, 1.5, false), 3.0),
+        (DisplayConfig::new(2.625, 0.85, false), 2.625 * 0.85),
+    ];
+    for (config, expected) in cases {
+        assert_eq!(config.pixels_per_point(), expected, "{config:?}");
+    }
+
+    let ctx = egui::Context::default();
+    DisplayConfig::new(2.0, 1.5, false).apply(&ctx);
+    let _ = ctx.run(Default::default(), |_| {});
+    assert_eq!(ctx.zoom_factor(), 1.5);
+}
+
+#[test]
+fn night_mode_picks_the_theme() {
+    assert_eq!(
+        DisplayConfig::new(1.0, 1.0, true).theme(),
+        egui::Theme::Dark
+    );
+    assert_eq!(
+        DisplayConfig::new(1.0, 1.0, false).theme(),
+        egui::Theme::Light
+    );
+    assert_eq!(
+        DisplayConfig::default(),
+        DisplayConfig::new(1.0, 1.0, false)
+    );
+}
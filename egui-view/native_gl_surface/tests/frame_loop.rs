@@ -0,0 +1,161 @@
+use native_gl_surface::{
+    demo_app::AppState,
+    display::DisplayConfig,
+    stats::{FrameHistory, FrameStats, HISTORY_LEN},
+    surface::{FrameInput, RustSurface},
+    testing::{RecordingRenderer, RenderCall},
+};
+
+fn input() -> FrameInput {
+    FrameInput {
+        size_px: [400, 600],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        ..Default::default()
+    }
+}
+
+fn uploaded(calls: &[RenderCall]) -> Vec<egui::TextureId> {
+    calls
+        .iter()
+        .filter_map(|call| match call {
+            RenderCall::SetTexture { id, .. } => Some(*id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn first_frame_clears_uploads_fonts_and_paints() {
+    let recorder = RecordingRenderer::default();
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    surface.set_renderer(Box::new(recorder.clone()));
+    surface.frame(input());
+
+    let calls = recorder.take_calls();
+    match calls.first() {
+        Some(RenderCall::Clear { size_px, color }) => {
+            assert_eq!(*size_px, [400, 600]);
+            // The demo's color picker round-trips the color through HSVA, so allow for rounding
+            let expected = [1.0, 0.0, 1.0, 1.0];
+            assert!(color
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-3));
+        }
+        _ => panic!("frame doesn't start with a clear"),
+    }
+    assert!(uploaded(&calls).contains(&egui::TextureId::default()));
+    let paint = calls.iter().find_map(|call| match call {
+        RenderCall::Paint {
+            size_px,
+            pixels_per_point,
+            primitives,
+        } => Some((*size_px, *pixels_per_point, primitives.len())),
+        _ => None,
+    });
+    let (size_px, pixels_per_point, primitive_count) = paint.expect("frame was painted");
+    assert_eq!(size_px, [400, 600]);
+    assert_eq!(pixels_per_point, 2.0);
+    assert!(primitive_count > 0);
+}
+
+#[test]
+fn font_atlas_is_only_uploaded_once() {
+    let recorder = RecordingRenderer::default();
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    surface.set_renderer(Box::new(recorder.clone()));
+    surface.frame(input());
+    recorder.take_calls();
+
+    surface.frame(input());
+    assert!(!uploaded(&recorder.take_calls()).contains(&egui::TextureId::default()));
+}
+
+#[test]
+fn new_renderer_gets_every_texture_after_context_loss() {
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    let first = RecordingRenderer::default();
+    surface.set_renderer(Box::new(first.clone()));
+    surface.frame(input());
+    first.take_calls();
+
+    // The old objects died with the context, and their names may be reused in the new one
+    surface.on_context_lost();
+    assert!(first.take_calls().is_empty());
+    assert!(!surface.has_renderer());
+
+    let second = RecordingRenderer::default();
+    surface.set_renderer(Box::new(second.clone()));
+    let replayed = second.take_calls();
+    assert!(uploaded(&replayed).contains(&egui::TextureId::default()));
+    assert!(replayed.iter().all(|call| match call {
+        RenderCall::SetTexture { delta, .. } => delta.pos.is_none(),
+        _ => false,
+    }));
+}
+
+#[test]
+fn runs_without_a_renderer() {
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    surface.frame(input());
+
+    // Textures from the renderer-less frame are still handed over later
+    let recorder = RecordingRenderer::default();
+    surface.set_renderer(Box::new(recorder.clone()));
+    assert!(uploaded(&recorder.take_calls()).contains(&egui::TextureId::default()));
+}
+
+#[test]
+fn max_texture_side_reaches_egui() {
+    let mut recorder = RecordingRenderer::default();
+    recorder.max_texture_side = 4096;
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    surface.set_renderer(Box::new(recorder));
+    surface.frame(input());
+
+    assert_eq!(surface.egui_ctx.input(|i| i.max_texture_side), 4096);
+}
+
+#[test]
+fn records_frame_stats() {
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    surface.set_renderer(Box::new(RecordingRenderer::default()));
+    surface.frame(input());
+    surface.frame(input());
+
+    let stats = surface.stats();
+    assert_eq!(stats.len(), 2);
+    let latest = stats.latest().unwrap();
+    assert!(latest.primitives > 0);
+    assert!(latest.vertices > 0);
+    assert!(latest.indices >= latest.vertices);
+    assert!(latest.texture_bytes > 0);
+    assert!(latest.total >= latest.run);
+    assert_eq!(stats.to_packed().len(), 2 * FrameStats::PACKED_LEN);
+}
+
+#[test]
+fn frame_history_is_bounded() {
+    let mut history = FrameHistory::default();
+    for _ in 0..HISTORY_LEN + 10 {
+        history.push(FrameStats::default());
+    }
+    assert_eq!(history.len(), HISTORY_LEN);
+}
+
+#[test]
+fn stats_overlay_adds_to_the_frame() {
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    surface.set_renderer(Box::new(RecordingRenderer::default()));
+    surface.frame(input());
+    let without = surface.stats().latest().unwrap().vertices;
+
+    // New areas are invisible for their first frame, while egui measures them
+    for _ in 0..2 {
+        surface.frame(FrameInput {
+            show_stats: true,
+            ..input()
+        });
+    }
+    assert!(surface.stats().latest().unwrap().vertices > without);
+}
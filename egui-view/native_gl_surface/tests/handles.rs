@@ -0,0 +1,70 @@
+use native_gl_surface::handles::{HandleError, HandleRegistry};
+
+#[test]
+fn handles_resolve_until_removed() {
+    let mut registry = HandleRegistry::new();
+    let first = registry.insert("first");
+    let second = registry.insert("second");
+    assert_ne!(first, second);
+    assert_eq!(registry.len(), 2);
+    assert_eq!(*registry.get(first).unwrap(), "first");
+
+    assert_eq!(*registry.remove(first).unwrap(), "first");
+    assert_eq!(registry.get(first).unwrap_err(), HandleError::Stale);
+    assert_eq!(*registry.get(second).unwrap(), "second");
+    assert_eq!(registry.len(), 1);
+}
+
+#[test]
+fn removing_twice_is_stale() {
+    let mut registry = HandleRegistry::new();
+    let handle = registry.insert(1);
+    registry.remove(handle).unwrap();
+    assert_eq!(registry.remove(handle).unwrap_err(), HandleError::Stale);
+    assert!(registry.is_empty());
+}
+
+#[test]
+fn stale_handle_misses_the_reused_slot() {
+    let mut registry = HandleRegistry::new();
+    let old = registry.insert("old");
+    registry.remove(old).unwrap();
+    let new = registry.insert("new");
+    assert_ne!(old, new, "same slot, new generation");
+
+    assert_eq!(registry.get(old).unwrap_err(), HandleError::Stale);
+    assert_eq!(registry.remove(old).unwrap_err(), HandleError::Stale);
+    assert_eq!(registry.poison(old).unwrap_err(), HandleError::Stale);
+    assert_eq!(*registry.get(new).unwrap(), "new");
+}
+
+#[test]
+fn null_and_unissued_handles_are_rejected() {
+    let mut registry = HandleRegistry::new();
+    assert_eq!(registry.get(0).unwrap_err(), HandleError::Null);
+    assert_eq!(registry.remove(0).unwrap_err(), HandleError::Null);
+
+    let handle = registry.insert(());
+    for unissued in [handle + 1, handle + (2 << 32), handle + (1 << 32), -1] {
+        assert_eq!(
+            registry.get(unissued).unwrap_err(),
+            HandleError::Invalid,
+            "{unissued:#x}"
+        );
+    }
+    assert!(registry.get(handle).is_ok());
+}
+
+#[test]
+fn poisoned_handles_fail_until_removed() {
+    let mut registry = HandleRegistry::new();
+    let handle = registry.insert(5);
+    registry.poison(handle).unwrap();
+    assert!(registry.is_poisoned(handle));
+    assert_eq!(registry.get(handle).unwrap_err(), HandleError::Poisoned);
+
+    assert_eq!(*registry.remove(handle).unwrap(), 5);
+    let reused = registry.insert(6);
+    assert!(!registry.is_poisoned(reused));
+    assert_eq!(*registry.get(reused).unwrap(), 6);
+}
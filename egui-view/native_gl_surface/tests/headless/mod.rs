@@ -0,0 +1,308 @@
+//! Runs the `RustSurface` frame pipeline against a software GL context and compares the result
+//! with golden PNGs.
+//!
+//! Uses Mesa's surfaceless EGL platform, so it needs `libEGL` and a software driver (llvmpipe)
+//! but no display server. Without them the tests fail, unless `EGUI_SKIP_PIXEL_TESTS` is set. Set
+//! `UPDATE_GOLDENS=1` to (re)write the goldens instead of comparing.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use glow::HasContext as _;
+use khronos_egl as egl;
+use native_gl_surface::{
+    renderer::GlowRenderer,
+    surface::{FrameInput, RustSurface},
+};
+
+/// Set to skip pixel tests on machines without EGL.
+const SKIP_ENV: &str = "EGUI_SKIP_PIXEL_TESTS";
+
+/// `EGL_PLATFORM_SURFACELESS_MESA`
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// Largest per-channel difference that still counts as equal, to absorb rounding differences
+/// between Mesa versions.
+const CHANNEL_TOLERANCE: u8 = 2;
+
+/// Share of pixels allowed to exceed [`CHANNEL_TOLERANCE`], for the odd anti-aliased edge.
+const MAX_MISMATCH_RATIO: f64 = 0.001;
+
+/// A GLES 3 context current on this thread, rendering into an offscreen framebuffer.
+pub struct HeadlessGl {
+    egl: egl::Instance<egl::Static>,
+    display: egl::Display,
+    context: egl::Context,
+    pub gl: Arc<glow::Context>,
+    framebuffer: glow::Framebuffer,
+    renderbuffer: glow::Renderbuffer,
+    size: [u32; 2],
+}
+
+impl HeadlessGl {
+    /// Panics if this machine has no usable EGL, so pixel tests can't pass without drawing.
+    ///
+    /// `None` if [`SKIP_ENV`] is set, to skip them on purpose.
+    pub fn new(size: [u32; 2]) -> Option<Self> {
+        if std::env::var_os(SKIP_ENV).is_some() {
+            eprintln!("Skipping pixel test, {SKIP_ENV} is set");
+            return None;
+        }
+        match Self::try_new(size) {
+            Ok(gl) => Some(gl),
+            Err(err) => panic!("No headless GL, set {SKIP_ENV}=1 to skip pixel tests: {err}"),
+        }
+    }
+
+    fn try_new(size: [u32; 2]) -> Result<Self, String> {
+        let egl = egl::Instance::new(egl::Static);
+        // SAFETY: the surfaceless platform takes no native display
+        let display = unsafe {
+            egl.get_platform_display(
+                PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY,
+                &[egl::ATTRIB_NONE],
+            )
+        }
+        .map_err(|err| format!("eglGetPlatformDisplay: {err}"))?;
+        egl.initialize(display)
+            .map_err(|err| format!("eglInitialize: {err}"))?;
+        egl.bind_api(egl::OPENGL_ES_API)
+            .map_err(|err| format!("eglBindAPI: {err}"))?;
+
+        let config = egl
+            .choose_first_config(
+                display,
+                &[
+                    egl::RENDERABLE_TYPE,
+                    egl::OPENGL_ES3_BIT,
+                    // Defaults to windows, which surfaceless doesn't have
+                    egl::SURFACE_TYPE,
+                    0,
+                    egl::NONE,
+                ],
+            )
+            .map_err(|err| format!("eglChooseConfig: {err}"))?
+            .ok_or("no GLES 3 config")?;
+        let context = egl
+            .create_context(
+                display,
+                config,
+                None,
+                &[egl::CONTEXT_MAJOR_VERSION, 3, egl::NONE],
+            )
+            .map_err(|err| format!("eglCreateContext: {err}"))?;
+        egl.make_current(display, None, None, Some(context))
+            .map_err(|err| format!("eglMakeCurrent: {err}"))?;
+
+        // SAFETY: the context is current on this thread
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| {
+                egl.get_proc_address(name)
+                    .map_or(std::ptr::null(), |f| f as *const _)
+            })
+        };
+
+        // SAFETY: plain object creation on the current context
+        let (framebuffer, renderbuffer) = unsafe {
+            let renderbuffer = gl.create_renderbuffer()?;
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+            gl.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                glow::RGBA8,
+                size[0] as i32,
+                size[1] as i32,
+            );
+            let framebuffer = gl.create_framebuffer()?;
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(renderbuffer),
+            );
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                return Err(format!("framebuffer incomplete: {status:#x}"));
+            }
+            (framebuffer, renderbuffer)
+        };
+
+        Ok(Self {
+            egl,
+            display,
+            context,
+            gl: Arc::new(gl),
+            framebuffer,
+            renderbuffer,
+            size,
+        })
+    }
+
+    /// Runs `frames` frames of `surface` and reads back the last one.
+    pub fn render(&self, surface: &mut RustSurface, input: &FrameInput, frames: usize) -> Image {
+        assert_eq!(
+            input.size_px, self.size,
+            "FrameInput doesn't match the framebuffer"
+        );
+        if !surface.has_renderer() {
+            surface.set_renderer(Box::new(
+                GlowRenderer::new(Arc::clone(&self.gl), &[]).expect("renderer"),
+            ));
+        }
+        for _ in 0..frames {
+            surface.frame(input.clone());
+        }
+        self.read_pixels()
+    }
+
+    fn read_pixels(&self) -> Image {
+        let [width, height] = self.size;
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        // SAFETY: `pixels` is exactly large enough for an RGBA8 read of the whole framebuffer
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            self.gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(&mut pixels),
+            );
+        }
+        // GL's origin is bottom-left, PNG's is top-left
+        let row = width as usize * 4;
+        let pixels = pixels.chunks_exact(row).rev().flatten().copied().collect();
+        Image {
+            size: self.size,
+            pixels,
+        }
+    }
+}
+
+impl Drop for HeadlessGl {
+    fn drop(&mut self) {
+        // SAFETY: the objects were created on this context, which is still current
+        unsafe {
+            self.gl.delete_framebuffer(self.framebuffer);
+            self.gl.delete_renderbuffer(self.renderbuffer);
+        }
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+    }
+}
+
+/// Tightly packed RGBA8, top row first.
+#[derive(PartialEq)]
+pub struct Image {
+    pub size: [u32; 2],
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.size[0] as usize + x as usize) * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    fn read_png(path: &Path) -> Result<Self, String> {
+        let file = std::fs::File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut pixels)
+            .map_err(|err| err.to_string())?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return Err(format!("{} is not RGBA8", path.display()));
+        }
+        pixels.truncate(info.buffer_size());
+        Ok(Self {
+            size: [info.width, info.height],
+            pixels,
+        })
+    }
+
+    fn write_png(&self, path: &Path) {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let file = std::fs::File::create(path).unwrap();
+        let mut encoder = png::Encoder::new(file, self.size[0], self.size[1]);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&self.pixels).unwrap();
+    }
+}
+
+/// Compares `actual` with `tests/golden/<name>.png`.
+///
+/// On mismatch, the actual image and a diff (mismatches in red over a faded copy of the actual
+/// image) are written next to the test binaries and the test fails.
+pub fn assert_matches_golden(name: &str, actual: &Image) {
+    let golden_path = golden_dir().join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDENS").is_some() {
+        actual.write_png(&golden_path);
+        return;
+    }
+
+    let golden = Image::read_png(&golden_path).unwrap_or_else(|err| {
+        panic!("Missing golden for {name} ({err}); run with UPDATE_GOLDENS=1 to create it")
+    });
+    assert_eq!(
+        golden.size, actual.size,
+        "{name}: golden is {:?}, rendered {:?}",
+        golden.size, actual.size
+    );
+
+    let mut mismatches = 0;
+    let mut diff = Vec::with_capacity(actual.pixels.len());
+    for (expected, got) in golden
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+    {
+        let differs = expected
+            .iter()
+            .zip(got)
+            .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE);
+        if differs {
+            mismatches += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let luma = (u32::from(got[0]) + u32::from(got[1]) + u32::from(got[2])) / 3;
+            let faded = (128 + luma / 2) as u8;
+            diff.extend_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+
+    let total = actual.pixels.len() / 4;
+    if mismatches as f64 > total as f64 * MAX_MISMATCH_RATIO {
+        let out_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("pixel-tests");
+        let actual_path = out_dir.join(format!("{name}.actual.png"));
+        let diff_path = out_dir.join(format!("{name}.diff.png"));
+        actual.write_png(&actual_path);
+        Image {
+            size: actual.size,
+            pixels: diff,
+        }
+        .write_png(&diff_path);
+        panic!(
+            "{name}: {mismatches} of {total} pixels differ from {}\nactual: {}\ndiff: {}",
+            golden_path.display(),
+            actual_path.display(),
+            diff_path.display(),
+        );
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
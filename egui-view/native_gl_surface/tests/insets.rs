@@ -0,0 +1,127 @@
+use egui::{pos2, Rect};
+use native_gl_surface::insets::{ImeScroller, Insets, WindowInsets};
+
+fn ltrb(left: f32, top: f32, right: f32, bottom: f32) -> Insets {
+    Insets {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+#[test]
+fn packed_insets_are_unpacked() {
+    let packed = [0, 60, 0, 120, 0, 80, 0, 0, 1, 2, 3, 4];
+    assert_eq!(
+        WindowInsets::from_packed(&packed),
+        Some(WindowInsets {
+            system_bars: ltrb(0.0, 60.0, 0.0, 120.0),
+            display_cutout: ltrb(0.0, 80.0, 0.0, 0.0),
+            ime: ltrb(1.0, 2.0, 3.0, 4.0),
+        })
+    );
+
+    let negative = [-1, 0, 0, 0, 0, -5, 0, 0, 0, 0, 0, -100];
+    assert_eq!(
+        WindowInsets::from_packed(&negative),
+        Some(WindowInsets::default()),
+        "negative insets are clamped"
+    );
+
+    for len in [0, 4, 8, 11, 13, 16] {
+        assert_eq!(WindowInsets::from_packed(&vec![0; len]), None, "{len}");
+    }
+}
+
+#[test]
+fn safe_area_avoids_the_largest_inset_on_each_edge() {
+    let screen = Rect::from_min_max(pos2(0.0, 0.0), pos2(400.0, 800.0));
+    let cases = [
+        ("none", WindowInsets::default(), screen),
+        (
+            "bars",
+            WindowInsets {
+                system_bars: ltrb(0.0, 60.0, 0.0, 120.0),
+                ..Default::default()
+            },
+            Rect::from_min_max(pos2(0.0, 30.0), pos2(400.0, 740.0)),
+        ),
+        (
+            "cutout taller than the status bar",
+            WindowInsets {
+                system_bars: ltrb(0.0, 60.0, 0.0, 120.0),
+                display_cutout: ltrb(40.0, 100.0, 0.0, 0.0),
+                ..Default::default()
+            },
+            Rect::from_min_max(pos2(20.0, 50.0), pos2(400.0, 740.0)),
+        ),
+        (
+            "keyboard over the navigation bar",
+            WindowInsets {
+                system_bars: ltrb(0.0, 60.0, 0.0, 120.0),
+                ime: ltrb(0.0, 0.0, 0.0, 600.0),
+                ..Default::default()
+            },
+            Rect::from_min_max(pos2(0.0, 30.0), pos2(400.0, 500.0)),
+        ),
+        (
+            "keyboard taller than the view",
+            WindowInsets {
+                system_bars: ltrb(0.0, 60.0, 0.0, 0.0),
+                ime: ltrb(0.0, 0.0, 0.0, 2000.0),
+                ..Default::default()
+            },
+            Rect::from_min_max(pos2(0.0, 30.0), pos2(0.0, 30.0)),
+        ),
+    ];
+    for (name, insets, expected) in cases {
+        assert_eq!(insets.safe_area(screen, 2.0), expected, "{name}");
+    }
+}
+
+/// Runs a pass of a short scroll area scrolled to the top, with a focused text field out of view
+/// at the bottom. Returns the scroll offset the pass ended with.
+fn run(ctx: &egui::Context, scroller: &mut ImeScroller, ime_bottom: f32) -> f32 {
+    let insets = WindowInsets {
+        ime: ltrb(0.0, 0.0, 0.0, ime_bottom),
+        ..Default::default()
+    };
+    let mut scrolled = 0.0;
+    let _ = ctx.run(Default::default(), |ctx| {
+        scroller.begin_pass(ctx, &insets);
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let output = egui::ScrollArea::vertical()
+                .max_height(100.0)
+                .animated(false)
+                .vertical_scroll_offset(0.0)
+                .show(ui, |ui| {
+                    for i in 0..50 {
+                        ui.label(format!("Line {i}"));
+                    }
+                    let field = ui.add(egui::TextEdit::singleline(&mut String::new()));
+                    field.request_focus();
+                });
+            scrolled = output.state.offset.y;
+        });
+    });
+    scrolled
+}
+
+#[test]
+fn ime_scroller_only_scrolls_when_the_keyboard_grows() {
+    let ctx = egui::Context::default();
+    let mut scroller = ImeScroller::default();
+    let cases = [
+        ("no keyboard", 0.0, false),
+        ("keyboard opens", 600.0, true),
+        ("keyboard stays", 600.0, false),
+        ("keyboard shrinks", 300.0, false),
+        ("keyboard grows", 400.0, true),
+        ("keyboard closes", 0.0, false),
+    ];
+    for (name, ime_bottom, scrolls) in cases {
+        let offset = run(&ctx, &mut scroller, ime_bottom);
+        assert_eq!(offset > 0.0, scrolls, "{name}");
+    }
+}
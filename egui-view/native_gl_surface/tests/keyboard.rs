@@ -0,0 +1,321 @@
+use egui::{ImeEvent, Key, Modifiers};
+use native_gl_surface::{
+    app::SurfaceApp,
+    display::DisplayConfig,
+    keyboard::{self, ImeTranslator, SoftKeyboardState, TextInputType},
+    surface::{FrameInput, RustSurface},
+    testing::{KeyboardRequest, RecordingSoftKeyboard},
+};
+
+const ACTION_DOWN: i32 = 0;
+const ACTION_UP: i32 = 1;
+const ACTION_MULTIPLE: i32 = 2;
+const KEYCODE_A: i32 = 29;
+const KEYCODE_ENTER: i32 = 66;
+const KEYCODE_VOLUME_UP: i32 = 24;
+const META_SHIFT_ON: i32 = 0x1;
+const META_CTRL_ON: i32 = 0x1000;
+
+fn key(key: Key, pressed: bool, repeat: bool, modifiers: Modifiers) -> egui::Event {
+    egui::Event::Key {
+        key,
+        physical_key: None,
+        pressed,
+        repeat,
+        modifiers,
+    }
+}
+
+fn key_events(action: i32, key_code: i32, meta_state: i32, unicode_char: char) -> Vec<egui::Event> {
+    let mut out = Vec::new();
+    keyboard::key_events(
+        action,
+        key_code,
+        meta_state,
+        unicode_char as i32,
+        0,
+        &mut out,
+    );
+    out
+}
+
+#[test]
+fn key_events_table() {
+    let cases = [
+        (
+            "press types",
+            key_events(ACTION_DOWN, KEYCODE_A, 0, 'a'),
+            vec![
+                key(Key::A, true, false, Modifiers::NONE),
+                egui::Event::Text("a".to_owned()),
+            ],
+        ),
+        (
+            "release doesn't",
+            key_events(ACTION_UP, KEYCODE_A, 0, 'a'),
+            vec![key(Key::A, false, false, Modifiers::NONE)],
+        ),
+        (
+            "shift",
+            key_events(ACTION_DOWN, KEYCODE_A, META_SHIFT_ON, 'A'),
+            vec![
+                key(Key::A, true, false, Modifiers::SHIFT),
+                egui::Event::Text("A".to_owned()),
+            ],
+        ),
+        (
+            "shortcut",
+            key_events(ACTION_DOWN, KEYCODE_A, META_CTRL_ON, 'a'),
+            vec![key(
+                Key::A,
+                true,
+                false,
+                Modifiers {
+                    ctrl: true,
+                    ..Modifiers::COMMAND
+                },
+            )],
+        ),
+        (
+            "enter isn't text",
+            key_events(ACTION_DOWN, KEYCODE_ENTER, 0, '\n'),
+            vec![key(Key::Enter, true, false, Modifiers::NONE)],
+        ),
+        (
+            "unknown key",
+            key_events(ACTION_DOWN, KEYCODE_VOLUME_UP, 0, '\0'),
+            vec![],
+        ),
+        (
+            "multiple",
+            key_events(ACTION_MULTIPLE, KEYCODE_A, 0, 'a'),
+            vec![],
+        ),
+    ];
+    for (name, actual, expected) in cases {
+        assert_eq!(actual, expected, "{name}");
+    }
+}
+
+#[test]
+fn held_keys_repeat() {
+    let mut out = Vec::new();
+    for repeat_count in 0..3 {
+        keyboard::key_events(
+            ACTION_DOWN,
+            KEYCODE_A,
+            0,
+            'a' as i32,
+            repeat_count,
+            &mut out,
+        );
+    }
+    keyboard::key_events(ACTION_UP, KEYCODE_A, 0, 'a' as i32, 2, &mut out);
+
+    let repeats: Vec<(bool, bool)> = out
+        .iter()
+        .filter_map(|event| match event {
+            egui::Event::Key {
+                pressed, repeat, ..
+            } => Some((*pressed, *repeat)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        repeats,
+        [(true, false), (true, true), (true, true), (false, false)]
+    );
+    let typed = out
+        .iter()
+        .filter(|event| matches!(event, egui::Event::Text(_)))
+        .count();
+    assert_eq!(typed, 3, "every repeat types");
+}
+
+#[test]
+fn composition_is_committed() {
+    let mut ime = ImeTranslator::default();
+    let mut out = Vec::new();
+    ime.set_composing_text("k", &mut out);
+    ime.set_composing_text("ka", &mut out);
+    ime.commit_text("か", &mut out);
+    assert_eq!(
+        out,
+        [
+            egui::Event::Ime(ImeEvent::Enabled),
+            egui::Event::Ime(ImeEvent::Preedit("k".to_owned())),
+            egui::Event::Ime(ImeEvent::Preedit("ka".to_owned())),
+            egui::Event::Ime(ImeEvent::Commit("か".to_owned())),
+        ]
+    );
+
+    // Without a composition, committed text is plain typing
+    out.clear();
+    ime.commit_text("x", &mut out);
+    ime.commit_text("", &mut out);
+    assert_eq!(out, [egui::Event::Text("x".to_owned())]);
+}
+
+#[test]
+fn newline_commit_presses_enter() {
+    let enter = |pressed| key(Key::Enter, pressed, false, Modifiers::NONE);
+    let mut ime = ImeTranslator::default();
+    let mut out = Vec::new();
+    ime.commit_text("\n", &mut out);
+    assert_eq!(out, [enter(true), enter(false)]);
+
+    out.clear();
+    ime.set_composing_text("word", &mut out);
+    out.clear();
+    ime.commit_text("\n", &mut out);
+    assert_eq!(
+        out,
+        [
+            egui::Event::Ime(ImeEvent::Disabled),
+            enter(true),
+            enter(false)
+        ]
+    );
+}
+
+#[test]
+fn finishing_keeps_the_composition() {
+    let mut ime = ImeTranslator::default();
+    let mut out = Vec::new();
+    ime.finish_composing_text(&mut out);
+    assert!(out.is_empty(), "nothing to finish");
+
+    ime.set_composing_text("draft", &mut out);
+    out.clear();
+    ime.finish_composing_text(&mut out);
+    ime.finish_composing_text(&mut out);
+    assert_eq!(
+        out,
+        [egui::Event::Ime(ImeEvent::Commit("draft".to_owned()))]
+    );
+}
+
+/// Runs one frame, with a focused text field if `focus` is set, and passes its output to
+/// `state`.
+fn frame(
+    ctx: &egui::Context,
+    state: &mut SoftKeyboardState,
+    keyboard: &mut RecordingSoftKeyboard,
+    focus: bool,
+    input_type: Option<TextInputType>,
+) {
+    let mut text = String::new();
+    let output = ctx.run(Default::default(), |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if !focus {
+                return;
+            }
+            let response = ui.text_edit_singleline(&mut text);
+            if let Some(input_type) = input_type {
+                keyboard::set_text_input_type(&response, input_type);
+            }
+            response.request_focus();
+        });
+    });
+    state.update(ctx, &output.platform_output, keyboard);
+}
+
+#[test]
+fn keyboard_follows_text_focus() {
+    let ctx = egui::Context::default();
+    let mut state = SoftKeyboardState::default();
+    let mut keyboard = RecordingSoftKeyboard::default();
+
+    frame(&ctx, &mut state, &mut keyboard, false, None);
+    assert!(keyboard.requests.is_empty(), "hidden to begin with");
+
+    for _ in 0..3 {
+        frame(&ctx, &mut state, &mut keyboard, true, None);
+    }
+    assert_eq!(
+        keyboard.requests,
+        [KeyboardRequest::Show(TextInputType::Text)],
+        "only asked once"
+    );
+
+    keyboard.requests.clear();
+    frame(
+        &ctx,
+        &mut state,
+        &mut keyboard,
+        true,
+        Some(TextInputType::Number),
+    );
+    frame(&ctx, &mut state, &mut keyboard, false, None);
+    frame(&ctx, &mut state, &mut keyboard, false, None);
+    assert_eq!(
+        keyboard.requests,
+        [
+            KeyboardRequest::Show(TextInputType::Number),
+            KeyboardRequest::Hide
+        ]
+    );
+}
+
+/// A focused text field without an input type hint.
+struct FieldApp {
+    text: String,
+    password: bool,
+    multiline: bool,
+}
+
+impl SurfaceApp for FieldApp {
+    fn update(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let edit = if self.multiline {
+                egui::TextEdit::multiline(&mut self.text)
+            } else {
+                egui::TextEdit::singleline(&mut self.text)
+            };
+            ui.add(edit.password(self.password)).request_focus();
+        });
+    }
+}
+
+#[test]
+fn keyboard_is_detected_without_a_hint() {
+    let cases = [
+        ("plain", false, false, TextInputType::Text),
+        ("password", true, false, TextInputType::Password),
+        ("multiline", false, true, TextInputType::Multiline),
+    ];
+    for (name, password, multiline, expected) in cases {
+        let mut surface = RustSurface::new(Box::new(FieldApp {
+            text: String::new(),
+            password,
+            multiline,
+        }));
+        let mut state = SoftKeyboardState::default();
+        let mut keyboard = RecordingSoftKeyboard::default();
+        for _ in 0..3 {
+            let output = surface.frame(FrameInput {
+                size_px: [720, 1280],
+                display: DisplayConfig::new(2.0, 1.0, false),
+                ..Default::default()
+            });
+            assert!(
+                output.platform_output.accesskit_update.is_none(),
+                "{name}: no tree without an accessibility service"
+            );
+            state.update(&surface.egui_ctx, &output.platform_output, &mut keyboard);
+        }
+        assert_eq!(
+            keyboard.requests,
+            [KeyboardRequest::Show(expected)],
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn input_types_map_to_android_flags() {
+    assert_eq!(TextInputType::Text.to_android(), 0x1);
+    assert_eq!(TextInputType::Password.to_android(), 0x81);
+    assert_eq!(TextInputType::Number.to_android(), 0x2);
+    assert_eq!(TextInputType::Multiline.to_android(), 0x20001);
+}
@@ -0,0 +1,149 @@
+// The table points at every entry point, and some of those call EGL directly
+use khronos_egl as _;
+use native_gl_surface::natives::{
+    self, NativeClass, NativeClasses, ACCESSIBILITY_NODE_PROPERTY, NATIVES_VERSION,
+    RENDERER_PROPERTY, SURFACE_VIEW_PROPERTY,
+};
+
+const KOTLIN: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../app/src/main/java/com/foxhunter/egui_view/ui/NativeGLSurfaceView.kt"
+));
+
+/// JNI descriptor of a Kotlin parameter or return type.
+fn descriptor(kotlin_type: &str, classes: &NativeClasses) -> String {
+    let descriptor = match kotlin_type.trim().trim_end_matches('?') {
+        "" => "V",
+        "Long" => "J",
+        "Int" => "I",
+        "Float" => "F",
+        "Boolean" => "Z",
+        "String" => "Ljava/lang/String;",
+        "ByteArray" => "[B",
+        "IntArray" => "[I",
+        "LongArray" => "[J",
+        "FloatArray" => "[F",
+        "Array<String>" => "[Ljava/lang/String;",
+        "NativeGLSurfaceView" => return format!("L{};", classes.surface_view),
+        "AccessibilityNode" => return format!("L{};", classes.accessibility_node),
+        other => panic!("no descriptor for Kotlin type {other:?}"),
+    };
+    descriptor.to_owned()
+}
+
+/// Every `external fun` in the Kotlin file, with its class and JNI signature.
+fn kotlin_externals(classes: &NativeClasses) -> Vec<(NativeClass, String, String)> {
+    let renderer_start = KOTLIN
+        .find("class NativeGLRenderer")
+        .expect("renderer class");
+    let mut externals = Vec::new();
+    for (start, _) in KOTLIN.match_indices("external fun ") {
+        let rest = &KOTLIN[start + "external fun ".len()..];
+        let open = rest.find('(').unwrap();
+        let close = rest.find(')').unwrap();
+        let name = rest[..open].to_owned();
+        let params: String = rest[open + 1..close]
+            .split(',')
+            .filter(|param| !param.trim().is_empty())
+            .map(|param| descriptor(param.split(':').nth(1).unwrap(), classes))
+            .collect();
+        let line_end = rest[close..].find('\n').unwrap() + close;
+        let returns = rest[close + 1..line_end].trim().trim_start_matches(':');
+        let class = if start > renderer_start {
+            NativeClass::Renderer
+        } else {
+            NativeClass::SurfaceView
+        };
+        externals.push((
+            class,
+            name,
+            format!("({params}){}", descriptor(returns, classes)),
+        ));
+    }
+    externals
+}
+
+fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+    items.sort();
+    items
+}
+
+#[test]
+fn table_matches_the_kotlin_externals() {
+    let classes = NativeClasses::built_in();
+    let table: Vec<(NativeClass, String, String)> = natives::methods(&classes)
+        .into_iter()
+        .map(|method| (method.class, method.name.to_owned(), method.sig))
+        .collect();
+    let kotlin = kotlin_externals(&classes);
+    assert!(kotlin.len() > 30, "found {} externals", kotlin.len());
+
+    let key = |(class, name, sig): &(NativeClass, String, String)| {
+        (
+            matches!(class, NativeClass::Renderer),
+            name.clone(),
+            sig.clone(),
+        )
+    };
+    assert_eq!(
+        sorted(table.iter().map(key).collect()),
+        sorted(kotlin.iter().map(key).collect())
+    );
+}
+
+#[test]
+fn signatures_follow_the_configured_classes() {
+    let classes = NativeClasses {
+        surface_view: natives::class_name("org.example.EguiView"),
+        renderer: "org/example/EguiRenderer".to_owned(),
+        accessibility_node: natives::class_name("org.example.EguiNode"),
+    };
+    assert_eq!(classes.surface_view, "org/example/EguiView");
+
+    let methods = natives::methods(&classes);
+    let sig = |name: &str| {
+        methods
+            .iter()
+            .find(|method| method.name == name)
+            .map(|method| method.sig.clone())
+            .unwrap()
+    };
+    assert_eq!(
+        sig("createNativeSurface0"),
+        "(Lorg/example/EguiView;Ljava/lang/String;FFZ)J"
+    );
+    assert_eq!(sig("getAccessibilityNode0"), "(JI)Lorg/example/EguiNode;");
+    assert!(methods
+        .iter()
+        .all(|method| !method.sig.contains("com/foxhunter")));
+}
+
+#[test]
+fn kotlin_declares_the_same_version_and_properties() {
+    assert!(
+        KOTLIN.contains(&format!("const val NATIVES_VERSION = {NATIVES_VERSION}\n")),
+        "NATIVES_VERSION differs between Rust and Kotlin"
+    );
+    for property in [
+        SURFACE_VIEW_PROPERTY,
+        RENDERER_PROPERTY,
+        ACCESSIBILITY_NODE_PROPERTY,
+    ] {
+        assert!(
+            KOTLIN.contains(&format!("System.setProperty(\"{property}\"")),
+            "Kotlin doesn't set {property}"
+        );
+    }
+}
+
+#[test]
+fn built_in_classes_default_to_this_repo() {
+    let classes = NativeClasses::built_in();
+    if option_env!("EGUI_VIEW_SURFACE_VIEW_CLASS").is_none() {
+        assert_eq!(
+            classes.surface_view,
+            "com/foxhunter/egui_view/ui/NativeGLSurfaceView"
+        );
+    }
+    assert!(!classes.renderer.contains('.'));
+}
@@ -0,0 +1,56 @@
+use native_gl_surface::{
+    handles::{HandleError, HandleRegistry},
+    panics,
+};
+
+#[test]
+fn catch_passes_results_through() {
+    assert_eq!(panics::catch(|| 42), Ok(42));
+}
+
+#[test]
+fn catch_reports_message_and_location() {
+    panics::install_hook();
+    let line = line!() + 1;
+    let report = panics::catch(|| panic!("lost the {}", "painter")).unwrap_err();
+    assert_eq!(report.message, "lost the painter");
+    let location = report.location.as_deref().expect("location");
+    assert!(
+        location.starts_with(&format!("tests/panics.rs:{line}:")),
+        "{location}"
+    );
+    assert!(report
+        .to_string()
+        .contains("lost the painter at tests/panics.rs"));
+}
+
+#[test]
+fn location_is_not_reused() {
+    panics::install_hook();
+    let _ = panics::catch(|| panic!("first"));
+    let report = panics::catch(|| std::panic::resume_unwind(Box::new("quiet"))).unwrap_err();
+    // resume_unwind skips the hook
+    assert_eq!(report.message, "quiet");
+    assert_eq!(report.location, None);
+}
+
+#[test]
+fn poisoned_handles_are_refused_until_removed() {
+    let mut registry = HandleRegistry::new();
+    let handle = registry.insert("surface");
+    let other = registry.insert("other");
+
+    registry.poison(handle).unwrap();
+    assert!(registry.is_poisoned(handle));
+    assert_eq!(registry.get(handle).unwrap_err(), HandleError::Poisoned);
+    assert_eq!(*registry.get(other).unwrap(), "other");
+
+    assert_eq!(*registry.remove(handle).unwrap(), "surface");
+    assert_eq!(registry.get(handle).unwrap_err(), HandleError::Stale);
+    assert_eq!(registry.poison(handle), Err(HandleError::Stale));
+
+    // The slot is reused without the poison
+    let reused = registry.insert("new");
+    assert!(!registry.is_poisoned(reused));
+    assert_eq!(*registry.get(reused).unwrap(), "new");
+}
@@ -0,0 +1,135 @@
+use native_gl_surface::{
+    app::SurfaceApp,
+    demo_app::{AppState, SavedAppState},
+    persistence::{self, PersistError, SavedState, FORMAT_VERSION},
+};
+
+fn saved_demo(app: &mut AppState) -> SavedAppState {
+    let bytes = app.save().expect("demo app saves its state");
+    ron::de::from_bytes(&bytes).unwrap()
+}
+
+#[test]
+fn round_trips_app_and_memory() {
+    let ctx = egui::Context::default();
+    let id = egui::Id::new("collapsed");
+    ctx.data_mut(|d| d.insert_persisted(id, 42_u32));
+
+    let mut app = AppState::new();
+    app.load(
+        ron::to_string(&SavedAppState {
+            name: "Alice".to_owned(),
+            age: 30,
+            clear_color: [0.1, 0.2, 0.3],
+        })
+        .unwrap()
+        .as_bytes(),
+    );
+
+    let bytes = SavedState::capture(&ctx, &mut app).to_bytes().unwrap();
+
+    let restored_ctx = egui::Context::default();
+    let mut restored_app = AppState::new();
+    SavedState::from_bytes(&bytes)
+        .unwrap()
+        .restore(&restored_ctx, &mut restored_app);
+
+    assert_eq!(
+        restored_ctx.data_mut(|d| d.get_persisted::<u32>(id)),
+        Some(42)
+    );
+    assert_eq!(saved_demo(&mut restored_app), saved_demo(&mut app));
+    assert_eq!(
+        restored_app.clear_color(&egui::Visuals::dark()),
+        [0.1, 0.2, 0.3, 1.0]
+    );
+}
+
+#[test]
+fn empty_state_round_trips() {
+    let bytes = SavedState::default().to_bytes().unwrap();
+    let state = SavedState::from_bytes(&bytes).unwrap();
+    assert!(state.app.is_none());
+    assert!(state.memory.is_none());
+}
+
+#[test]
+fn missing_fields_use_defaults() {
+    // An envelope that only has the `app` field, as a minimal writer would produce
+    let mut bytes = b"EGST".to_vec();
+    bytes.extend_from_slice(&1_u16.to_le_bytes());
+    bytes.extend_from_slice(b"(app: Some([1, 2, 3]))");
+
+    let state = SavedState::from_bytes(&bytes).unwrap();
+    assert_eq!(state.app.as_deref(), Some(&[1, 2, 3][..]));
+    assert!(state.memory.is_none());
+}
+
+#[test]
+fn demo_state_missing_fields_uses_defaults() {
+    // Saved before `clear_color` was persisted
+    let mut app = AppState::new();
+    app.load(br#"(name: "Carol", age: 7)"#);
+
+    let saved = saved_demo(&mut app);
+    assert_eq!(saved.name, "Carol");
+    assert_eq!(saved.age, 7);
+    assert_eq!(saved.clear_color, SavedAppState::default().clear_color);
+}
+
+#[test]
+fn demo_state_ignores_garbage() {
+    let mut app = AppState::new();
+    let before = saved_demo(&mut app);
+    app.load(b"not ron at all");
+    assert_eq!(saved_demo(&mut app), before);
+}
+
+#[test]
+fn rejects_newer_versions() {
+    let mut bytes = SavedState::default().to_bytes().unwrap();
+    bytes[4..6].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    assert!(matches!(
+        SavedState::from_bytes(&bytes),
+        Err(PersistError::UnsupportedVersion(version)) if version == FORMAT_VERSION + 1
+    ));
+}
+
+#[test]
+fn rejects_foreign_data() {
+    assert!(matches!(
+        SavedState::from_bytes(b""),
+        Err(PersistError::NotSavedState)
+    ));
+    assert!(matches!(
+        SavedState::from_bytes(b"EGST"),
+        Err(PersistError::NotSavedState)
+    ));
+    assert!(matches!(
+        SavedState::from_bytes(b"(app: None)"),
+        Err(PersistError::NotSavedState)
+    ));
+}
+
+#[test]
+fn file_round_trip() {
+    let dir = std::env::temp_dir().join(format!("native_gl_surface-{}", std::process::id()));
+    let path = dir.join("demo.state");
+
+    assert!(persistence::read_file(&path).unwrap().is_none());
+
+    let state = SavedState {
+        app: Some(b"hello".to_vec()),
+        memory: None,
+    };
+    persistence::write_file(&path, &state.to_bytes().unwrap()).unwrap();
+    let read = persistence::read_file(&path).unwrap().unwrap();
+    assert_eq!(read.app.as_deref(), Some(&b"hello"[..]));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn new_demo_saves_the_defaults() {
+    assert_eq!(saved_demo(&mut AppState::new()), SavedAppState::default());
+}
@@ -0,0 +1,216 @@
+mod headless;
+
+use std::sync::Arc;
+
+use headless::{assert_matches_golden, HeadlessGl};
+use native_gl_surface::{
+    app::SurfaceApp,
+    demo_app::AppState,
+    display::DisplayConfig,
+    insets::{Insets, WindowInsets},
+    renderer::{GlowRenderer, RendererError, SurfaceRenderer},
+    screenshot,
+    surface::{FrameInput, RustSurface},
+};
+use parking_lot::Mutex;
+
+/// Enough for egui to settle layout that depends on the previous frame.
+const FRAMES: usize = 3;
+
+fn render_demo(name: &str, input: FrameInput) {
+    let Some(gl) = HeadlessGl::new(input.size_px) else {
+        return;
+    };
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    let image = gl.render(&mut surface, &input, FRAMES);
+    assert_matches_golden(name, &image);
+}
+
+#[test]
+fn demo_mdpi_portrait() {
+    render_demo(
+        "demo_mdpi_portrait",
+        FrameInput {
+            size_px: [360, 640],
+            display: DisplayConfig::new(1.0, 1.0, false),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn demo_xhdpi_portrait() {
+    render_demo(
+        "demo_xhdpi_portrait",
+        FrameInput {
+            size_px: [720, 1280],
+            display: DisplayConfig::new(2.0, 1.0, false),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn demo_hdpi_large_font_dark() {
+    render_demo(
+        "demo_hdpi_large_font_dark",
+        FrameInput {
+            size_px: [540, 960],
+            display: DisplayConfig::new(1.5, 1.3, true),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn demo_mdpi_landscape_with_insets() {
+    let bars = Insets {
+        top: 24.0,
+        bottom: 48.0,
+        ..Default::default()
+    };
+    render_demo(
+        "demo_mdpi_landscape_with_insets",
+        FrameInput {
+            size_px: [640, 360],
+            display: DisplayConfig::new(1.0, 1.0, false),
+            insets: WindowInsets {
+                system_bars: bars,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+}
+
+/// Draws nothing, so only the clear color is visible.
+struct ClearOnly;
+
+impl SurfaceApp for ClearOnly {
+    fn update(&mut self, _ctx: &egui::Context) {}
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        [0.2, 0.4, 0.6, 1.0]
+    }
+}
+
+#[test]
+fn clear_color_fills_the_surface() {
+    let input = FrameInput {
+        size_px: [64, 48],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        ..Default::default()
+    };
+    let Some(gl) = HeadlessGl::new(input.size_px) else {
+        return;
+    };
+    let mut surface = RustSurface::new(Box::new(ClearOnly));
+    let image = gl.render(&mut surface, &input, 1);
+
+    for (x, y) in [(0, 0), (63, 0), (0, 47), (63, 47), (32, 24)] {
+        assert_eq!(image.pixel(x, y), [51, 102, 153, 255], "pixel ({x}, {y})");
+    }
+    assert_matches_golden("clear_color", &image);
+}
+
+/// Asks for a screenshot in its first frame.
+#[derive(Default)]
+struct Screenshots {
+    requested: bool,
+    received: Arc<Mutex<Option<Arc<egui::ColorImage>>>>,
+}
+
+impl SurfaceApp for Screenshots {
+    fn update(&mut self, ctx: &egui::Context) {
+        ctx.input(|i| {
+            for event in &i.events {
+                if let egui::Event::Screenshot { image, .. } = event {
+                    *self.received.lock() = Some(Arc::clone(image));
+                }
+            }
+        });
+        if !std::mem::replace(&mut self.requested, true) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+        }
+    }
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        [0.2, 0.4, 0.6, 1.0]
+    }
+}
+
+#[test]
+fn screenshot_matches_the_surface() {
+    let input = FrameInput {
+        size_px: [64, 48],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        ..Default::default()
+    };
+    let Some(gl) = HeadlessGl::new(input.size_px) else {
+        return;
+    };
+    let app = Screenshots::default();
+    let received = Arc::clone(&app.received);
+    let mut surface = RustSurface::new(Box::new(app));
+    let image = gl.render(&mut surface, &input, 2);
+
+    let screenshot = received.lock().clone().expect("screenshot event");
+    assert_eq!(screenshot.size, [64, 48]);
+    assert_eq!(screenshot.as_raw(), &image.pixels[..]);
+}
+
+#[test]
+fn offscreen_image_leaves_the_surface_alone() {
+    let input = FrameInput {
+        size_px: [360, 640],
+        display: DisplayConfig::new(1.0, 1.0, false),
+        ..Default::default()
+    };
+    let Some(gl) = HeadlessGl::new(input.size_px) else {
+        return;
+    };
+    let mut surface = RustSurface::new(Box::new(AppState::new()));
+    let before = gl.render(&mut surface, &input, FRAMES);
+
+    let png = surface
+        .repaint_last_frame_scaled([180, 320])
+        .map(|image| screenshot::encode_png(&image).unwrap())
+        .expect("offscreen image");
+    let decoder = png::Decoder::new(png.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (180, 320));
+    assert_matches_golden(
+        "demo_offscreen_half",
+        &headless::Image {
+            size: [180, 320],
+            pixels,
+        },
+    );
+
+    assert!(
+        before == gl.render(&mut surface, &input, 0),
+        "surface was drawn over"
+    );
+}
+
+#[test]
+fn renderer_checks_required_extensions() {
+    let Some(gl) = HeadlessGl::new([16, 16]) else {
+        return;
+    };
+    let err = GlowRenderer::new(Arc::clone(&gl.gl), &["GL_FOX_imaginary"])
+        .err()
+        .expect("created without the extension");
+    assert_eq!(
+        err,
+        RendererError::MissingExtension {
+            name: "GL_FOX_imaginary".to_owned()
+        }
+    );
+
+    let mut renderer = GlowRenderer::new(Arc::clone(&gl.gl), &[]).expect("renderer");
+    assert!(renderer.gl_info().unwrap().supports_egui_shaders());
+    renderer.destroy();
+}
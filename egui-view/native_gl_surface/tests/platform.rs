@@ -0,0 +1,165 @@
+use egui::{output::OutputEvent, CursorIcon, Key, Modifiers, OpenUrl};
+use native_gl_surface::{
+    platform::{self, PlatformOutputHandler},
+    testing::RecordingPlatformHost,
+};
+
+fn key(key: Key, pressed: bool, modifiers: Modifiers) -> egui::Event {
+    egui::Event::Key {
+        key,
+        physical_key: None,
+        pressed,
+        repeat: false,
+        modifiers,
+    }
+}
+
+fn with_clipboard_events(
+    host: &mut RecordingPlatformHost,
+    events: Vec<egui::Event>,
+) -> Vec<egui::Event> {
+    let mut events = events;
+    platform::clipboard_events(&mut events, host);
+    events
+}
+
+#[test]
+fn clipboard_shortcuts_become_clipboard_events() {
+    let mut host = RecordingPlatformHost {
+        clipboard: Some("pasted".to_owned()),
+        ..Default::default()
+    };
+    let cases = [
+        (
+            key(Key::C, true, Modifiers::COMMAND),
+            Some(egui::Event::Copy),
+        ),
+        (
+            key(Key::X, true, Modifiers::COMMAND),
+            Some(egui::Event::Cut),
+        ),
+        (
+            key(Key::V, true, Modifiers::COMMAND),
+            Some(egui::Event::Paste("pasted".to_owned())),
+        ),
+        (
+            key(Key::Copy, true, Modifiers::NONE),
+            Some(egui::Event::Copy),
+        ),
+        (key(Key::Cut, true, Modifiers::NONE), Some(egui::Event::Cut)),
+        (
+            key(Key::Paste, true, Modifiers::NONE),
+            Some(egui::Event::Paste("pasted".to_owned())),
+        ),
+        (key(Key::C, true, Modifiers::NONE), None),
+        (key(Key::V, true, Modifiers::SHIFT), None),
+        (key(Key::C, false, Modifiers::COMMAND), None),
+        (key(Key::Paste, false, Modifiers::NONE), None),
+    ];
+    for (event, extra) in cases {
+        let mut expected = vec![event.clone()];
+        expected.extend(extra);
+        assert_eq!(
+            with_clipboard_events(&mut host, vec![event.clone()]),
+            expected,
+            "{event:?}"
+        );
+    }
+}
+
+#[test]
+fn clipboard_events_follow_their_key() {
+    let mut host = RecordingPlatformHost {
+        clipboard: Some("b".to_owned()),
+        ..Default::default()
+    };
+    let text = |text: &str| egui::Event::Text(text.to_owned());
+    let paste = key(Key::Paste, true, Modifiers::NONE);
+    let copy = key(Key::C, true, Modifiers::COMMAND);
+    assert_eq!(
+        with_clipboard_events(
+            &mut host,
+            vec![text("a"), paste.clone(), text("c"), copy.clone()]
+        ),
+        [
+            text("a"),
+            paste,
+            egui::Event::Paste("b".to_owned()),
+            text("c"),
+            copy,
+            egui::Event::Copy,
+        ]
+    );
+}
+
+#[test]
+fn paste_with_an_empty_clipboard_does_nothing() {
+    let mut host = RecordingPlatformHost::default();
+    let paste = key(Key::V, true, Modifiers::COMMAND);
+    assert_eq!(
+        with_clipboard_events(&mut host, vec![paste.clone()]),
+        [paste]
+    );
+}
+
+#[test]
+fn copy_and_links_reach_the_host() {
+    let ctx = egui::Context::default();
+    let mut handler = PlatformOutputHandler::default();
+    let mut host = RecordingPlatformHost::default();
+
+    let output = ctx.run(Default::default(), |ctx| {
+        ctx.copy_text("copied".to_owned());
+        ctx.open_url(OpenUrl::new_tab("https://example.com"));
+    });
+    handler.handle(&output.platform_output, &mut host);
+    assert_eq!(host.copied, ["copied"]);
+    assert_eq!(host.clipboard.as_deref(), Some("copied"), "pastes see it");
+    let opened: Vec<(&str, bool)> = host
+        .opened_urls
+        .iter()
+        .map(|open_url| (open_url.url.as_str(), open_url.new_tab))
+        .collect();
+    assert_eq!(opened, [("https://example.com", true)]);
+
+    // Nothing new next frame
+    let output = ctx.run(Default::default(), |_| {});
+    handler.handle(&output.platform_output, &mut host);
+    assert_eq!(host.copied.len(), 1);
+    assert_eq!(host.opened_urls.len(), 1);
+}
+
+#[test]
+fn cursor_icon_is_only_sent_when_it_changes() {
+    let mut handler = PlatformOutputHandler::default();
+    let mut host = RecordingPlatformHost::default();
+    for icon in [
+        CursorIcon::Default,
+        CursorIcon::Text,
+        CursorIcon::Text,
+        CursorIcon::Default,
+    ] {
+        let output = egui::PlatformOutput {
+            cursor_icon: icon,
+            ..Default::default()
+        };
+        handler.handle(&output, &mut host);
+    }
+    assert_eq!(host.cursor_icons, [CursorIcon::Text, CursorIcon::Default]);
+}
+
+#[test]
+fn widget_events_are_forwarded() {
+    let mut handler = PlatformOutputHandler::default();
+    let mut host = RecordingPlatformHost::default();
+    let info = egui::WidgetInfo::labeled(egui::WidgetType::Button, true, "Press");
+    let output = egui::PlatformOutput {
+        events: vec![
+            OutputEvent::FocusGained(info.clone()),
+            OutputEvent::Clicked(info),
+        ],
+        ..Default::default()
+    };
+    handler.handle(&output, &mut host);
+    assert_eq!(host.widget_events, output.events);
+}
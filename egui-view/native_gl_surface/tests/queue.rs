@@ -0,0 +1,74 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use native_gl_surface::queue::EventQueue;
+
+const PUSHERS: usize = 8;
+const BATCHES: usize = 500;
+const BATCH_LEN: usize = 3;
+
+/// `"{pusher} {batch} {index}"`, so every event says where it came from.
+fn event(pusher: usize, batch: usize, index: usize) -> egui::Event {
+    egui::Event::Text(format!("{pusher} {batch} {index}"))
+}
+
+fn parse(event: &egui::Event) -> [usize; 3] {
+    let egui::Event::Text(text) = event else {
+        panic!("unexpected event {event:?}");
+    };
+    let mut parts = text.split(' ').map(|part| part.parse().unwrap());
+    [(); 3].map(|()| parts.next().unwrap())
+}
+
+#[test]
+fn concurrent_pushes_arrive_whole_and_once() {
+    let queue = Arc::new(EventQueue::default());
+    let running = Arc::new(AtomicUsize::new(PUSHERS));
+    let pushers: Vec<_> = (0..PUSHERS)
+        .map(|pusher| {
+            let queue = Arc::clone(&queue);
+            let running = Arc::clone(&running);
+            std::thread::spawn(move || {
+                for batch in 0..BATCHES {
+                    queue.extend((0..BATCH_LEN).map(|index| event(pusher, batch, index)));
+                }
+                running.fetch_sub(1, Ordering::Release);
+            })
+        })
+        .collect();
+
+    let mut drains = Vec::new();
+    loop {
+        // Read before draining, so the last drain sees everything
+        let done = running.load(Ordering::Acquire) == 0;
+        drains.push(queue.drain());
+        if done {
+            break;
+        }
+    }
+    for pusher in pushers {
+        pusher.join().unwrap();
+    }
+    assert!(queue.drain().is_empty());
+
+    let mut seen = HashSet::new();
+    let mut next_batch = [0; PUSHERS];
+    for drain in &drains {
+        assert_eq!(drain.len() % BATCH_LEN, 0, "batch split across drains");
+        for batch in drain.chunks(BATCH_LEN) {
+            let [pusher, number, _] = parse(&batch[0]);
+            for (index, event) in batch.iter().enumerate() {
+                assert_eq!(parse(event), [pusher, number, index], "batch interleaved");
+                assert!(seen.insert(parse(event)), "{event:?} duplicated");
+            }
+            assert_eq!(number, next_batch[pusher], "batches out of order");
+            next_batch[pusher] += 1;
+        }
+    }
+    assert_eq!(seen.len(), PUSHERS * BATCHES * BATCH_LEN, "events lost");
+}
@@ -0,0 +1,135 @@
+use native_gl_surface::{gl_info::GlInfo, renderer::RendererError};
+
+fn info(version: &str, shading_language_version: &str) -> GlInfo {
+    GlInfo {
+        version: version.to_owned(),
+        shading_language_version: shading_language_version.to_owned(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn glsl_versions_are_parsed() {
+    let es3 = info("OpenGL ES 3.2 Mesa 24.0", "OpenGL ES GLSL ES 3.20");
+    assert!(es3.is_es());
+    assert_eq!(es3.glsl_version(), Some((3, 20)));
+
+    let desktop = info("4.6 (Core Profile) Mesa 24.0", "4.60 NVIDIA");
+    assert!(!desktop.is_es());
+    assert_eq!(desktop.glsl_version(), Some((4, 60)));
+
+    assert_eq!(info("", "").glsl_version(), None);
+    assert_eq!(info("", "version 3").glsl_version(), None);
+}
+
+#[test]
+fn old_or_unknown_glsl_is_unsupported() {
+    assert!(info("OpenGL ES 2.0", "OpenGL ES GLSL ES 1.00").supports_egui_shaders());
+    assert!(info("3.0 Mesa", "1.30").supports_egui_shaders());
+    assert!(!info("2.0 Mesa", "1.10").supports_egui_shaders());
+    assert!(!info("OpenGL ES 2.0", "").supports_egui_shaders());
+}
+
+#[test]
+fn real_version_strings() {
+    let cases = [
+        // Adreno
+        (
+            "OpenGL ES 3.2 V@0502.0 (GIT@2c2b8a2, I3d5e6b8d2e, 1615289513) (Date:03/09/21)",
+            "OpenGL ES GLSL ES 3.20",
+            Some((3, 20)),
+            true,
+        ),
+        // Mali
+        (
+            "OpenGL ES 3.2 v1.r32p1-01eac0.2b5fd8d1ba5bd2b4f9a2b3a8b2d1d1f4",
+            "OpenGL ES GLSL ES 3.20",
+            Some((3, 20)),
+            true,
+        ),
+        // PowerVR
+        (
+            "OpenGL ES 2.0 build 1.9@2166536",
+            "OpenGL ES GLSL ES 1.00 build 1.9@2166536",
+            Some((1, 0)),
+            true,
+        ),
+        // ANGLE
+        (
+            "OpenGL ES 3.0.0 (ANGLE 2.1.0.b4d5e1f4a7b2)",
+            "OpenGL ES GLSL ES 3.00 (ANGLE 2.1.0.b4d5e1f4a7b2)",
+            Some((3, 0)),
+            true,
+        ),
+        (
+            "4.6 (Compatibility Profile) Mesa 23.2.1",
+            "4.60",
+            Some((4, 60)),
+            true,
+        ),
+        (
+            "4.6.0 NVIDIA 535.104.05",
+            "4.60 NVIDIA",
+            Some((4, 60)),
+            true,
+        ),
+        ("2.1 Metal - 83.1", "1.20", Some((1, 20)), true),
+        (
+            "2.0 - Build 8.15.10.2900",
+            "1.10 - Intel",
+            Some((1, 10)),
+            false,
+        ),
+        ("OpenGL ES 3.0", "", None, false),
+    ];
+    for (version, shading_language_version, glsl, supported) in cases {
+        let info = info(version, shading_language_version);
+        assert_eq!(info.glsl_version(), glsl, "{shading_language_version:?}");
+        assert_eq!(info.supports_egui_shaders(), supported, "{version:?}");
+    }
+}
+
+#[test]
+fn extensions_are_looked_up_by_exact_name() {
+    let info = GlInfo {
+        extensions: vec![
+            "GL_EXT_color_buffer_float".to_owned(),
+            "GL_EXT_texture_filter_anisotropic".to_owned(),
+            "GL_OES_EGL_image_external".to_owned(),
+            "GL_OES_EGL_image_external_essl3".to_owned(),
+        ],
+        ..Default::default()
+    };
+    let cases = [
+        ("GL_EXT_color_buffer_float", true),
+        ("GL_OES_EGL_image_external", true),
+        ("GL_OES_EGL_image_external_essl3", true),
+        ("GL_OES_EGL_image", false),
+        ("gl_ext_color_buffer_float", false),
+        ("", false),
+    ];
+    for (name, expected) in cases {
+        assert_eq!(info.has_extension(name), expected, "{name:?}");
+    }
+    assert!(!GlInfo::default().has_extension("GL_EXT_color_buffer_float"));
+}
+
+#[test]
+fn every_error_has_its_own_status_code() {
+    let errors = [
+        RendererError::NoContext,
+        RendererError::UnsupportedGlsl {
+            version: "1.10".to_owned(),
+        },
+        RendererError::ShaderCompile {
+            log: "0:1: syntax error".to_owned(),
+        },
+        RendererError::MissingExtension {
+            name: "GL_OES_EGL_image_external".to_owned(),
+        },
+    ];
+    let codes: Vec<i32> = errors.iter().map(RendererError::status_code).collect();
+    assert_eq!(codes, [1, 2, 3, 4]);
+    assert!(errors[2].to_string().contains("0:1: syntax error"));
+    assert!(errors[3].to_string().contains("GL_OES_EGL_image_external"));
+}
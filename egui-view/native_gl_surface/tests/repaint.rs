@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use native_gl_surface::repaint::RepaintSchedule;
+
+#[test]
+fn delays_become_schedules() {
+    let cases = [
+        (Duration::ZERO, RepaintSchedule::Immediately),
+        (Duration::MAX, RepaintSchedule::Idle),
+        (
+            Duration::from_nanos(1),
+            RepaintSchedule::After(Duration::from_nanos(1)),
+        ),
+        (
+            Duration::from_secs(1),
+            RepaintSchedule::After(Duration::from_secs(1)),
+        ),
+    ];
+    for (delay, expected) in cases {
+        assert_eq!(RepaintSchedule::from_delay(delay), expected, "{delay:?}");
+    }
+}
+
+#[test]
+fn schedules_become_millis() {
+    let cases = [
+        (RepaintSchedule::Immediately, 0),
+        (RepaintSchedule::Idle, -1),
+        (RepaintSchedule::After(Duration::from_nanos(1)), 1),
+        (RepaintSchedule::After(Duration::from_micros(500)), 1),
+        (RepaintSchedule::After(Duration::from_micros(1001)), 2),
+        (RepaintSchedule::After(Duration::from_millis(16)), 16),
+        (RepaintSchedule::After(Duration::MAX), i64::MAX),
+    ];
+    for (schedule, expected) in cases {
+        assert_eq!(schedule.to_millis(), expected, "{schedule:?}");
+    }
+}
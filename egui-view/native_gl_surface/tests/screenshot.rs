@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use egui::{Color32, ColorImage, ViewportCommand};
+use native_gl_surface::{
+    app::SurfaceApp,
+    display::DisplayConfig,
+    screenshot,
+    surface::{FrameInput, RustSurface},
+    testing::{RecordingRenderer, RenderCall},
+};
+use parking_lot::Mutex;
+
+fn input() -> FrameInput {
+    FrameInput {
+        size_px: [400, 600],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        ..Default::default()
+    }
+}
+
+/// Asks for a screenshot while `request` is set, and keeps the ones it gets.
+#[derive(Clone, Default)]
+struct ScreenshotApp {
+    request: Arc<Mutex<bool>>,
+    received: Arc<Mutex<Vec<Arc<ColorImage>>>>,
+}
+
+impl SurfaceApp for ScreenshotApp {
+    fn update(&mut self, ctx: &egui::Context) {
+        ctx.input(|i| {
+            for event in &i.events {
+                if let egui::Event::Screenshot { image, .. } = event {
+                    self.received.lock().push(Arc::clone(image));
+                }
+            }
+        });
+        if std::mem::take(&mut *self.request.lock()) {
+            ctx.send_viewport_cmd(ViewportCommand::Screenshot);
+        }
+        egui::CentralPanel::default().show(ctx, |ui| ui.label("Hello"));
+    }
+}
+
+fn surface_with(app: &ScreenshotApp) -> (RustSurface, RecordingRenderer) {
+    let mut surface = RustSurface::new(Box::new(app.clone()));
+    let renderer = RecordingRenderer::default();
+    surface.set_renderer(Box::new(renderer.clone()));
+    (surface, renderer)
+}
+
+#[test]
+fn screenshot_is_read_after_painting_and_delivered_next_frame() {
+    let app = ScreenshotApp::default();
+    let (mut surface, renderer) = surface_with(&app);
+    *app.request.lock() = true;
+
+    surface.frame(input());
+    let calls = renderer.take_calls();
+    let paint = calls
+        .iter()
+        .position(|call| matches!(call, RenderCall::Paint { .. }))
+        .expect("painted");
+    let read = calls
+        .iter()
+        .position(|call| {
+            matches!(
+                call,
+                RenderCall::ReadPixels {
+                    size_px: [400, 600]
+                }
+            )
+        })
+        .expect("read back");
+    assert!(paint < read);
+    assert!(app.received.lock().is_empty());
+    assert!(surface.egui_ctx.has_requested_repaint());
+
+    surface.frame(input());
+    let received = std::mem::take(&mut *app.received.lock());
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].size, [400, 600]);
+
+    surface.frame(input());
+    assert!(app.received.lock().is_empty());
+    assert!(!renderer
+        .take_calls()
+        .iter()
+        .any(|call| matches!(call, RenderCall::ReadPixels { .. })));
+}
+
+#[test]
+fn screenshot_waits_for_a_renderer() {
+    let app = ScreenshotApp::default();
+    let mut surface = RustSurface::new(Box::new(app.clone()));
+    *app.request.lock() = true;
+    surface.frame(input());
+
+    let renderer = RecordingRenderer::default();
+    surface.set_renderer(Box::new(renderer.clone()));
+    surface.frame(input());
+    surface.frame(input());
+    assert_eq!(app.received.lock().len(), 1);
+}
+
+#[test]
+fn last_frame_is_repainted_scaled_to_fit() {
+    let app = ScreenshotApp::default();
+    let (mut surface, renderer) = surface_with(&app);
+    assert!(
+        surface.repaint_last_frame_scaled([200, 200]).is_none(),
+        "nothing painted"
+    );
+
+    surface.frame(input());
+    renderer.take_calls();
+    let image = surface
+        .repaint_last_frame_scaled([200, 200])
+        .expect("image");
+    assert_eq!(image.size, [200, 200]);
+
+    let calls = renderer.take_calls();
+    let [RenderCall::PaintOffscreen {
+        size_px,
+        pixels_per_point,
+        primitives,
+    }] = &calls[..]
+    else {
+        panic!("expected one offscreen paint");
+    };
+    assert_eq!(*size_px, [200, 200]);
+    // Limited by the height: 600 px down to 200
+    assert_eq!(*pixels_per_point, 2.0 / 3.0);
+    assert!(!primitives.is_empty());
+}
+
+#[test]
+fn png_round_trips() {
+    let mut image = ColorImage::new([3, 2], Color32::from_rgb(10, 20, 30));
+    image[(2, 1)] = Color32::from_rgba_unmultiplied(200, 100, 50, 128);
+
+    let bytes = screenshot::encode_png(&image).unwrap();
+    let decoder = png::Decoder::new(bytes.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+
+    assert_eq!((info.width, info.height), (3, 2));
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(&pixels[..4], [10, 20, 30, 255]);
+    // Stored unmultiplied, give or take rounding
+    let last = &pixels[20..24];
+    for (got, expected) in last.iter().zip([200, 100, 50, 128]) {
+        assert!(got.abs_diff(expected) <= 2, "{last:?}");
+    }
+}
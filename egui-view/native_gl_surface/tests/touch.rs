@@ -0,0 +1,276 @@
+use egui::{pos2, Modifiers, Pos2, TouchDeviceId, TouchId, TouchPhase};
+use native_gl_surface::touch::{
+    MotionAction, MotionBatch, MotionEvent, PointerSample, TouchTranslator,
+};
+
+const DOWN: i32 = 0;
+const UP: i32 = 1;
+const MOVE: i32 = 2;
+const CANCEL: i32 = 3;
+const POINTER_DOWN: i32 = 5;
+const POINTER_UP: i32 = 6;
+const DEVICE: i32 = 4;
+const PIXELS_PER_POINT: f32 = 2.0;
+
+/// A `MotionEvent` without history, with `[x, y]` in physical pixels for each of `pointers`.
+fn event(
+    action: i32,
+    action_index: i32,
+    time_ms: i64,
+    pointers: &[(i32, [f32; 2])],
+) -> MotionEvent {
+    let ids: Vec<i32> = pointers.iter().map(|(id, _)| *id).collect();
+    let samples: Vec<f32> = pointers
+        .iter()
+        .flat_map(|(_, [x, y])| [*x, *y, 0.5])
+        .collect();
+    MotionEvent::from_packed(action, action_index, DEVICE, &ids, &[time_ms], &samples).unwrap()
+}
+
+fn touch(id: u64, phase: TouchPhase, pos: Pos2) -> egui::Event {
+    egui::Event::Touch {
+        device_id: TouchDeviceId(DEVICE as u64),
+        id: TouchId(id),
+        phase,
+        pos,
+        force: Some(0.5),
+    }
+}
+
+fn button(pos: Pos2, pressed: bool) -> egui::Event {
+    egui::Event::PointerButton {
+        pos,
+        button: egui::PointerButton::Primary,
+        pressed,
+        modifiers: Modifiers::NONE,
+    }
+}
+
+fn translate(translator: &mut TouchTranslator, event: MotionEvent) -> Vec<egui::Event> {
+    let mut out = Vec::new();
+    translator.translate(&event, PIXELS_PER_POINT, &mut out);
+    out
+}
+
+#[test]
+fn packed_arrays_are_unpacked() {
+    let event = MotionEvent::from_packed(
+        MOVE,
+        0,
+        DEVICE,
+        &[3, 7],
+        &[10, 20],
+        &[1.0, 2.0, 0.1, 3.0, 4.0, 0.2, 5.0, 6.0, 0.3, 7.0, 8.0, 0.4],
+    )
+    .unwrap();
+    let sample = |id, x, y, pressure| PointerSample { id, x, y, pressure };
+    assert_eq!(
+        event,
+        MotionEvent {
+            action: MotionAction::Move,
+            action_index: 0,
+            device_id: DEVICE,
+            history: vec![MotionBatch {
+                time_ms: 10,
+                pointers: vec![sample(3, 1.0, 2.0, 0.1), sample(7, 3.0, 4.0, 0.2)],
+            }],
+            current: MotionBatch {
+                time_ms: 20,
+                pointers: vec![sample(3, 5.0, 6.0, 0.3), sample(7, 7.0, 8.0, 0.4)],
+            },
+        }
+    );
+    assert_eq!(MotionAction::from_raw(8), MotionAction::Other(8));
+}
+
+/// Name, action index, pointer ids, times and samples.
+type PackedCase<'a> = (&'a str, i32, &'a [i32], &'a [i64], &'a [f32]);
+
+#[test]
+fn malformed_packed_arrays_are_rejected() {
+    let cases: [PackedCase; 7] = [
+        ("no pointers", 0, &[], &[1], &[]),
+        ("no times", 0, &[0], &[], &[]),
+        ("short samples", 0, &[0], &[1], &[1.0, 2.0]),
+        ("long samples", 0, &[0], &[1], &[1.0, 2.0, 3.0, 4.0]),
+        (
+            "samples for fewer times",
+            0,
+            &[0],
+            &[1, 2],
+            &[1.0, 2.0, 3.0],
+        ),
+        (
+            "action index past the pointers",
+            1,
+            &[0],
+            &[1],
+            &[1.0, 2.0, 3.0],
+        ),
+        ("negative action index", -1, &[0], &[1], &[1.0, 2.0, 3.0]),
+    ];
+    for (name, action_index, ids, times, samples) in cases {
+        assert_eq!(
+            MotionEvent::from_packed(DOWN, action_index, DEVICE, ids, times, samples),
+            None,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn one_finger_drives_the_pointer() {
+    let mut translator = TouchTranslator::default();
+    let start = pos2(5.0, 10.0);
+    assert_eq!(
+        translate(&mut translator, event(DOWN, 0, 1, &[(0, [10.0, 20.0])])),
+        [
+            touch(0, TouchPhase::Start, start),
+            egui::Event::PointerMoved(start),
+            button(start, true),
+        ]
+    );
+
+    let moved = pos2(15.0, 10.0);
+    assert_eq!(
+        translate(&mut translator, event(MOVE, 0, 2, &[(0, [30.0, 20.0])])),
+        [
+            touch(0, TouchPhase::Move, moved),
+            egui::Event::PointerMoved(moved)
+        ]
+    );
+
+    assert_eq!(
+        translate(&mut translator, event(UP, 0, 3, &[(0, [30.0, 20.0])])),
+        [
+            touch(0, TouchPhase::End, moved),
+            button(moved, false),
+            egui::Event::PointerGone,
+        ]
+    );
+}
+
+#[test]
+fn only_the_first_finger_drives_the_pointer() {
+    let mut translator = TouchTranslator::default();
+    let first = pos2(1.0, 1.0);
+    let second = pos2(50.0, 50.0);
+    translate(&mut translator, event(DOWN, 0, 1, &[(0, [2.0, 2.0])]));
+
+    let both = [(0, [2.0, 2.0]), (1, [100.0, 100.0])];
+    assert_eq!(
+        translate(&mut translator, event(POINTER_DOWN, 1, 2, &both)),
+        [touch(1, TouchPhase::Start, second)]
+    );
+    assert_eq!(
+        translate(&mut translator, event(MOVE, 0, 3, &both)),
+        [
+            touch(0, TouchPhase::Move, first),
+            egui::Event::PointerMoved(first),
+            touch(1, TouchPhase::Move, second),
+        ]
+    );
+
+    // The first finger lifts; the second one doesn't take over
+    assert_eq!(
+        translate(&mut translator, event(POINTER_UP, 0, 4, &both)),
+        [
+            touch(0, TouchPhase::End, first),
+            button(first, false),
+            egui::Event::PointerGone,
+        ]
+    );
+    let second_only = [(1, [100.0, 100.0])];
+    assert_eq!(
+        translate(&mut translator, event(MOVE, 0, 5, &second_only)),
+        [touch(1, TouchPhase::Move, second)]
+    );
+    assert_eq!(
+        translate(&mut translator, event(UP, 0, 6, &second_only)),
+        [touch(1, TouchPhase::End, second)]
+    );
+
+    // The next gesture starts over
+    assert!(
+        translate(&mut translator, event(DOWN, 0, 7, &second_only)).contains(&button(second, true))
+    );
+}
+
+#[test]
+fn cancel_ends_every_touch() {
+    let mut translator = TouchTranslator::default();
+    let both = [(0, [2.0, 2.0]), (1, [100.0, 100.0])];
+    translate(&mut translator, event(DOWN, 0, 1, &both[..1]));
+    translate(&mut translator, event(POINTER_DOWN, 1, 2, &both));
+
+    assert_eq!(
+        translate(&mut translator, event(CANCEL, 0, 3, &both)),
+        [
+            touch(0, TouchPhase::Cancel, pos2(1.0, 1.0)),
+            touch(1, TouchPhase::Cancel, pos2(50.0, 50.0)),
+            button(pos2(1.0, 1.0), false),
+            egui::Event::PointerGone,
+        ]
+    );
+    // No pointer left to release
+    assert_eq!(
+        translate(&mut translator, event(CANCEL, 0, 4, &both[..1])),
+        [touch(0, TouchPhase::Cancel, pos2(1.0, 1.0))]
+    );
+}
+
+#[test]
+fn cancelled_drag_does_not_stick() {
+    let ctx = egui::Context::default();
+    let mut translator = TouchTranslator::default();
+    for (action, time_ms, down) in [(DOWN, 1, true), (MOVE, 2, true), (CANCEL, 3, false)] {
+        let events = translate(
+            &mut translator,
+            event(action, 0, time_ms, &[(0, [10.0, 20.0])]),
+        );
+        let _ = ctx.run(
+            egui::RawInput {
+                events,
+                ..Default::default()
+            },
+            |_| {},
+        );
+        assert_eq!(ctx.input(|i| i.pointer.primary_down()), down, "{action}");
+    }
+}
+
+#[test]
+fn history_is_replayed_once() {
+    let mut translator = TouchTranslator::default();
+    translate(&mut translator, event(DOWN, 0, 10, &[(0, [0.0, 0.0])]));
+
+    let batched = |times: &[i64]| {
+        let samples: Vec<f32> = times
+            .iter()
+            .flat_map(|&time| [time as f32, 0.0, 2.0])
+            .collect();
+        MotionEvent::from_packed(MOVE, 0, DEVICE, &[0], times, &samples).unwrap()
+    };
+    let positions = |events: Vec<egui::Event>| -> Vec<f32> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                egui::Event::Touch { pos, force, .. } => {
+                    assert_eq!(*force, Some(1.0), "pressure is clamped");
+                    Some(pos.x)
+                }
+                _ => None,
+            })
+            .collect()
+    };
+    assert_eq!(
+        positions(translate(&mut translator, batched(&[8, 12, 14, 16]))),
+        [6.0, 7.0, 8.0],
+        "history from before the down is skipped"
+    );
+    assert_eq!(
+        positions(translate(&mut translator, batched(&[14, 16, 18]))),
+        [9.0],
+        "already seen"
+    );
+}
@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use native_gl_surface::{
+    app::SurfaceApp,
+    display::DisplayConfig,
+    surface::{FrameInput, RustSurface},
+    testing::{RecordingRenderer, RecordingViewportHost, RenderCall, ViewportCall},
+    viewports,
+};
+use parking_lot::Mutex;
+
+fn input() -> FrameInput {
+    FrameInput {
+        size_px: [400, 600],
+        display: DisplayConfig::new(2.0, 1.0, false),
+        ..Default::default()
+    }
+}
+
+fn child_id() -> egui::ViewportId {
+    egui::ViewportId::from_hash_of("child")
+}
+
+/// What a viewport's UI callback saw in its most recent pass.
+#[derive(Clone, Default)]
+struct Seen {
+    class: Option<egui::ViewportClass>,
+    viewport_id: Option<egui::ViewportId>,
+    screen_rect: Option<egui::Rect>,
+    close_requested: bool,
+    text: String,
+}
+
+impl Seen {
+    fn record(&mut self, ctx: &egui::Context, class: egui::ViewportClass) {
+        self.class = Some(class);
+        self.viewport_id = Some(ctx.viewport_id());
+        self.screen_rect = Some(ctx.screen_rect());
+        self.close_requested = ctx.input(|i| i.viewport().close_requested());
+        self.text = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Text(text) => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect()
+        });
+    }
+}
+
+/// Shows one child viewport while `show` is set.
+struct ViewportApp {
+    immediate: bool,
+    show: Arc<Mutex<bool>>,
+    child: Arc<Mutex<Seen>>,
+    root: Arc<Mutex<Seen>>,
+}
+
+impl ViewportApp {
+    fn new(immediate: bool) -> Self {
+        Self {
+            immediate,
+            show: Arc::new(Mutex::new(true)),
+            child: Arc::default(),
+            root: Arc::default(),
+        }
+    }
+}
+
+impl SurfaceApp for ViewportApp {
+    fn update(&mut self, ctx: &egui::Context) {
+        self.root.lock().record(ctx, egui::ViewportClass::Root);
+        if !*self.show.lock() {
+            return;
+        }
+        let builder = egui::ViewportBuilder::default()
+            .with_title("Child")
+            .with_inner_size([100.0, 50.0]);
+        if self.immediate {
+            ctx.show_viewport_immediate(child_id(), builder, |ctx, class| {
+                self.child.lock().record(ctx, class);
+                egui::CentralPanel::default().show(ctx, |ui| ui.label("child"));
+            });
+        } else {
+            let child = Arc::clone(&self.child);
+            ctx.show_viewport_deferred(child_id(), builder, move |ctx, class| {
+                child.lock().record(ctx, class);
+                if class != egui::ViewportClass::Embedded {
+                    egui::CentralPanel::default().show(ctx, |ui| ui.label("child"));
+                }
+            });
+        }
+    }
+}
+
+fn surface_with(
+    app: &ViewportApp,
+    host: Option<RecordingViewportHost>,
+) -> (RustSurface, RecordingRenderer) {
+    let mut surface = RustSurface::new(Box::new(ViewportApp {
+        immediate: app.immediate,
+        show: Arc::clone(&app.show),
+        child: Arc::clone(&app.child),
+        root: Arc::clone(&app.root),
+    }));
+    if let Some(host) = host {
+        surface.set_viewport_host(Box::new(host));
+    }
+    let renderer = RecordingRenderer::default();
+    surface.set_renderer(Box::new(renderer.clone()));
+    (surface, renderer)
+}
+
+#[test]
+fn without_a_host_viewports_are_embedded() {
+    let app = ViewportApp::new(false);
+    let (mut surface, _) = surface_with(&app, None);
+    let output = surface.frame(input());
+
+    assert!(matches!(
+        app.child.lock().class,
+        Some(egui::ViewportClass::Embedded)
+    ));
+    assert_eq!(
+        output.viewport_output.keys().collect::<Vec<_>>(),
+        [&egui::ViewportId::ROOT]
+    );
+    assert!(surface.viewport_ids().is_empty());
+}
+
+#[test]
+fn declined_viewports_fall_back_to_embedded() {
+    let app = ViewportApp::new(false);
+    let host = RecordingViewportHost::default();
+    let (mut surface, _) = surface_with(&app, Some(host.clone()));
+
+    surface.frame(input());
+    assert_eq!(
+        host.take_calls(),
+        [ViewportCall::Open {
+            id: child_id(),
+            title: Some("Child".to_owned()),
+        }]
+    );
+
+    surface.frame(input());
+    assert!(matches!(
+        app.child.lock().class,
+        Some(egui::ViewportClass::Embedded)
+    ));
+    assert!(host.take_calls().is_empty(), "asked the host again");
+    assert!(surface.viewport_ids().is_empty());
+}
+
+#[test]
+fn accepted_deferred_viewport_runs_its_own_pass() {
+    let app = ViewportApp::new(false);
+    let host = RecordingViewportHost::accepting();
+    let (mut surface, _) = surface_with(&app, Some(host.clone()));
+
+    surface.frame(input());
+    assert_eq!(surface.viewport_ids(), [child_id()]);
+    let seen = app.child.lock().clone();
+    assert!(matches!(seen.class, Some(egui::ViewportClass::Deferred)));
+    assert_eq!(seen.viewport_id, Some(child_id()));
+    // No surface yet, so the size the app asked for
+    assert_eq!(seen.screen_rect.unwrap().size(), egui::vec2(100.0, 50.0));
+    assert_eq!(app.root.lock().viewport_id, Some(egui::ViewportId::ROOT));
+    assert!(host
+        .take_calls()
+        .contains(&ViewportCall::FrameReady(child_id())));
+
+    surface.set_viewport_input(
+        child_id(),
+        FrameInput {
+            size_px: [300, 200],
+            display: DisplayConfig::new(1.5, 1.0, false),
+            ..Default::default()
+        },
+    );
+    surface.frame(input());
+    assert_eq!(
+        app.child.lock().screen_rect.unwrap().size(),
+        egui::vec2(200.0, 400.0 / 3.0)
+    );
+}
+
+#[test]
+fn viewport_frame_brings_a_new_renderer_up_to_date() {
+    let app = ViewportApp::new(false);
+    let (mut surface, _) = surface_with(&app, Some(RecordingViewportHost::accepting()));
+    surface.set_viewport_input(child_id(), input());
+    surface.frame(input());
+    surface.set_viewport_input(child_id(), input());
+    surface.frame(input());
+
+    let frame = surface
+        .viewport_frame(child_id(), true)
+        .expect("child frame");
+    assert_eq!(frame.size_px, [400, 600]);
+    assert!(!frame.primitives.is_empty());
+
+    let renderer = RecordingRenderer::default();
+    frame.paint(&mut renderer.clone());
+    let calls = renderer.take_calls();
+    assert!(calls.iter().any(|call| matches!(
+        call,
+        RenderCall::SetTexture { id, .. } if *id == egui::TextureId::default()
+    )));
+    assert!(calls
+        .iter()
+        .any(|call| matches!(call, RenderCall::Paint { .. })));
+
+    // Already up to date
+    let frame = surface.viewport_frame(child_id(), false).unwrap();
+    assert!(frame.textures.is_empty());
+}
+
+#[test]
+fn texture_changes_are_only_kept_for_children_with_a_renderer() {
+    let app = ViewportApp::new(false);
+    let (mut surface, _) = surface_with(&app, Some(RecordingViewportHost::accepting()));
+    for _ in 0..3 {
+        surface.set_viewport_input(child_id(), input());
+        surface.frame(input());
+    }
+    // Nothing took a frame yet, so the font atlas upload wasn't kept
+    let frame = surface.viewport_frame(child_id(), false).unwrap();
+    assert!(frame.textures.is_empty());
+
+    let image = || egui::ColorImage::new([4, 4], egui::Color32::RED);
+    let kept = surface
+        .egui_ctx
+        .load_texture("kept", image(), Default::default());
+    surface.frame(input());
+    let frame = surface.viewport_frame(child_id(), false).unwrap();
+    assert!(frame
+        .textures
+        .iter()
+        .any(|delta| delta.set.iter().any(|(id, _)| *id == kept.id())));
+
+    surface.viewport_renderer_lost(child_id());
+    let _dropped = surface
+        .egui_ctx
+        .load_texture("dropped", image(), Default::default());
+    surface.frame(input());
+    let frame = surface.viewport_frame(child_id(), false).unwrap();
+    assert!(frame.textures.is_empty());
+}
+
+#[test]
+fn input_goes_to_the_viewport_it_was_sent_to() {
+    let app = ViewportApp::new(false);
+    let (mut surface, _) = surface_with(&app, Some(RecordingViewportHost::accepting()));
+    surface.frame(input());
+
+    surface.set_viewport_input(
+        child_id(),
+        FrameInput {
+            events: vec![egui::Event::Text("for the child".to_owned())],
+            ..input()
+        },
+    );
+    surface.frame(FrameInput {
+        events: vec![egui::Event::Text("for the root".to_owned())],
+        ..input()
+    });
+
+    assert_eq!(app.child.lock().text, "for the child");
+    assert_eq!(app.root.lock().text, "for the root");
+}
+
+#[test]
+fn close_request_reaches_the_viewport_once() {
+    let app = ViewportApp::new(false);
+    let (mut surface, _) = surface_with(&app, Some(RecordingViewportHost::accepting()));
+    surface.frame(input());
+
+    surface.request_viewport_close(child_id());
+    surface.frame(input());
+    assert!(app.child.lock().close_requested);
+
+    surface.frame(input());
+    assert!(!app.child.lock().close_requested);
+}
+
+#[test]
+fn hidden_viewport_is_closed() {
+    let app = ViewportApp::new(false);
+    let host = RecordingViewportHost::accepting();
+    let (mut surface, _) = surface_with(&app, Some(host.clone()));
+    surface.frame(input());
+    host.take_calls();
+
+    *app.show.lock() = false;
+    surface.frame(input());
+    assert_eq!(host.take_calls(), [ViewportCall::Close(child_id())]);
+    assert!(surface.viewport_ids().is_empty());
+}
+
+#[test]
+fn immediate_viewport_runs_inside_the_root_frame() {
+    let app = ViewportApp::new(true);
+    let host = RecordingViewportHost::accepting();
+    let (mut surface, _) = surface_with(&app, Some(host.clone()));
+    surface.set_viewport_input(child_id(), input());
+    surface.frame(input());
+    surface.frame(input());
+
+    let seen = app.child.lock().clone();
+    assert!(matches!(seen.class, Some(egui::ViewportClass::Immediate)));
+    assert_eq!(seen.viewport_id, Some(child_id()));
+    assert_eq!(app.root.lock().viewport_id, Some(egui::ViewportId::ROOT));
+    let calls = host.take_calls();
+    assert_eq!(
+        calls
+            .iter()
+            .filter(|call| matches!(call, ViewportCall::Open { .. }))
+            .count(),
+        1
+    );
+    assert!(calls.contains(&ViewportCall::FrameReady(child_id())));
+    assert!(surface.viewport_frame(child_id(), false).is_some());
+}
+
+#[test]
+fn declined_immediate_viewport_falls_back_to_embedded() {
+    let app = ViewportApp::new(true);
+    let (mut surface, _) = surface_with(&app, Some(RecordingViewportHost::default()));
+    surface.frame(input());
+    // Run off screen, so it doesn't draw over the root before it is embedded
+    assert!(matches!(
+        app.child.lock().class,
+        Some(egui::ViewportClass::Immediate)
+    ));
+
+    surface.frame(input());
+    assert!(matches!(
+        app.child.lock().class,
+        Some(egui::ViewportClass::Embedded)
+    ));
+}
+
+/// Uses the helpers, which wrap embedded content in a window.
+struct WindowApp;
+
+impl SurfaceApp for WindowApp {
+    fn update(&mut self, ctx: &egui::Context) {
+        viewports::show_deferred(
+            ctx,
+            child_id(),
+            egui::ViewportBuilder::default().with_title("Helper"),
+            |ui| {
+                ui.label("inside");
+            },
+        );
+    }
+}
+
+#[test]
+fn helpers_show_embedded_viewports_as_windows() {
+    let mut surface = RustSurface::new(Box::new(WindowApp));
+    surface.frame(input());
+    surface.frame(input());
+
+    let window_layer = egui::LayerId::new(egui::Order::Middle, child_id().0);
+    assert!(surface
+        .egui_ctx
+        .memory(|mem| mem.areas().visible_layer_ids().contains(&window_layer)));
+}
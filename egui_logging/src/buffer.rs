@@ -0,0 +1,93 @@
+//! The most recent log records, kept in memory for the console.
+
+use std::{collections::VecDeque, time::Duration};
+
+use log::Level;
+
+/// One log record, already formatted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRecord {
+    /// Counts up across the logger's lifetime, so readers can tell which records are new
+    pub seq: u64,
+    /// Since the logger was created
+    pub time: Duration,
+    pub level: Level,
+    /// Usually the module path
+    pub target: String,
+    pub message: String,
+}
+
+impl LogRecord {
+    /// The record as one line of text, as the console copies it.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{:>9.3} {:<5} {}: {}",
+            self.time.as_secs_f64(),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// Keeps the last `capacity` records, dropping the oldest first.
+pub struct RingBuffer {
+    records: VecDeque<LogRecord>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, time: Duration, level: Level, target: &str, message: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(LogRecord {
+            seq: self.next_seq,
+            time,
+            level,
+            target: target.to_owned(),
+            message,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &LogRecord> {
+        self.records.iter()
+    }
+
+    /// The records with a [`LogRecord::seq`] of at least `seq`, oldest first.
+    pub fn since(&self, seq: u64) -> impl Iterator<Item = &LogRecord> {
+        let skip = self.records.partition_point(|record| record.seq < seq);
+        self.records.iter().skip(skip)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Drops every record. Sequence numbers keep counting.
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
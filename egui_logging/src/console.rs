@@ -0,0 +1,151 @@
+//! An egui window for reading the logs on the device.
+
+use egui::{Color32, RichText};
+use log::{Level, LevelFilter};
+
+use crate::{LogRecord, Logger};
+
+/// Shows the records kept by a [`Logger`], narrowed down by level and text, and lets the user
+/// copy them or change the logger's filters.
+///
+/// Keep one around in the app and call [`Self::show`] every frame; it only draws while
+/// [`Self::open`] is set.
+pub struct LogConsole {
+    pub open: bool,
+    /// Least severe level shown
+    pub level: LevelFilter,
+    /// Only records whose target or message contain this, ignoring case
+    pub search: String,
+    /// Scroll to new records as they come in
+    pub follow: bool,
+    /// The filter spec being edited, until applied
+    filters: Option<String>,
+    filters_error: Option<String>,
+}
+
+impl Default for LogConsole {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: LevelFilter::Trace,
+            search: String::new(),
+            follow: true,
+            filters: None,
+            filters_error: None,
+        }
+    }
+}
+
+impl LogConsole {
+    /// Shows the console as a window, while [`Self::open`] is set.
+    pub fn show(&mut self, ctx: &egui::Context, logger: &Logger) {
+        let mut open = self.open;
+        egui::Window::new("Logs")
+            .open(&mut open)
+            .default_size([480.0, 320.0])
+            .show(ctx, |ui| self.ui(ui, logger));
+        self.open = open;
+    }
+
+    /// Shows the console inside `ui`, for apps that want it somewhere other than a window.
+    pub fn ui(&mut self, ui: &mut egui::Ui, logger: &Logger) {
+        let records = logger.records();
+        let visible = self.visible(&records);
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("log_console_level")
+                .selected_text(self.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in LevelFilter::iter().skip(1) {
+                        ui.selectable_value(&mut self.level, level, level.as_str());
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.search)
+                    .hint_text("Search")
+                    .desired_width(120.0),
+            );
+            ui.checkbox(&mut self.follow, "Follow");
+            if ui.button("Copy").clicked() {
+                ui.ctx().copy_text(lines(&visible));
+            }
+            if ui.button("Clear").clicked() {
+                logger.clear();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Filters");
+            let filters = self.filters.get_or_insert_with(|| logger.filters());
+            let response = ui.add(
+                egui::TextEdit::singleline(filters)
+                    .hint_text("info,my_crate::module=trace")
+                    .desired_width(200.0),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Apply").clicked() || submitted {
+                match logger.set_filters(filters) {
+                    Ok(()) => {
+                        self.filters = None;
+                        self.filters_error = None;
+                    }
+                    Err(err) => self.filters_error = Some(err.to_string()),
+                }
+            }
+        });
+        if let Some(err) = &self.filters_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+
+        ui.separator();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::both()
+            .auto_shrink(false)
+            .stick_to_bottom(self.follow)
+            .show_rows(ui, row_height, visible.len(), |ui, rows| {
+                for record in &visible[rows] {
+                    let text = RichText::new(record.to_line())
+                        .monospace()
+                        .color(level_color(ui.visuals(), record.level));
+                    ui.add(egui::Label::new(text).extend());
+                }
+            });
+    }
+
+    /// The records that pass the console's level and search filters.
+    pub fn visible<'a>(&self, records: &'a [LogRecord]) -> Vec<&'a LogRecord> {
+        let search = self.search.to_lowercase();
+        records
+            .iter()
+            .filter(|record| record.level <= self.level)
+            .filter(|record| {
+                search.is_empty()
+                    || record.target.to_lowercase().contains(&search)
+                    || record.message.to_lowercase().contains(&search)
+            })
+            .collect()
+    }
+
+    /// What the Copy button puts on the clipboard.
+    pub fn copy_text(&self, logger: &Logger) -> String {
+        lines(&self.visible(&logger.records()))
+    }
+}
+
+fn lines(records: &[&LogRecord]) -> String {
+    let mut text = String::new();
+    for record in records {
+        text.push_str(&record.to_line());
+        text.push('\n');
+    }
+    text
+}
+
+fn level_color(visuals: &egui::Visuals, level: Level) -> Color32 {
+    match level {
+        Level::Error => visuals.error_fg_color,
+        Level::Warn => visuals.warn_fg_color,
+        Level::Info => visuals.text_color(),
+        Level::Debug | Level::Trace => visuals.weak_text_color(),
+    }
+}
@@ -0,0 +1,15 @@
+//! Logging shared by the egui hosts in this repository.
+//!
+//! [`init`] installs a [`Logger`] that sends records to logcat, keeps the most recent ones in
+//! memory and filters them by module with a spec that can be changed while the app runs. A
+//! [`LogConsole`] shows the kept records in an egui window, so they can be read and copied on
+//! the device.
+
+mod buffer;
+mod console;
+mod logger;
+
+pub use buffer::{LogRecord, RingBuffer};
+pub use console::LogConsole;
+pub use env_filter::ParseError;
+pub use logger::{init, logger, LogConfig, Logger};
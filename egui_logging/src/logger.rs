@@ -0,0 +1,183 @@
+//! The [`log`] backend, and the process-wide instance of it.
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use android_logger::AndroidLogger;
+use env_filter::{Filter, ParseError};
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::{Mutex, RwLock};
+
+use crate::buffer::{LogRecord, RingBuffer};
+
+/// How [`init`] sets up the logger.
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    /// The logcat tag
+    pub tag: String,
+    /// `RUST_LOG`-style filters, e.g. `info,native_gl_surface::touch=trace`
+    pub filters: String,
+    /// How many records to keep for the console
+    pub capacity: usize,
+}
+
+impl LogConfig {
+    pub fn new(tag: impl Into<String>) -> Self {
+        Self {
+            tag: tag.into(),
+            filters: "info".to_owned(),
+            capacity: 1000,
+        }
+    }
+
+    pub fn with_filters(mut self, filters: impl Into<String>) -> Self {
+        self.filters = filters.into();
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+struct ActiveFilter {
+    spec: String,
+    filter: Filter,
+}
+
+impl ActiveFilter {
+    fn parse(spec: &str) -> Result<Self, ParseError> {
+        let filter = env_filter::Builder::new().try_parse(spec)?.build();
+        Ok(Self {
+            spec: spec.to_owned(),
+            filter,
+        })
+    }
+}
+
+/// Filters records by module, writes the rest to logcat and keeps the most recent ones.
+pub struct Logger {
+    filter: RwLock<ActiveFilter>,
+    records: Mutex<RingBuffer>,
+    output: AndroidLogger,
+    start: Instant,
+}
+
+impl Logger {
+    /// Fails if `config.filters` doesn't parse.
+    pub fn new(config: LogConfig) -> Result<Self, ParseError> {
+        let filter = ActiveFilter::parse(&config.filters)?;
+        let output = android_logger::Config::default()
+            .with_tag(config.tag)
+            // Filtering already happened by the time a record gets there
+            .with_max_level(LevelFilter::Trace)
+            .format(|f, record| write!(f, "{}: {}", record.target(), record.args()));
+        Ok(Self {
+            filter: RwLock::new(filter),
+            records: Mutex::new(RingBuffer::new(config.capacity)),
+            output: AndroidLogger::new(output),
+            start: Instant::now(),
+        })
+    }
+
+    /// Replaces the filters. On failure the old ones stay in place.
+    pub fn set_filters(&self, spec: &str) -> Result<(), ParseError> {
+        let filter = ActiveFilter::parse(spec)?;
+        let max_level = filter.filter.filter();
+        *self.filter.write() = filter;
+        if logger().is_some_and(|installed| std::ptr::eq(installed, self)) {
+            log::set_max_level(max_level);
+        }
+        Ok(())
+    }
+
+    /// The spec last passed to [`Self::set_filters`] or [`LogConfig::filters`].
+    pub fn filters(&self) -> String {
+        self.filter.read().spec.clone()
+    }
+
+    /// The most verbose level any module logs at.
+    pub fn max_level(&self) -> LevelFilter {
+        self.filter.read().filter.filter()
+    }
+
+    /// The kept records, oldest first.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.lock().iter().cloned().collect()
+    }
+
+    /// The kept records with a [`LogRecord::seq`] of at least `seq`, oldest first.
+    pub fn records_since(&self, seq: u64) -> Vec<LogRecord> {
+        self.records.lock().since(seq).cloned().collect()
+    }
+
+    /// Like [`Self::records`], but gives up after `timeout` instead of waiting on a thread that may
+    /// never let go of the buffer, e.g. one that panicked halfway through logging.
+    pub fn try_records(&self, timeout: Duration) -> Option<Vec<LogRecord>> {
+        let records = self.records.try_lock_for(timeout)?;
+        Some(records.iter().cloned().collect())
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().clear();
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.read().filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.read().filter.matches(record) {
+            return;
+        }
+        // Formatting may run code that logs or panics, so it must not happen under the lock, and
+        // only happens once
+        let time = self.start.elapsed();
+        let message = record.args().to_string();
+        self.output.log(
+            &Record::builder()
+                .metadata(record.metadata().clone())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .args(format_args!("{message}"))
+                .build(),
+        );
+        self.records
+            .lock()
+            .push(time, record.level(), record.target(), message);
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Installs the process-wide logger, unless one was installed already. Falls back to `info` if
+/// `config.filters` doesn't parse.
+pub fn init(config: LogConfig) -> &'static Logger {
+    let mut error = None;
+    let logger = LOGGER.get_or_init(|| {
+        Logger::new(config.clone()).unwrap_or_else(|err| {
+            error = Some(err);
+            Logger::new(config.with_filters("info")).expect("default filters")
+        })
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.max_level());
+    }
+    if let Some(err) = error {
+        log::warn!("Ignoring log filters: {err}");
+    }
+    logger
+}
+
+/// The logger installed by [`init`], if any.
+pub fn logger() -> Option<&'static Logger> {
+    LOGGER.get()
+}
@@ -0,0 +1,22 @@
+use egui_logging::LogConfig;
+use log::LevelFilter;
+
+#[test]
+fn init_installs_one_logger() {
+    assert!(egui_logging::logger().is_none());
+    let logger = egui_logging::init(LogConfig::new("test").with_filters("warn"));
+    assert_eq!(log::max_level(), LevelFilter::Warn);
+
+    log::info!("not kept");
+    log::warn!("kept");
+    let messages: Vec<String> = logger.records().into_iter().map(|r| r.message).collect();
+    assert_eq!(messages, ["kept"]);
+
+    logger.set_filters("debug").unwrap();
+    assert_eq!(log::max_level(), LevelFilter::Debug);
+
+    // Later calls keep the first logger
+    let again = egui_logging::init(LogConfig::new("other").with_filters("error"));
+    assert!(std::ptr::eq(logger, again));
+    assert_eq!(again.filters(), "debug");
+}
@@ -0,0 +1,156 @@
+use egui_logging::{LogConfig, LogConsole, Logger, RingBuffer};
+use log::{Level, LevelFilter, Log, Record};
+
+fn log(logger: &Logger, level: Level, target: &str, message: &str) {
+    logger.log(
+        &Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{message}"))
+            .build(),
+    );
+}
+
+fn messages(logger: &Logger) -> Vec<String> {
+    logger
+        .records()
+        .into_iter()
+        .map(|record| record.message)
+        .collect()
+}
+
+#[test]
+fn filters_apply_per_module() {
+    let logger = Logger::new(LogConfig::new("test").with_filters("info,app::touch=trace")).unwrap();
+    log(&logger, Level::Debug, "app::surface", "dropped");
+    log(&logger, Level::Trace, "app::touch", "kept trace");
+    log(&logger, Level::Warn, "app::surface", "kept warning");
+
+    assert_eq!(messages(&logger), ["kept trace", "kept warning"]);
+    assert_eq!(logger.max_level(), LevelFilter::Trace);
+}
+
+#[test]
+fn filters_change_at_runtime() {
+    let logger = Logger::new(LogConfig::new("test")).unwrap();
+    log(&logger, Level::Debug, "app", "before");
+    logger.set_filters("debug").unwrap();
+    log(&logger, Level::Debug, "app", "after");
+    assert_eq!(messages(&logger), ["after"]);
+    assert_eq!(logger.filters(), "debug");
+
+    assert!(logger.set_filters("app=loud").is_err());
+    assert_eq!(logger.filters(), "debug", "kept the old filters");
+    assert!(Logger::new(LogConfig::new("test").with_filters("app=loud")).is_err());
+}
+
+#[test]
+fn buffer_keeps_the_most_recent_records() {
+    let logger = Logger::new(LogConfig::new("test").with_capacity(3)).unwrap();
+    for i in 0..5 {
+        log(&logger, Level::Info, "app", &i.to_string());
+    }
+    assert_eq!(messages(&logger), ["2", "3", "4"]);
+    let seqs: Vec<u64> = logger.records().iter().map(|record| record.seq).collect();
+    assert_eq!(seqs, [2, 3, 4]);
+
+    let newer: Vec<String> = logger
+        .records_since(4)
+        .into_iter()
+        .map(|record| record.message)
+        .collect();
+    assert_eq!(newer, ["4"]);
+    assert!(logger.records_since(5).is_empty());
+
+    logger.clear();
+    assert!(logger.records().is_empty());
+    log(&logger, Level::Info, "app", "5");
+    assert_eq!(logger.records()[0].seq, 5);
+}
+
+#[test]
+fn empty_buffer_keeps_nothing() {
+    let mut buffer = RingBuffer::new(0);
+    buffer.push(Default::default(), Level::Info, "app", "lost".to_owned());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn console_narrows_by_level_and_text() {
+    let logger = Logger::new(LogConfig::new("test").with_filters("trace")).unwrap();
+    log(&logger, Level::Error, "app::gl", "Shader failed");
+    log(&logger, Level::Debug, "app::gl", "Texture uploaded");
+    log(&logger, Level::Info, "app::touch", "Pointer down");
+
+    let mut console = LogConsole::default();
+    console.level = LevelFilter::Info;
+    let records = logger.records();
+    let visible: Vec<&str> = console
+        .visible(&records)
+        .iter()
+        .map(|record| record.message.as_str())
+        .collect();
+    assert_eq!(visible, ["Shader failed", "Pointer down"]);
+
+    console.search = "GL".to_owned();
+    let text = console.copy_text(&logger);
+    assert_eq!(text.lines().count(), 1);
+    assert!(text.contains("ERROR app::gl: Shader failed"), "{text}");
+}
+
+#[test]
+fn console_window_shows_records() {
+    let logger = Logger::new(LogConfig::new("test")).unwrap();
+    log(&logger, Level::Info, "app", "Hello from the log");
+    let mut console = LogConsole::default();
+    console.open = true;
+
+    let ctx = egui::Context::default();
+    let mut output = Default::default();
+    for _ in 0..2 {
+        output = ctx.run(Default::default(), |ctx| console.show(ctx, &logger));
+    }
+    assert!(console.open);
+    assert!(!output.shapes.is_empty());
+
+    // Give the window time to fade out
+    console.open = false;
+    for _ in 0..30 {
+        output = ctx.run(Default::default(), |ctx| console.show(ctx, &logger));
+    }
+    assert!(output.shapes.is_empty(), "drawn while closed");
+}
+
+#[test]
+fn try_records_reads_the_buffer() {
+    let logger = Logger::new(LogConfig::new("test")).unwrap();
+    log(&logger, Level::Info, "app", "kept");
+    let records = logger
+        .try_records(std::time::Duration::from_millis(10))
+        .expect("buffer is free");
+    assert_eq!(records[0].message, "kept");
+}
+
+/// Logs while being formatted, like a `Display` impl with tracing in it.
+struct Chatty<'a>(&'a Logger);
+
+impl std::fmt::Display for Chatty<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        log(self.0, Level::Info, "app::inner", "from Display");
+        write!(f, "outer")
+    }
+}
+
+#[test]
+fn display_impls_may_log() {
+    let logger = Logger::new(LogConfig::new("test")).unwrap();
+    logger.log(
+        &Record::builder()
+            .level(Level::Info)
+            .target("app")
+            .args(format_args!("{}", Chatty(&logger)))
+            .build(),
+    );
+    // The inner record finished first
+    assert_eq!(messages(&logger), ["from Display", "outer"]);
+}
@@ -1,9 +1,12 @@
 use android_activity::AndroidApp;
 use eframe::NativeOptions;
+use egui_logging::{LogConfig, LogConsole};
 use winit::platform::android::EventLoopBuilderExtAndroid;
 
 #[no_mangle]
 fn android_main(app: AndroidApp) -> eframe::Result {
+    egui_logging::init(LogConfig::new("com.foxhunter.egui_demo"));
+
     let options = NativeOptions {
         event_loop_builder: Some(Box::new(move |builder| {
             builder.with_android_app(app);
@@ -25,6 +28,7 @@ fn android_main(app: AndroidApp) -> eframe::Result {
 struct MyApp {
     name: String,
     age: u32,
+    log_console: LogConsole,
 }
 
 impl Default for MyApp {
@@ -32,6 +36,7 @@ impl Default for MyApp {
         Self {
             name: "Arthur".to_owned(),
             age: 42,
+            log_console: LogConsole::default(),
         }
     }
 }
@@ -50,10 +55,15 @@ impl eframe::App for MyApp {
                 self.age += 1;
             }
             ui.label(format!("Hello '{}', age {}", self.name, self.age));
+            ui.toggle_value(&mut self.log_console.open, "Logs");
 
             // ui.image(egui::include_image!(
             //     "../../../crates/egui/assets/ferris.png"
             // ));
         });
+
+        if let Some(logger) = egui_logging::logger() {
+            self.log_console.show(ctx, logger);
+        }
     }
 }